@@ -0,0 +1,186 @@
+/// Command line split into arguments
+///
+/// Arguments are separated by whitespace, quotes (`"` or `'`) group whitespace into a single
+/// argument and a backslash escapes the next character
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Arguments {
+    pub values: Vec<String>,
+    /// Byte index in the line where each argument starts
+    pub starts: Vec<usize>,
+    /// A quote was opened but never closed
+    pub unterminated_quote: bool,
+    /// The line ends inside an argument rather than after a separator
+    pub ends_in_argument: bool,
+}
+
+impl Arguments {
+    pub fn split(line: &str) -> Self {
+        let mut arguments = Self::default();
+        let mut current: Option<String> = None;
+        let mut quote: Option<char> = None;
+        let mut chars = line.char_indices();
+
+        while let Some((i, ch)) = chars.next() {
+            match (quote, ch) {
+                (Some(open), ch) if ch == open => quote = None,
+                (None, ch) if ch.is_whitespace() => {
+                    if let Some(value) = current.take() {
+                        arguments.values.push(value);
+                    }
+                    continue;
+                }
+                (_, '\\') => {
+                    let escaped = match chars.next() {
+                        Some((_, 'n')) => '\n',
+                        Some((_, 't')) => '\t',
+                        Some((_, escaped)) => escaped,
+                        None => '\\',
+                    };
+                    arguments.start(&mut current, i).push(escaped);
+                    continue;
+                }
+                (None, '"' | '\'') => quote = Some(ch),
+                (_, ch) => {
+                    arguments.start(&mut current, i).push(ch);
+                    continue;
+                }
+            }
+
+            // Quotes start an argument even if nothing is inside them
+            arguments.start(&mut current, i);
+        }
+
+        arguments.ends_in_argument = current.is_some();
+        arguments.unterminated_quote = quote.is_some();
+        arguments.values.extend(current);
+
+        arguments
+    }
+
+    /// Get the argument being built, recording its start if it is a new one
+    fn start<'a>(&mut self, current: &'a mut Option<String>, index: usize) -> &'a mut String {
+        if current.is_none() {
+            self.starts.push(index);
+        }
+
+        current.get_or_insert_with(String::new)
+    }
+}
+
+/// Quote `value` if needed so that [`Arguments::split`] gives it back as a single argument,
+/// `;` is quoted too so that the argument survives [`split_commands`]
+pub fn quote_argument(value: &str) -> String {
+    if !value.is_empty()
+        && !value
+            .chars()
+            .any(|ch| ch.is_whitespace() || matches!(ch, '"' | '\'' | '\\' | ';'))
+    {
+        return value.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    for ch in value.chars() {
+        match ch {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(ch);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+/// Split a line at each `;` that isn't quoted or escaped
+pub fn split_commands(line: &str) -> Vec<&str> {
+    let mut commands = Vec::new();
+    let mut start = 0;
+    let mut quote: Option<char> = None;
+    let mut chars = line.char_indices();
+
+    while let Some((i, ch)) = chars.next() {
+        match (quote, ch) {
+            (_, '\\') => {
+                chars.next();
+            }
+            (Some(open), ch) if ch == open => quote = None,
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, ';') => {
+                commands.push(&line[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    commands.push(&line[start..]);
+
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_quoted_and_escaped_arguments() {
+        let arguments = Arguments::split(r#"open "my file.rs" 'a "b"' c\ d\n "" "#);
+
+        assert_eq!(
+            arguments.values,
+            ["open", "my file.rs", r#"a "b""#, "c d\n", ""]
+        );
+        assert_eq!(arguments.starts, [0, 5, 18, 26, 33]);
+        assert!(!arguments.unterminated_quote);
+        assert!(!arguments.ends_in_argument);
+
+        let arguments = Arguments::split("open a\\");
+        assert_eq!(arguments.values, ["open", "a\\"]);
+        assert!(arguments.ends_in_argument);
+    }
+
+    #[test]
+    fn detects_unterminated_quotes() {
+        let arguments = Arguments::split(r#"open "my fi"#);
+
+        assert_eq!(arguments.values, ["open", "my fi"]);
+        assert!(arguments.unterminated_quote);
+        assert!(arguments.ends_in_argument);
+        assert!(!Arguments::split(r#"open "a\"b""#).unterminated_quote);
+    }
+
+    #[test]
+    fn quoted_arguments_split_back() {
+        for value in [
+            "plain",
+            "",
+            "my file",
+            r#"a "b" \c"#,
+            "a;b",
+            "tab\there\n",
+            "it's",
+        ] {
+            let quoted = quote_argument(value);
+
+            assert_eq!(Arguments::split(&quoted).values, [value]);
+            assert_eq!(split_commands(&quoted), [quoted.as_str()]);
+        }
+
+        assert_eq!(quote_argument("plain"), "plain");
+        assert_eq!(quote_argument("my file"), r#""my file""#);
+    }
+
+    #[test]
+    fn splits_commands_outside_quotes() {
+        assert_eq!(
+            split_commands(r#"a 1; b "x;y" 'z;' \; ;c"#),
+            ["a 1", r#" b "x;y" 'z;' \; "#, "c"]
+        );
+        assert_eq!(split_commands(""), [""]);
+        assert_eq!(split_commands(r#"a "b;c"#), [r#"a "b;c"#]);
+    }
+}
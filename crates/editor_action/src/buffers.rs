@@ -0,0 +1,25 @@
+use std::cell::RefCell;
+
+thread_local! {
+    /// Paths of the files open in the editor, to complete [`crate::BufferName`] arguments
+    ///
+    /// Set by the app when the command bar gets the focus, the buffers don't change while a
+    /// command is being typed
+    static OPEN_BUFFERS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Replace the paths of the open buffers
+pub fn set_open_buffers(paths: Vec<String>) {
+    OPEN_BUFFERS.with_borrow_mut(|buffers| *buffers = paths);
+}
+
+/// Paths of the open buffers starting with `arg`, in the order they were set
+pub(crate) fn complete(arg: &str) -> Vec<String> {
+    OPEN_BUFFERS.with_borrow(|buffers| {
+        buffers
+            .iter()
+            .filter(|path| path.starts_with(arg))
+            .cloned()
+            .collect()
+    })
+}
@@ -1,8 +1,22 @@
+mod arguments;
+mod buffers;
+mod user_commands;
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    path::PathBuf,
+};
+
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 
+pub use arguments::{quote_argument, split_commands, Arguments};
+pub use buffers::set_open_buffers;
+pub use user_commands::register_user_command;
+
 macro_rules! actions {
     ( enum Action { $($content:tt)* } ) => {
-        actions!{ @elements {} {} {} {} {} { parse_string } { fuzzy_vec fuzzy_str } Action $($content)* }
+        actions!{ @elements {} {} {} {} {} {} { parse_string parse_error } { fuzzy_vec fuzzy_str } { complete_args } { infos_vec } Action $($content)* }
     };
 
     (@elements
@@ -10,29 +24,47 @@ macro_rules! actions {
         { $($enum_pile:ident $variant_pile:ident)* }
         { $($current_enum:tt)* }
         { $($as_strs:tt)* }
+        { $($to_command:tt)* }
         { $($is_public:tt)* }
-        { $parse_args:ident $($parse:tt)* }
+        { $parse_args:ident $parse_error:ident $($parse:tt)* }
         { $fuzzy_vec:ident $fuzzy_str:ident $($fuzzy_match:tt)* }
+        { $complete_args:ident $($complete:tt)* }
+        { $infos_vec:ident $($infos:tt)* }
         $enum_name:ident
+        $(#[doc = $doc:literal])*
         $variant:ident => enum $inner_enum_name:ident {
             $($body:tt)*
         }
         $($tail:tt)*
     ) => {
         actions!{@elements
-            { actions!{@elements { $($code)* } {$($enum_pile $variant_pile)* $enum_name $variant} {} {} {} {$parse_args} {$fuzzy_vec $fuzzy_str} $inner_enum_name $($body)* } }
+            { actions!{@elements { $($code)* } {$($enum_pile $variant_pile)* $enum_name $variant} {} {} {} {} {$parse_args $parse_error} {$fuzzy_vec $fuzzy_str} {$complete_args} {$infos_vec} $inner_enum_name $($body)* } }
             { $($enum_pile $variant_pile)* }
-            { $($current_enum)* $variant($inner_enum_name),}
+            { $($current_enum)* $(#[doc = $doc])* $variant($inner_enum_name),}
             { $($as_strs)* Self::$variant(action) => action.as_strs(), }
+            { $($to_command)* Self::$variant(action) => action.to_command(), }
             { $($is_public)* Self::$variant(action) => action.is_public(), }
-            { $parse_args $($parse)* {
-                if let Some(action) = $inner_enum_name::parse_from_args($parse_args) {
-                    return Some(Self::$variant(action));
+            { $parse_args $parse_error $($parse)* {
+                match $inner_enum_name::parse_from_args($parse_args) {
+                    Ok(action) => return Ok(Self::$variant(action)),
+                    Err(ParseError::UnknownCommand(_)) => {}
+                    Err(err) => {
+                        $parse_error.get_or_insert(err);
+                    }
                 }
             } }
             { $fuzzy_vec $fuzzy_str $($fuzzy_match)* {
                 $fuzzy_vec.extend_from_slice(&$inner_enum_name::fuzzy_scores($fuzzy_str));
             } }
+            { $complete_args $($complete)* {
+                let completions = $inner_enum_name::argument_completions($complete_args);
+                if !completions.is_empty() {
+                    return completions;
+                }
+            } }
+            { $infos_vec $($infos)* {
+                $infos_vec.extend($inner_enum_name::action_infos());
+            } }
             $enum_name $($tail)*
         }
     };
@@ -42,36 +74,60 @@ macro_rules! actions {
         { $($enum_pile:ident $variant_pile:ident)* }
         { $($current_enum:tt)* }
         { $($as_strs:tt)* }
+        { $($to_command:tt)* }
         { $($is_public:tt)* }
-        { $parse_args:ident $($parse:tt)* }
+        { $parse_args:ident $parse_error:ident $($parse:tt)* }
         { $fuzzy_vec:ident $fuzzy_str:ident $($fuzzy_match:tt)* }
+        { $complete_args:ident $($complete:tt)* }
+        { $infos_vec:ident $($infos:tt)* }
         $enum_name:ident
+        $(#[doc = $doc:literal])*
         pub $variant:ident $({
-            $($field:ident : $field_ty:ty),*
+            $($(#[doc = $field_doc:literal])* $field:ident : $field_ty:ty $(= $default:expr)?),*
         })? $(,$string:literal)+;
         $($tail:tt)*
     ) => {
         actions!{@elements
             { $($code)* }
             { $($enum_pile $variant_pile)* }
-            { $($current_enum)* $variant $({
-                $($field: $field_ty),*
+            { $($current_enum)* $(#[doc = $doc])* $variant $({
+                $($(#[doc = $field_doc])* $field: $field_ty),*
             })?, }
             { $($as_strs)* Self::$variant $({$($field: _),*})? => &[$($string),*], }
-            { $($is_public)* Self::$variant $({$($field: _)*})? => true,}
-            { $parse_args $($parse)* if matches!($parse_args.get(0), $(Some(&$string))|+) {
+            { $($to_command)* Self::$variant $({$($field),*})? => {
+                #[allow(unused_mut)]
+                let mut command = actions!(@first $($string),*).to_string();
+                $($(
+                    let argument = ArgumentParse::to_argument($field);
+                    if !argument.is_empty() {
+                        command.push(' ');
+                        command.push_str(&argument);
+                    }
+                )*)?
+                command
+            } }
+            { $($is_public)* Self::$variant $({$($field: _),*})? => true,}
+            { $parse_args $parse_error $($parse)* if matches!($parse_args.first(), $(Some(&$string))|+) {
                 #[allow(unused_variables, unused_mut)]
-                let mut arg_index = 0;
-                return Some(Self::$variant $({
-                    $($field: match ArgumentParse::parse(
-                        match $parse_args.get({arg_index += 1; arg_index}) {
-                            Some(arg) => arg,
-                            None => return None,
-                        }) {
-                            Some(arg) => arg,
-                            None => return None,
-                        })*
-                })?);
+                let mut arg_index = 1;
+                // Arguments that fail to parse fall through to the next variant sharing the name
+                #[allow(clippy::redundant_closure_call)]
+                let action = (|| -> Result<Self, ParseError> {
+                    Ok(Self::$variant $({
+                        $($field: parse_argument(
+                            $parse_args,
+                            &mut arg_index,
+                            stringify!($field),
+                            actions!(@default $($default)?),
+                        )?),*
+                    })?)
+                })();
+                match action.and_then(|action| check_argument_count($parse_args, arg_index).map(|()| action)) {
+                    Ok(action) => return Ok(action),
+                    Err(err) => {
+                        $parse_error.get_or_insert(err);
+                    }
+                }
             } }
             { $fuzzy_vec $fuzzy_str $($fuzzy_match)* {$(
                     if let Some(score) = SkimMatcherV2::default().fuzzy_match(
@@ -82,6 +138,25 @@ macro_rules! actions {
                     }
                 )*}
             }
+            { $complete_args $($complete)* if matches!($complete_args.first(), $(Some(&$string))|+) {
+                #[allow(unused_mut, unused_variables)]
+                let mut field_index = 0;
+                $($(
+                    field_index += 1;
+                    if field_index + 1 == $complete_args.len() {
+                        return <$field_ty as ArgumentParse>::complete($complete_args[field_index]);
+                    }
+                )*)?
+            } }
+            { $infos_vec $($infos)* $infos_vec.push(ActionInfo {
+                names: &[$($string),*],
+                description: doc_string(&[$($doc),*]),
+                arguments: vec![$($(ArgumentInfo {
+                    name: stringify!($field),
+                    description: doc_string(&[$($field_doc),*]),
+                    optional: actions!(@has_default $($default)?) || <$field_ty as ArgumentParse>::OPTIONAL,
+                }),*)?],
+            }); }
             $enum_name $($tail)*
         }
     };
@@ -91,38 +166,64 @@ macro_rules! actions {
         { $($enum_pile:ident $variant_pile:ident)* }
         { $($current_enum:tt)* }
         { $($as_strs:tt)* }
+        { $($to_command:tt)* }
         { $($is_public:tt)* }
-        { $parse_args:ident $($parse:tt)* }
+        { $parse_args:ident $parse_error:ident $($parse:tt)* }
         { $fuzzy_vec:ident $fuzzy_str:ident $($fuzzy_match:tt)* }
+        { $complete_args:ident $($complete:tt)* }
+        { $infos_vec:ident $($infos:tt)* }
         $enum_name:ident
+        $(#[doc = $doc:literal])*
         $variant:ident $({
-            $($field:ident : $field_ty:ty),*
+            $($(#[doc = $field_doc:literal])* $field:ident : $field_ty:ty $(= $default:expr)?),*
         })? $(,$string:literal),+;
         $($tail:tt)*
     ) => {
         actions!{@elements
             { $($code)* }
             { $($enum_pile $variant_pile)* }
-            { $($current_enum)* $variant $({
-                $($field: $field_ty),*
+            { $($current_enum)* $(#[doc = $doc])* $variant $({
+                $($(#[doc = $field_doc])* $field: $field_ty),*
             })?, }
             { $($as_strs)* Self::$variant $({$($field: _),*})? => &[$($string),*], }
+            { $($to_command)* Self::$variant $({$($field),*})? => {
+                #[allow(unused_mut)]
+                let mut command = actions!(@first $($string),*).to_string();
+                $($(
+                    let argument = ArgumentParse::to_argument($field);
+                    if !argument.is_empty() {
+                        command.push(' ');
+                        command.push_str(&argument);
+                    }
+                )*)?
+                command
+            } }
             { $($is_public)*}
-            { $parse_args $($parse)* if matches!($parse_args.get(0), $(Some(&$string))|+) {
+            { $parse_args $parse_error $($parse)* if matches!($parse_args.first(), $(Some(&$string))|+) {
                 #[allow(unused_variables, unused_mut)]
-                let mut arg_index = 0;
-                return Some(Self::$variant $({
-                    $($field: match ArgumentParse::parse(
-                        match $parse_args.get({arg_index += 1; arg_index}) {
-                            Some(arg) => arg,
-                            None => return None,
-                        }) {
-                            Some(arg) => arg,
-                            None => return None,
-                        })*
-                })?);
+                let mut arg_index = 1;
+                // Arguments that fail to parse fall through to the next variant sharing the name
+                #[allow(clippy::redundant_closure_call)]
+                let action = (|| -> Result<Self, ParseError> {
+                    Ok(Self::$variant $({
+                        $($field: parse_argument(
+                            $parse_args,
+                            &mut arg_index,
+                            stringify!($field),
+                            actions!(@default $($default)?),
+                        )?),*
+                    })?)
+                })();
+                match action.and_then(|action| check_argument_count($parse_args, arg_index).map(|()| action)) {
+                    Ok(action) => return Ok(action),
+                    Err(err) => {
+                        $parse_error.get_or_insert(err);
+                    }
+                }
             } }
             { $fuzzy_vec $fuzzy_str $($fuzzy_match)* }
+            { $complete_args $($complete)* }
+            { $infos_vec $($infos)* }
             $enum_name
             $($tail)*
         }
@@ -133,9 +234,12 @@ macro_rules! actions {
         { $($enum_pile:ident $variant_pile:ident)* }
         { $($current_enum:tt)* }
         { $($as_strs:tt)* }
+        { $($to_command:tt)* }
         { $($is_public:tt)* }
-        { $parse_args:ident $($parse:tt)* }
+        { $parse_args:ident $parse_error:ident $($parse:tt)* }
         { $fuzzy_vec:ident $fuzzy_str:ident $($fuzzy_match:tt)* }
+        { $complete_args:ident $($complete:tt)* }
+        { $infos_vec:ident $($infos:tt)* }
         $enum_name:ident
     ) => {
         #[derive(Clone, Debug)]
@@ -150,31 +254,85 @@ macro_rules! actions {
                 }
             }
 
+            /// Command line parsing back to this action, see [`Action::parse`]
+            pub fn to_command(&self) -> String {
+                match self {
+                    $($to_command)*
+                }
+            }
+
             pub fn is_public(&self) -> bool {
                 match self {
                     $($is_public)*
+                    #[allow(unreachable_patterns)]
                     _ => false,
                 }
             }
 
-            pub fn parse(string: &str) -> Option<Self> {
-                Self::parse_from_args(&string.split_whitespace().collect())
+            /// Parse an action from a command line like `open "my file.rs"`
+            pub fn parse(string: &str) -> Result<Self, ParseError> {
+                let arguments = Arguments::split(string);
+
+                if arguments.unterminated_quote {
+                    return Err(ParseError::UnterminatedQuote);
+                }
+
+                if arguments.values.is_empty() {
+                    return Err(ParseError::Empty);
+                }
+
+                Self::parse_from_args(&arguments.values.iter().map(String::as_str).collect::<Vec<_>>())
             }
 
-            fn parse_from_args($parse_args: &Vec<&str>) -> Option<Self> {
+            fn parse_from_args($parse_args: &[&str]) -> Result<Self, ParseError> {
+                #[allow(unused_mut)]
+                let mut $parse_error = None;
+
                 $($parse)*
 
-                None
+                actions!(@user_parse $enum_name $parse_args $parse_error);
+
+                Err($parse_error.unwrap_or_else(|| {
+                    ParseError::UnknownCommand($parse_args.first().unwrap_or(&"").to_string())
+                }))
+            }
+
+            /// Completions for the last element of `args`,
+            /// `args[0]` being the action name and the rest its arguments
+            #[allow(unused_variables)]
+            pub fn argument_completions($complete_args: &[&str]) -> Vec<String> {
+                $($complete)*
+
+                Vec::new()
             }
 
             pub fn fuzzy_ordered(fuzzy_str: &str) -> Vec<&'static str> {
                 let mut fuzzy_scores = Self::fuzzy_scores(fuzzy_str);
                 fuzzy_scores.sort_unstable_by_key(|(score, _)| *score);
 
-                fuzzy_scores.into_iter().rev().map(|(_, string)| string).collect()
+                let mut strings = Vec::<&'static str>::new();
+                for (_, string) in fuzzy_scores.into_iter().rev() {
+                    if !strings.contains(&string) {
+                        strings.push(string);
+                    }
+                }
+                strings
             }
 
 
+            /// Documentation of the public actions
+            #[allow(clippy::vec_init_then_push)]
+            pub fn action_infos() -> Vec<ActionInfo> {
+                #[allow(unused_mut)]
+                let mut $infos_vec = Vec::new();
+
+                $($infos)*
+
+                actions!(@user_infos $enum_name $infos_vec);
+
+                $infos_vec
+            }
+
             #[allow(unused_variables)]
             pub fn fuzzy_scores($fuzzy_str: &str) -> Vec<(i64, &'static str)> {
                 #[allow(unused_mut)]
@@ -182,6 +340,8 @@ macro_rules! actions {
 
                 $($fuzzy_match)*
 
+                actions!(@user_fuzzy_scores $enum_name $fuzzy_vec $fuzzy_str);
+
                 $fuzzy_vec
             }
         }
@@ -194,11 +354,54 @@ macro_rules! actions {
         $($code)*
     };
 
+    (@default $default:expr) => {
+        Some($default)
+    };
+
+    (@default) => {
+        None
+    };
+
+    (@first $first:literal $(, $rest:literal)*) => {
+        $first
+    };
+
+    (@has_default $default:expr) => {
+        true
+    };
+
+    (@has_default) => {
+        false
+    };
+
     (@from_impl
         { $($enum_pile:ident $variant_pile:ident)* }
         Action
     ) => {};
 
+    // Commands registered at runtime are top level actions, see `user_commands`
+    (@user_parse Action $parse_args:ident $parse_error:ident) => {
+        if $parse_error.is_none() {
+            if let Some(action) = user_commands::parse($parse_args) {
+                return Ok(action);
+            }
+        }
+    };
+
+    (@user_parse $enum_name:ident $parse_args:ident $parse_error:ident) => {};
+
+    (@user_infos Action $infos_vec:ident) => {
+        $infos_vec.extend(user_commands::action_infos());
+    };
+
+    (@user_infos $enum_name:ident $infos_vec:ident) => {};
+
+    (@user_fuzzy_scores Action $fuzzy_vec:ident $fuzzy_str:ident) => {
+        $fuzzy_vec.extend(user_commands::fuzzy_scores($fuzzy_str));
+    };
+
+    (@user_fuzzy_scores $enum_name:ident $fuzzy_vec:ident $fuzzy_str:ident) => {};
+
     (@from_impl
         { $($enum_pile:ident $variant_pile:ident)* }
         $enum_name:ident
@@ -223,40 +426,254 @@ macro_rules! actions {
     };
 }
 
-// TODO: make action with args parseable
+/// Define an enum usable as an action argument, parsed from and completed with its names
+macro_rules! argument_enum {
+    (pub enum $enum_name:ident { $($variant:ident, $string:literal;)* }) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum $enum_name {
+            $($variant,)*
+        }
+
+        impl $enum_name {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $string,)*
+                }
+            }
+        }
+
+        impl ArgumentParse for $enum_name {
+            fn parse(arg: &str) -> Option<Self> {
+                match arg {
+                    $($string => Some(Self::$variant),)*
+                    _ => None,
+                }
+            }
+
+            fn to_argument(&self) -> String {
+                self.as_str().to_string()
+            }
+
+            fn complete(arg: &str) -> Vec<String> {
+                [$($string),*]
+                    .into_iter()
+                    .filter(|string| string.starts_with(arg))
+                    .map(String::from)
+                    .collect()
+            }
+        }
+    };
+}
+
 actions! {
     enum Action {
         Document => enum DocumentAction {
             SingleLine => enum SingleLineDocumentAction {
-                MoveLeft, "move_left";
-                MoveRight, "move_right";
+                /// Move the cursor one character left
+                pub MoveLeft, "move_left";
+                /// Move the cursor one character right
+                pub MoveRight, "move_right";
                 Insert{char: char}, "insert";
-                DeleteBefore, "delete_before";
+                /// Delete the character before the cursor
+                pub DeleteBefore, "delete_before";
             }
-            MoveUp, "move_up";
-            MoveDown, "move_down";
-
-            ExtendStartLeft, "extend_start_left";
-            ExtendStartRight, "extend_start_right";
-            ExtendStartUp, "extend_start_up";
-            ExtendStartDown, "extend_start_down";
-
-            ExtendEndLeft, "extend_end_left";
-            ExtendEndRight, "extend_end_right";
-            ExtendEndUp, "extend_end_up";
-            ExtendEndDown, "extend_end_down";
-
-            MoveSelectionLeft, "move_selection_left";
-            MoveSelectionRight, "move_selection_right";
-            MoveSelectionUp, "move_selection_up";
-            MoveSelectionDown, "move_selection_down";
-
-            InsertLineBeforeCursor, "insert_line_before_cursor";
-            pub Write, "write", "w";
+            /// Move the cursor one line up
+            pub MoveUp, "move_up";
+            /// Move the cursor one line down
+            pub MoveDown, "move_down";
+
+            /// Move the start of the selection one character left
+            pub ExtendStartLeft, "extend_start_left";
+            /// Move the start of the selection one character right
+            pub ExtendStartRight, "extend_start_right";
+            /// Move the start of the selection one line up
+            pub ExtendStartUp, "extend_start_up";
+            /// Move the start of the selection one line down
+            pub ExtendStartDown, "extend_start_down";
+
+            /// Move the end of the selection one character left
+            pub ExtendEndLeft, "extend_end_left";
+            /// Move the end of the selection one character right
+            pub ExtendEndRight, "extend_end_right";
+            /// Move the end of the selection one line up
+            pub ExtendEndUp, "extend_end_up";
+            /// Move the end of the selection one line down
+            pub ExtendEndDown, "extend_end_down";
+
+            /// Move the whole selection one character left
+            pub MoveSelectionLeft, "move_selection_left";
+            /// Move the whole selection one character right
+            pub MoveSelectionRight, "move_selection_right";
+            /// Move the whole selection one line up
+            pub MoveSelectionUp, "move_selection_up";
+            /// Move the whole selection one line down
+            pub MoveSelectionDown, "move_selection_down";
+
+            /// Move the cursor to the start of a line
+            pub Goto{
+                /// Line number, the first line if omitted
+                line: usize = 1
+            }, "goto", "g";
+
+            /// Split the line at the cursor
+            pub InsertLineBeforeCursor, "insert_line_before_cursor";
+            InsertText{text: String}, "insert_text";
+            /// Insert a tab, or spaces up to the next level of indentation if the document
+            /// is indented with spaces
+            pub InsertTab, "insert_tab";
+            /// Add a level of indentation to the selected lines
+            pub Indent, "indent", ">";
+            /// Remove a level of indentation from the selected lines
+            pub Outdent, "outdent", "<";
+            /// Insert an indented line below the selection and enter insert mode on it
+            pub OpenLineBelow, "open_line_below";
+            /// Insert an indented line above the selection and enter insert mode on it
+            pub OpenLineAbove, "open_line_above";
+
+            /// Hide the lines of the innermost fold containing the cursor
+            pub Fold, "fold";
+            /// Show the lines of the closed fold at the cursor
+            pub Unfold, "unfold";
+            /// Open the closed fold at the cursor, or close the innermost one containing it
+            pub ToggleFold, "toggle_fold";
+            /// Close the folds nested in at least `level` other folds and open the others
+            pub FoldLevel{
+                /// Nesting of the outermost folds to close, all of them if omitted
+                level: usize = 0
+            }, "fold_level";
+            /// Open all the folds
+            pub UnfoldAll, "unfold_all";
+
+            /// Move the cursor to the next merge conflict
+            pub NextConflict, "next_conflict";
+            /// Move the cursor to the previous merge conflict
+            pub PreviousConflict, "previous_conflict";
+            /// Resolve the merge conflict at the cursor by keeping our version
+            pub AcceptOurs, "accept_ours";
+            /// Resolve the merge conflict at the cursor by keeping their version
+            pub AcceptTheirs, "accept_theirs";
+            /// Resolve the merge conflict at the cursor by keeping our version followed by theirs
+            pub AcceptBoth, "accept_both";
+
+            /// Revert the last change
+            pub Undo, "undo", "u";
+            /// Apply again the last reverted change
+            pub Redo, "redo";
         }
+        /// Exit the editor
         pub Quit, "quit", "q";
-        pub Open{path: String}, "open", "o";
+        /// Open a file in a new buffer, or switch to it if already open
+        pub Open{
+            /// Path of the file to open
+            path: PathBuf
+        }, "open", "o";
+        /// Write the document to a new path and keep editing it there
+        pub SaveAs{
+            /// Path to write to, missing directories can be created
+            path: PathBuf
+        }, "write", "w", "saveas", "save_as";
+        /// Write the document to its path
+        pub Write, "write", "w";
+        /// Move the document file to a new path
+        pub Rename{
+            /// New path of the file
+            path: PathBuf
+        }, "rename";
+        CreateDirectory{path: PathBuf}, "create_directory";
+        /// Close the current buffer, refused if it has unsaved changes
+        pub Close, "close", "buffer_close", "bc";
+        /// Switch to the next buffer
+        pub BufferNext, "buffer_next", "bn";
+        /// Switch to the previous buffer
+        pub BufferPrevious, "buffer_previous", "bp";
+        /// Redraw the whole screen
         pub Redraw, "redraw";
+        /// Show the history of messages
+        pub ShowMessages, "messages";
+        /// Change a setting, or show its value
+        pub Set{
+            /// Name of the setting
+            setting: Setting,
+            /// New value, the current one is shown if omitted
+            value: Option<String>
+        }, "set";
+        /// Move the cursor to the next change since the version of the file in the git index
+        pub NextHunk, "next_hunk";
+        /// Move the cursor to the previous change since the version of the file in the git index
+        pub PreviousHunk, "previous_hunk";
+        /// Show the lines of the base version replaced by the hunk at the cursor
+        pub PreviewHunk, "preview_hunk";
+        /// Replace the hunk at the cursor by the lines of the base version
+        pub RevertHunk, "revert_hunk";
+        /// Add the hunk at the cursor to the git index
+        pub StageHunk, "stage_hunk";
+        /// Remove the changes of the line at the cursor from the git index
+        pub UnstageHunk, "unstage_hunk";
+        /// Run a git command on the repository of the current file, or of the working directory
+        pub Git{
+            /// One of `status`, `add`, `commit`, `log` or `init`
+            command: GitCommand
+        }, "git";
+        /// Stage the file at the cursor of the git status buffer, or unstage it if all its
+        /// changes are staged
+        pub ToggleStaged, "toggle_staged";
+        /// Open the file or the commit at the cursor of the git status or git log buffer
+        pub OpenEntry, "open_entry";
+        /// Show the last commit changing each line in the gutter, or hide it
+        pub Blame, "blame";
+        /// Open the commit that last changed the line at the cursor
+        pub ShowLineCommit, "show_line_commit";
+        /// Compare the document side by side with another one
+        pub Diff{
+            /// Path of the other file, or `HEAD` for its last committed version, its version on
+            /// disk if omitted
+            target: Option<BufferName>
+        }, "diff";
+        /// Close the diff view
+        pub DiffOff, "diff_off";
+        /// Edit the other side of the diff view
+        pub DiffSwitch, "diff_switch";
+        /// Replace the hunk at the cursor by the lines of the other side of the diff view
+        pub DiffGet, "diff_get";
+        /// Replace the lines of the other side of the diff view by the hunk at the cursor
+        pub DiffPut, "diff_put";
+        /// Switch the line numbers to the next of absolute, relative and hybrid
+        pub ToggleLineNumbers, "toggle_line_numbers";
+        /// Open the list of actions with their key bindings
+        pub Help{
+            /// Action to jump to in the list
+            action: Option<ActionName>
+        }, "help", "h";
+        /// Open the list of key bindings of each mode
+        pub Keymap, "keymap";
+        /// Replay the last change at the current selection
+        pub RepeatLastChange, "repeat_last_change";
+        /// Add a digit to the count applied to the next action
+        CountDigit{digit: usize}, "count_digit";
+        /// Use the register named by the next typed character for the next yank, paste or macro
+        SelectRegister, "select_register";
+        /// Copy the selection to a register
+        pub Yank{
+            /// Register to copy to, `"` if omitted
+            register: Option<char>
+        }, "yank", "y";
+        /// Insert the content of a register at the cursor
+        pub Paste{
+            /// Register to insert, `"` if omitted
+            register: Option<char>
+        }, "paste", "p";
+        /// Start recording the actions into a register, or stop the current recording
+        pub RecordMacro{
+            /// Register to record into, `@` if omitted
+            register: Option<char>
+        }, "record_macro";
+        /// Replay the actions recorded in a register
+        pub ReplayMacro{
+            /// Register to replay, `@` if omitted
+            register: Option<char>
+        }, "replay_macro";
+        /// Run a command registered by a script, see [`register_user_command`]
+        UserCommand{name: String, arguments: Vec<String>}, "user_command";
         Validate, "validate";
         Cancel, "cancel";
         EnterNormalMode, "enter_normal_mode";
@@ -264,20 +681,354 @@ actions! {
         EnterSelectionMode, "enter_selection_mode";
         FocusCommandBar, "focus_command_bar";
         FocusEditor, "focus_editor";
+        CompleteNext, "complete_next";
+        CompletePrevious, "complete_previous";
+        HistoryPrevious, "history_previous";
+        HistoryNext, "history_next";
+        HistorySearch, "history_search";
+    }
+}
+
+argument_enum! {
+    pub enum Setting {
+        TabWidth, "tab-width";
+        SoftWrap, "soft-wrap";
+        LineNumbers, "line-numbers";
+    }
+}
+
+argument_enum! {
+    pub enum GitCommand {
+        Status, "status";
+        Add, "add";
+        Commit, "commit";
+        Log, "log";
+        Init, "init";
+    }
+}
+
+argument_enum! {
+    pub enum LineNumbers {
+        Absolute, "absolute";
+        Relative, "relative";
+        Hybrid, "hybrid";
+    }
+}
+
+impl LineNumbers {
+    /// Mode following this one when toggling, see [`Action::ToggleLineNumbers`]
+    pub fn next(self) -> Self {
+        match self {
+            Self::Absolute => Self::Relative,
+            Self::Relative => Self::Hybrid,
+            Self::Hybrid => Self::Absolute,
+        }
+    }
+}
+
+impl Action {
+    /// Name of the command of this action, the script command of an [`Action::UserCommand`]
+    pub fn command_name(&self) -> &str {
+        match self {
+            Self::UserCommand { name, .. } => name,
+            action => action.as_strs()[0],
+        }
+    }
+
+    /// Parse a `;` separated sequence of actions,
+    /// each can be followed by a count like `move_down 3` if it is a document action
+    pub fn parse_sequence(string: &str) -> Result<Vec<(Self, usize)>, ParseError> {
+        let commands = split_commands(string);
+
+        if commands.iter().all(|command| command.trim().is_empty()) {
+            return Err(ParseError::Empty);
+        }
+
+        commands
+            .into_iter()
+            .filter(|command| !command.trim().is_empty())
+            .map(Self::parse_counted)
+            .collect()
+    }
+
+    /// Parse an action optionally followed by a count if it is a document action
+    pub fn parse_counted(string: &str) -> Result<(Self, usize), ParseError> {
+        let err = match Self::parse(string) {
+            Ok(action) => return Ok((action, 1)),
+            Err(err) => err,
+        };
+
+        let ParseError::UnexpectedArgument { value, .. } = &err else {
+            return Err(err);
+        };
+
+        let arguments = Arguments::split(string);
+        let args = arguments
+            .values
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+
+        match (args.split_last(), value.parse::<usize>()) {
+            (Some((last, args)), Ok(count)) if last == value => match Self::parse_from_args(args) {
+                Ok(action @ Self::Document(_)) => Ok((action, count)),
+                _ => Err(err),
+            },
+            _ => Err(err),
+        }
+    }
+}
+
+impl DocumentAction {
+    /// Whether the action modifies the document text, see [`Action::RepeatLastChange`]
+    pub fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            Self::SingleLine(
+                SingleLineDocumentAction::Insert { .. } | SingleLineDocumentAction::DeleteBefore
+            ) | Self::InsertLineBeforeCursor
+                | Self::InsertText { .. }
+                | Self::InsertTab
+                | Self::Indent
+                | Self::Outdent
+                | Self::OpenLineBelow
+                | Self::OpenLineAbove
+                | Self::AcceptOurs
+                | Self::AcceptTheirs
+                | Self::AcceptBoth
+        )
+    }
+
+    /// Whether the action is followed by typing text, entering insert mode first
+    pub fn starts_insert(&self) -> bool {
+        matches!(self, Self::OpenLineBelow | Self::OpenLineAbove)
+    }
+}
+
+/// Documentation of a public action, see [`Action::action_infos`]
+#[derive(Clone, Debug)]
+pub struct ActionInfo {
+    pub names: &'static [&'static str],
+    pub description: String,
+    pub arguments: Vec<ArgumentInfo>,
+}
+
+impl ActionInfo {
+    /// Get the documentation of the public action named `name`
+    pub fn find(name: &str) -> Option<Self> {
+        Action::action_infos()
+            .into_iter()
+            .find(|info| info.names.contains(&name))
+    }
+
+    /// Usage line like `open, o <path>`, optional arguments are in brackets
+    pub fn usage(&self) -> String {
+        let mut usage = self.names.join(", ");
+
+        for argument in &self.arguments {
+            match argument.optional {
+                true => usage.push_str(&format!(" [{}]", argument.name)),
+                false => usage.push_str(&format!(" <{}>", argument.name)),
+            }
+        }
+
+        usage
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ArgumentInfo {
+    pub name: &'static str,
+    pub description: String,
+    pub optional: bool,
+}
+
+/// Join doc comment lines, removing the space following `///`
+fn doc_string(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Name of a public action, completed from the action names
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActionName(pub String);
+
+impl ArgumentParse for ActionName {
+    fn parse(arg: &str) -> Option<Self> {
+        ActionInfo::find(arg).map(|_| Self(arg.to_string()))
+    }
+
+    fn to_argument(&self) -> String {
+        self.0.clone()
+    }
+
+    fn complete(arg: &str) -> Vec<String> {
+        let mut names = Action::action_infos()
+            .into_iter()
+            .flat_map(|info| info.names.iter())
+            .filter(|name| name.starts_with(arg))
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// Path of an open buffer or of a file, completed from the open buffers first
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BufferName(pub String);
+
+impl ArgumentParse for BufferName {
+    fn parse(arg: &str) -> Option<Self> {
+        Some(Self(arg.to_string()))
+    }
+
+    fn to_argument(&self) -> String {
+        quote_argument(&self.0)
+    }
+
+    fn complete(arg: &str) -> Vec<String> {
+        let mut names = buffers::complete(arg);
+        for path in PathBuf::complete(arg) {
+            if !names.contains(&path) {
+                names.push(path);
+            }
+        }
+
+        names
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    UnterminatedQuote,
+    UnknownCommand(String),
+    MissingArgument {
+        command: String,
+        argument: &'static str,
+    },
+    InvalidArgument {
+        command: String,
+        argument: &'static str,
+        value: String,
+    },
+    UnexpectedArgument {
+        command: String,
+        value: String,
+    },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "empty command"),
+            ParseError::UnterminatedQuote => write!(f, "unterminated quote"),
+            ParseError::UnknownCommand(command) => write!(f, "unknown command `{}`", command),
+            ParseError::MissingArgument { command, argument } => {
+                write!(f, "{}: missing argument `{}`", command, argument)
+            }
+            ParseError::InvalidArgument {
+                command,
+                argument,
+                value,
+            } => write!(
+                f,
+                "{}: invalid value `{}` for argument `{}`",
+                command, value, argument
+            ),
+            ParseError::UnexpectedArgument { command, value } => {
+                write!(f, "{}: unexpected argument `{}`", command, value)
+            }
+        }
+    }
+}
+
+fn parse_argument<T: ArgumentParse>(
+    args: &[&str],
+    index: &mut usize,
+    argument: &'static str,
+    default: Option<T>,
+) -> Result<T, ParseError> {
+    let command = args.first().unwrap_or(&"").to_string();
+
+    let mut remaining = args.get(*index..).unwrap_or(&[]);
+    let remaining_len = remaining.len();
+    let result = T::parse_args(&mut remaining);
+    *index += remaining_len - remaining.len();
+
+    match (result, default) {
+        (Ok(value), _) => Ok(value),
+        (Err(ArgumentError::Missing), Some(default)) => Ok(default),
+        (Err(ArgumentError::Missing), None) => {
+            Err(ParseError::MissingArgument { command, argument })
+        }
+        (Err(ArgumentError::Invalid(value)), _) => Err(ParseError::InvalidArgument {
+            command,
+            argument,
+            value,
+        }),
     }
 }
 
-trait ArgumentParse
+/// Check that no argument remains after the ones consumed before `index`
+fn check_argument_count(args: &[&str], index: usize) -> Result<(), ParseError> {
+    match args.get(index) {
+        Some(arg) => Err(ParseError::UnexpectedArgument {
+            command: args.first().unwrap_or(&"").to_string(),
+            value: arg.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgumentError {
+    Missing,
+    Invalid(String),
+}
+
+pub trait ArgumentParse
 where
     Self: Sized,
 {
+    /// Whether the argument can be omitted
+    const OPTIONAL: bool = false;
+
     fn parse(arg: &str) -> Option<Self>;
+
+    /// Format the value as it would be typed, empty if nothing needs to be typed
+    fn to_argument(&self) -> String;
+
+    /// Parse the value from the start of `args`, advancing it past the arguments used
+    fn parse_args(args: &mut &[&str]) -> Result<Self, ArgumentError> {
+        let Some((arg, rest)) = args.split_first() else {
+            return Err(ArgumentError::Missing);
+        };
+        *args = rest;
+
+        Self::parse(arg).ok_or_else(|| ArgumentError::Invalid(arg.to_string()))
+    }
+
+    /// Possible values for an argument starting with `arg`
+    fn complete(_arg: &str) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 impl ArgumentParse for String {
     fn parse(arg: &str) -> Option<Self> {
         Some(arg.to_string())
     }
+
+    fn to_argument(&self) -> String {
+        quote_argument(self)
+    }
 }
 impl ArgumentParse for char {
     fn parse(arg: &str) -> Option<Self> {
@@ -290,4 +1041,232 @@ impl ArgumentParse for char {
         }
         Some(char)
     }
+
+    fn to_argument(&self) -> String {
+        quote_argument(&self.to_string())
+    }
+}
+impl ArgumentParse for PathBuf {
+    fn parse(arg: &str) -> Option<Self> {
+        Some(PathBuf::from(arg))
+    }
+
+    fn to_argument(&self) -> String {
+        quote_argument(&self.display().to_string())
+    }
+
+    fn complete(arg: &str) -> Vec<String> {
+        let (dir, prefix) = match arg.rfind('/') {
+            Some(i) => arg.split_at(i + 1),
+            None => ("", arg),
+        };
+
+        let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+            return Vec::new();
+        };
+
+        let mut completions = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+
+                if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.'))
+                {
+                    return None;
+                }
+
+                let separator = match entry.file_type().ok()?.is_dir() {
+                    true => "/",
+                    false => "",
+                };
+
+                Some(format!("{}{}{}", dir, name, separator))
+            })
+            .collect::<Vec<_>>();
+
+        completions.sort();
+        completions
+    }
+}
+
+macro_rules! integer_argument_parse {
+    ($($integer:ty)*) => {$(
+        impl ArgumentParse for $integer {
+            fn parse(arg: &str) -> Option<Self> {
+                arg.parse().ok()
+            }
+
+            fn to_argument(&self) -> String {
+                self.to_string()
+            }
+        }
+    )*};
+}
+
+integer_argument_parse!(u8 u16 u32 u64 usize i8 i16 i32 i64 isize);
+
+impl ArgumentParse for bool {
+    fn parse(arg: &str) -> Option<Self> {
+        match arg {
+            "true" | "on" | "yes" => Some(true),
+            "false" | "off" | "no" => Some(false),
+            _ => None,
+        }
+    }
+
+    fn to_argument(&self) -> String {
+        self.to_string()
+    }
+
+    fn complete(arg: &str) -> Vec<String> {
+        ["true", "false"]
+            .into_iter()
+            .filter(|value| value.starts_with(arg))
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Optional argument, `None` when missing
+impl<T: ArgumentParse> ArgumentParse for Option<T> {
+    const OPTIONAL: bool = true;
+
+    fn parse(arg: &str) -> Option<Self> {
+        T::parse(arg).map(Some)
+    }
+
+    fn to_argument(&self) -> String {
+        self.as_ref().map(T::to_argument).unwrap_or_default()
+    }
+
+    fn parse_args(args: &mut &[&str]) -> Result<Self, ArgumentError> {
+        if args.is_empty() {
+            return Ok(None);
+        }
+
+        T::parse_args(args).map(Some)
+    }
+
+    fn complete(arg: &str) -> Vec<String> {
+        T::complete(arg)
+    }
+}
+
+/// Variadic argument, consumes all the remaining arguments
+impl<T: ArgumentParse> ArgumentParse for Vec<T> {
+    const OPTIONAL: bool = true;
+
+    fn parse(arg: &str) -> Option<Self> {
+        T::parse(arg).map(|value| vec![value])
+    }
+
+    fn to_argument(&self) -> String {
+        self.iter()
+            .map(T::to_argument)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn parse_args(args: &mut &[&str]) -> Result<Self, ArgumentError> {
+        let mut values = Vec::new();
+
+        while !args.is_empty() {
+            values.push(T::parse_args(args)?);
+        }
+
+        Ok(values)
+    }
+
+    fn complete(arg: &str) -> Vec<String> {
+        T::complete(arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn parses_optional_and_default_arguments() {
+        assert!(matches!(
+            Action::parse("yank"),
+            Ok(Action::Yank { register: None })
+        ));
+        assert!(matches!(
+            Action::parse("yank a"),
+            Ok(Action::Yank {
+                register: Some('a')
+            })
+        ));
+        assert!(matches!(
+            Action::parse("fold_level"),
+            Ok(Action::Document(DocumentAction::FoldLevel { level: 0 }))
+        ));
+        assert!(matches!(
+            Action::parse("fold_level 2"),
+            Ok(Action::Document(DocumentAction::FoldLevel { level: 2 }))
+        ));
+    }
+
+    #[test]
+    fn parses_variadic_arguments() {
+        let Ok(Action::UserCommand { name, arguments }) =
+            Action::parse(r#"user_command upper "a b" c"#)
+        else {
+            panic!("expected a user command");
+        };
+        assert_eq!(name, "upper");
+        assert_eq!(arguments, ["a b", "c"]);
+
+        let mut args: &[&str] = &["1", "2", "x"];
+        assert_eq!(
+            Vec::<usize>::parse_args(&mut args),
+            Err(ArgumentError::Invalid("x".to_string()))
+        );
+        let mut args: &[&str] = &[];
+        assert_eq!(Vec::<usize>::parse_args(&mut args), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn reports_argument_errors() {
+        assert_eq!(
+            Action::parse("open").unwrap_err(),
+            ParseError::MissingArgument {
+                command: "open".to_string(),
+                argument: "path",
+            }
+        );
+        assert_eq!(
+            Action::parse("yank ab").unwrap_err(),
+            ParseError::InvalidArgument {
+                command: "yank".to_string(),
+                argument: "register",
+                value: "ab".to_string(),
+            }
+        );
+        assert_eq!(
+            Action::parse("quit now").unwrap_err(),
+            ParseError::UnexpectedArgument {
+                command: "quit".to_string(),
+                value: "now".to_string(),
+            }
+        );
+        assert_eq!(
+            Action::parse(r#"open "a"#).unwrap_err(),
+            ParseError::UnterminatedQuote
+        );
+        assert_eq!(Action::parse("  ").unwrap_err(), ParseError::Empty);
+    }
+
+    #[test]
+    fn commands_parse_back_to_their_action() {
+        let action = Action::parse(r#"open "my file.rs""#).unwrap();
+        assert_eq!(action.to_command(), r#"open "my file.rs""#);
+        assert!(matches!(
+            Action::parse(&action.to_command()),
+            Ok(Action::Open { path }) if path == Path::new("my file.rs")
+        ));
+    }
 }
@@ -0,0 +1,87 @@
+use std::sync::RwLock;
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+
+use crate::{Action, ActionInfo, ArgumentInfo};
+
+/// Commands defined at runtime, by scripts, in addition to the built-in actions
+///
+/// They parse to [`Action::UserCommand`] and show up in suggestions and the help like the
+/// public actions. Names are leaked to be usable as `&'static str` like the built-in ones,
+/// commands are registered once at startup
+static USER_COMMANDS: RwLock<Vec<UserCommand>> = RwLock::new(Vec::new());
+
+#[derive(Clone, Debug)]
+struct UserCommand {
+    names: &'static [&'static str],
+    description: String,
+}
+
+/// Register the command `name`, replacing a previous one with the same name
+pub fn register_user_command(name: &str, description: &str) {
+    let mut commands = USER_COMMANDS.write().unwrap_or_else(|err| err.into_inner());
+
+    commands.retain(|command| command.names[0] != name);
+    commands.push(UserCommand {
+        names: Box::leak(Box::new([&*Box::leak(name.into())])),
+        description: description.to_string(),
+    });
+}
+
+fn user_commands() -> Vec<UserCommand> {
+    USER_COMMANDS
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+}
+
+pub(crate) fn is_user_command(name: &str) -> bool {
+    user_commands()
+        .iter()
+        .any(|command| command.names[0] == name)
+}
+
+pub(crate) fn parse(args: &[&str]) -> Option<Action> {
+    let (name, arguments) = args.split_first()?;
+
+    is_user_command(name).then(|| Action::UserCommand {
+        name: name.to_string(),
+        arguments: arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect(),
+    })
+}
+
+pub(crate) fn fuzzy_scores(fuzzy_str: &str) -> Vec<(i64, &'static str)> {
+    user_commands()
+        .into_iter()
+        .filter_map(|command| {
+            let name = command.names[0];
+            let prefix = &name[0..name
+                .char_indices()
+                .nth(fuzzy_str.chars().count())
+                .map(|(i, _)| i)
+                .unwrap_or(name.len())];
+
+            SkimMatcherV2::default()
+                .fuzzy_match(fuzzy_str, prefix)
+                .map(|score| (score, name))
+        })
+        .collect()
+}
+
+pub(crate) fn action_infos() -> Vec<ActionInfo> {
+    user_commands()
+        .into_iter()
+        .map(|command| ActionInfo {
+            names: command.names,
+            description: command.description,
+            arguments: vec![ArgumentInfo {
+                name: "arguments",
+                description: "Arguments given to the command".to_string(),
+                optional: true,
+            }],
+        })
+        .collect()
+}
@@ -0,0 +1,117 @@
+use std::{iter, mem, path::PathBuf};
+
+use editor_document::{Document, DocumentName};
+
+use crate::{editor::Editor, App};
+
+impl App {
+    /// Show the file at `path`, taking its buffer if it is already open
+    pub(crate) fn open(&mut self, path: PathBuf) {
+        if self.editor.document.path() != Some(path.as_path()) {
+            let editor = match self
+                .buffers
+                .iter()
+                .position(|editor| editor.document.path() == Some(path.as_path()))
+            {
+                Some(index) => self.buffers.remove(index).unwrap(),
+                None => Editor::from_path(path),
+            };
+            self.show_editor(editor);
+        }
+        self.draw();
+    }
+
+    /// Close the current buffer unless it has unsaved changes, the next one replacing it
+    pub(crate) fn close(&mut self) {
+        if self.editor.document.dirty() {
+            self.messages.error(format!(
+                "{} has unsaved changes, write them before closing",
+                self.editor.document.display_name()
+            ));
+            return;
+        }
+
+        self.commit_on_close();
+        self.close_diff();
+        let next = self.buffers.pop_front().unwrap_or_else(Editor::new_scratch);
+        self.replace_editor(next);
+        self.draw();
+    }
+
+    /// Show the next buffer, the current one becoming the last
+    pub(crate) fn buffer_next(&mut self) {
+        self.close_diff();
+        if let Some(editor) = self.buffers.pop_front() {
+            let previous = self.replace_editor(editor);
+            self.buffers.push_back(previous);
+        }
+        self.draw();
+    }
+
+    /// Show the last buffer, the current one becoming the next
+    pub(crate) fn buffer_previous(&mut self) {
+        self.close_diff();
+        if let Some(editor) = self.buffers.pop_back() {
+            let previous = self.replace_editor(editor);
+            self.buffers.push_front(previous);
+        }
+        self.draw();
+    }
+
+    /// Paths of the buffers backed by a file, the current one first
+    pub(crate) fn buffer_paths(&self) -> Vec<String> {
+        iter::once(&self.editor)
+            .chain(&self.buffers)
+            .filter_map(|editor| editor.document.path())
+            .map(|path| path.display().to_string())
+            .collect()
+    }
+
+    /// Make `editor` the current one and return the previous one, see [`swap_editors`]
+    pub(crate) fn replace_editor(&mut self, mut editor: Editor) -> Editor {
+        swap_editors(&mut self.editor, &mut editor, self.transactions);
+        editor
+    }
+
+    /// Make `editor` the current buffer, the previous one becoming the next in line
+    ///
+    /// An empty and unmodified scratch buffer is dropped instead
+    pub(crate) fn show_editor(&mut self, editor: Editor) {
+        self.close_diff();
+        let previous = self.replace_editor(editor);
+
+        let is_blank_scratch = matches!(previous.document.name(), DocumentName::Scratch)
+            && !previous.document.dirty()
+            && previous.document.lines().is_empty();
+
+        if !is_blank_scratch {
+            self.buffers.push_front(previous);
+        }
+    }
+
+    /// Show a generated document, read-only, replacing the previous one with the same name
+    pub(crate) fn show_virtual(&mut self, name: &str, lines: Vec<String>) {
+        self.close_diff();
+        let is_same = |editor: &Editor| matches!(editor.document.name(), DocumentName::Virtual(other) if other == name);
+        self.buffers.retain(|editor| !is_same(editor));
+
+        let mut document = Document::from_lines(name.to_string(), lines);
+        document.set_read_only(true);
+        if is_same(&self.editor) {
+            self.replace_editor(Editor::from_document(document));
+        } else {
+            self.show_editor(Editor::from_document(document));
+        }
+    }
+}
+
+/// Swap the current editor `current` with `other`, the `transactions` open on the current
+/// document being closed and opened again on the new current one, see [`App::begin_transaction`]
+pub(crate) fn swap_editors(current: &mut Editor, other: &mut Editor, transactions: usize) {
+    mem::swap(current, other);
+
+    for _ in 0..transactions {
+        other.document.end_transaction();
+        current.document.begin_transaction();
+    }
+}
@@ -1,16 +1,43 @@
-use editor_action::{Action, SingleLineDocumentAction};
-use editor_document::SingleLineDocument;
+use editor_action::{quote_argument, Action, Arguments, ParseError, SingleLineDocumentAction};
+use editor_document::{text, SingleLineDocument};
 use editor_terminal::{TermRect, TermSlice};
 use editor_theme::Theme;
 
+use crate::history::History;
+
 pub struct CommandBar {
     document: SingleLineDocument,
+    /// Line typed before cycling through the suggestions, see [`CommandBar::complete`]
+    completion_base: Option<String>,
+    selected: Option<usize>,
+    history: History,
+    /// Index of the displayed history entry, see [`CommandBar::history_previous`]
+    history_index: Option<usize>,
+    /// Line typed before navigating the history, only entries starting with it are shown
+    history_prefix: String,
+    search: Option<HistorySearch>,
+}
+
+/// Reverse search through the history, see [`CommandBar::history_search`]
+struct HistorySearch {
+    query: String,
+    index: Option<usize>,
 }
 
 impl CommandBar {
     pub fn new() -> Self {
+        Self::with_history(History::load("command"))
+    }
+
+    pub fn with_history(history: History) -> Self {
         Self {
             document: SingleLineDocument::new(),
+            completion_base: None,
+            selected: None,
+            history,
+            history_index: None,
+            history_prefix: String::new(),
+            search: None,
         }
     }
 
@@ -33,25 +60,45 @@ impl CommandBar {
     }
 
     fn draw_suggestions(&self, theme: &Theme, mut term: TermSlice) {
-        if self.document.line().chars().any(|ch| ch.is_whitespace()) {
-            return;
-        }
+        let suggestions = suggestions(
+            self.completion_base
+                .as_deref()
+                .unwrap_or(self.document.line()),
+        );
+
+        let value_width = suggestions
+            .iter()
+            .map(|suggestion| text::width(&suggestion.value))
+            .max()
+            .unwrap_or(0);
+
+        let height = term.rect().heigth() as usize;
+        let first = self
+            .selected
+            .map(|selected| (selected + 1).saturating_sub(height))
+            .unwrap_or(0);
 
-        term.set_background_color(theme.command_suggestion_background);
-        term.set_text_color(theme.command_suggestion_text);
+        for y in 0..height {
+            if self.selected == Some(first + y) {
+                term.set_background_color(theme.command_suggestion_selected_background);
+                term.set_text_color(theme.command_suggestion_selected_text);
+            } else {
+                term.set_background_color(theme.command_suggestion_background);
+                term.set_text_color(theme.command_suggestion_text);
+            }
 
-        let suggestions = Action::fuzzy_ordered(self.document.line());
+            let row = match suggestions.get(first + y) {
+                Some(suggestion) => format!(
+                    "{}  {}",
+                    text::pad(&suggestion.value, value_width),
+                    suggestion.description,
+                ),
+                None => String::new(),
+            };
 
-        for y in 0..term.rect().heigth() {
             term.write_to(
-                (0, y),
-                &suggestions
-                    .get(y as usize)
-                    .unwrap_or(&"")
-                    .chars()
-                    .chain(std::iter::repeat(' '))
-                    .take(term.rect().width() as usize)
-                    .collect::<String>(),
+                (0, y as u16),
+                &text::pad(&row, term.rect().width() as usize),
             );
         }
     }
@@ -59,60 +106,267 @@ impl CommandBar {
     fn draw_line(&self, theme: &Theme, mut term: TermSlice) {
         term.set_background_color(theme.command_bar_background);
 
-        let text = format!(
-            ":{:<width$}",
-            self.document.line(),
-            width = (term.rect().width() as usize).saturating_sub(1)
-        );
-
-        let cursor_index = text
-            .char_indices()
-            .nth(self.document.cursor() + 1)
-            .map(|(i, _)| i)
-            .unwrap_or(self.document.line().len());
-
-        let (before_cursor, after) = text.split_at(cursor_index);
-        let before_cursor_count = before_cursor.chars().count() as u16;
-        let (at_cursor, after_cursor) = after.split_at(
-            after
-                .char_indices()
-                .nth(1)
-                .map(|(i, _)| i)
-                .unwrap_or(after.len()),
-        );
-
-        term.set_text_color(theme.command_bar_text);
-        term.write_to((0, 0), before_cursor);
+        if let Some(search) = &self.search {
+            let matched = search
+                .index
+                .map(|index| self.history.entries()[index].as_str())
+                .unwrap_or("");
 
-        if at_cursor.len() == 0 {
+            term.set_text_color(theme.command_bar_text);
+            term.write_to(
+                (0, 0),
+                &text::pad(
+                    &format!("(history search) {}: {}", search.query, matched),
+                    term.rect().width() as usize,
+                ),
+            );
             return;
         }
 
+        let line = self.document.line();
+        let cursor = text::byte_index(line, self.document.cursor());
+        let cursor_end = text::byte_index(line, text::next_grapheme(line, self.document.cursor()));
+
+        let before_cursor = format!(":{}", &line[..cursor]);
+        let at_cursor = match &line[cursor..cursor_end] {
+            "" => " ",
+            at_cursor => at_cursor,
+        };
+        let cursor_x = text::width(&before_cursor) as u16;
+        let after_x = cursor_x + text::grapheme_width(at_cursor) as u16;
+
+        term.set_text_color(theme.command_bar_text);
+        term.write_to((0, 0), &before_cursor);
+
         term.set_background_color(theme.cursor);
         term.set_text_color(theme.command_bar_background);
-        term.write_to((before_cursor_count, 0), at_cursor);
-
-        if after_cursor.len() == 0 {
-            return;
-        }
+        term.write_to((cursor_x, 0), at_cursor);
 
         term.set_background_color(theme.command_bar_background);
         term.set_text_color(theme.command_bar_text);
-        term.write_to((before_cursor_count + 1, 0), after_cursor);
+        term.write_to(
+            (after_x, 0),
+            &text::pad(
+                line.get(cursor_end..).unwrap_or(""),
+                (term.rect().width().saturating_sub(after_x)) as usize,
+            ),
+        );
     }
 
-    pub fn validate(&mut self) -> Option<Action> {
-        let action = Action::parse(&self.document.line())
-            .and_then(|action| action.is_public().then_some(action));
-        self.document.clear();
-        action
+    /// Parse the command line and clear it, returns `None` if the line is empty
+    ///
+    /// The line can contain a `;` separated sequence of actions, each with a count
+    pub fn validate(&mut self) -> Option<Result<Vec<(Action, usize)>, ParseError>> {
+        self.accept_search();
+
+        let result = Action::parse_sequence(self.document.line());
+        self.history.push(self.document.line().trim().to_string());
+        self.cancel();
+
+        let actions = match result {
+            Err(ParseError::Empty) => return None,
+            Err(err) => return Some(Err(err)),
+            Ok(actions) => actions,
+        };
+
+        // Script commands aren't public actions but are typed with their own name
+        let is_typable =
+            |action: &Action| action.is_public() || matches!(action, Action::UserCommand { .. });
+        if let Some((action, _)) = actions.iter().find(|(action, _)| !is_typable(action)) {
+            return Some(Err(ParseError::UnknownCommand(
+                action.as_strs()[0].to_string(),
+            )));
+        }
+
+        Some(Ok(actions))
     }
 
     pub fn cancel(&mut self) {
         self.document.clear();
+        self.reset_completion();
+        self.history_index = None;
+        self.search = None;
+    }
+
+    /// Replace the line with the previous history entry starting with the typed line
+    pub fn history_previous(&mut self) {
+        self.accept_search();
+        self.reset_completion();
+
+        if self.history_index.is_none() {
+            self.history_prefix = self.document.line().to_string();
+        }
+
+        if let Some(index) = self
+            .history
+            .previous(self.history_index, &self.history_prefix)
+        {
+            self.history_index = Some(index);
+            self.document
+                .set_line(self.history.entries()[index].clone());
+        }
+    }
+
+    /// Replace the line with the next history entry starting with the typed line,
+    /// restoring the typed line after the most recent entry
+    pub fn history_next(&mut self) {
+        self.accept_search();
+        self.reset_completion();
+
+        let Some(current) = self.history_index else {
+            return;
+        };
+
+        match self.history.next(current, &self.history_prefix) {
+            Some(index) => {
+                self.history_index = Some(index);
+                self.document
+                    .set_line(self.history.entries()[index].clone());
+            }
+            None => {
+                self.history_index = None;
+                self.document
+                    .set_line(std::mem::take(&mut self.history_prefix));
+            }
+        }
+    }
+
+    /// Start a reverse search through the history or go to the next older match
+    pub fn history_search(&mut self) {
+        self.reset_completion();
+
+        match &mut self.search {
+            Some(search) => {
+                if let Some(index) = self.history.search(search.index, &search.query) {
+                    search.index = Some(index);
+                }
+            }
+            None => {
+                self.search = Some(HistorySearch {
+                    query: String::new(),
+                    index: self.history.search(None, ""),
+                })
+            }
+        }
+    }
+
+    /// Stop searching, replacing the line with the matched entry
+    fn accept_search(&mut self) {
+        let Some(search) = self.search.take() else {
+            return;
+        };
+
+        if let Some(index) = search.index {
+            self.document
+                .set_line(self.history.entries()[index].clone());
+        }
+    }
+
+    /// Replace the word under completion with the next (or previous) suggestion
+    pub fn complete(&mut self, forward: bool) {
+        let base = self
+            .completion_base
+            .get_or_insert_with(|| self.document.line().to_string())
+            .clone();
+
+        let suggestions = suggestions(&base);
+        if suggestions.is_empty() {
+            self.reset_completion();
+            return;
+        }
+
+        let len = suggestions.len();
+        let selected = match (self.selected, forward) {
+            (None, true) => 0,
+            (None, false) => len - 1,
+            (Some(selected), true) => (selected + 1) % len,
+            (Some(selected), false) => (selected + len - 1) % len,
+        };
+        self.selected = Some(selected);
+
+        let arguments = Arguments::split(&base);
+        let before_word = match (arguments.ends_in_argument, arguments.starts.last()) {
+            (true, Some(&start)) => &base[..start],
+            _ => &base,
+        };
+        self.document.set_line(format!(
+            "{}{}",
+            before_word,
+            quote_argument(&suggestions[selected].value)
+        ));
+    }
+
+    fn reset_completion(&mut self) {
+        self.completion_base = None;
+        self.selected = None;
     }
 
     pub fn handle_action(&mut self, document_action: SingleLineDocumentAction) {
+        self.reset_completion();
+
+        if let Some(search) = &mut self.search {
+            match document_action {
+                SingleLineDocumentAction::Insert { char } => search.query.push(char),
+                SingleLineDocumentAction::DeleteBefore => {
+                    search.query.pop();
+                }
+                _ => {
+                    self.accept_search();
+                    return;
+                }
+            }
+
+            search.index = self.history.search(None, &search.query);
+            return;
+        }
+
+        self.history_index = None;
         self.document.handle_action(document_action);
     }
 }
+
+/// Action names if the first word is being typed, otherwise completions for the last argument
+struct Suggestion {
+    value: String,
+    /// First line of the action documentation, empty for arguments
+    description: String,
+}
+
+fn suggestions(line: &str) -> Vec<Suggestion> {
+    let arguments = Arguments::split(line);
+    let mut args = arguments
+        .values
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    if !arguments.ends_in_argument {
+        args.push("");
+    }
+
+    match args.as_slice() {
+        [action] => {
+            let infos = Action::action_infos();
+
+            Action::fuzzy_ordered(action)
+                .into_iter()
+                .map(|name| Suggestion {
+                    value: name.to_string(),
+                    description: infos
+                        .iter()
+                        .find(|info| info.names.contains(&name))
+                        .and_then(|info| info.description.lines().next())
+                        .unwrap_or("")
+                        .to_string(),
+                })
+                .collect()
+        }
+        args => Action::argument_completions(args)
+            .into_iter()
+            .map(|value| Suggestion {
+                value,
+                description: String::new(),
+            })
+            .collect(),
+    }
+}
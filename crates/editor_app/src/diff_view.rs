@@ -0,0 +1,348 @@
+use std::{collections::HashMap, ops::Range, path::PathBuf};
+
+use editor_action::{Action, DocumentAction};
+use editor_document::Document;
+use editor_git::{diff_chars, diff_lines, GitError, Hunk, TrackedFile};
+use editor_mode::Mode;
+use editor_terminal::TermRect;
+
+use crate::{buffers::swap_editors, editor::Editor, App};
+
+/// Side of a diff view, the lines of the left one being compared to the right one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffSide {
+    Left,
+    Right,
+}
+
+/// Lines of one side of a diff view differing from the other side, see [`Editor::side_diff`]
+#[derive(Debug)]
+pub struct SideDiff {
+    pub side: DiffSide,
+    /// Lines changed on this side, with the ranges of chars changed in the lines paired with a
+    /// line of the other side
+    pub changed: HashMap<usize, Vec<Range<usize>>>,
+    /// Number of filler rows drawn above a line, standing for lines of the other side
+    pub fillers: HashMap<usize, usize>,
+}
+
+impl SideDiff {
+    fn new(side: DiffSide) -> Self {
+        Self {
+            side,
+            changed: HashMap::new(),
+            fillers: HashMap::new(),
+        }
+    }
+
+    pub fn fillers(&self, line: usize) -> usize {
+        self.fillers.get(&line).copied().unwrap_or(0)
+    }
+
+    /// Index of the row `row` of the line `line` in the rows shared by both sides, counting the
+    /// filler rows above the line, lines not being wrapped in a diff view
+    pub fn aligned_row(&self, (line, row): (usize, usize)) -> usize {
+        let fillers = self
+            .fillers
+            .iter()
+            .filter(|(y, _)| **y < line)
+            .map(|(_, count)| count)
+            .sum::<usize>();
+
+        line + fillers + row
+    }
+
+    /// Line and row at the shared row `aligned`, see [`SideDiff::aligned_row`]
+    pub fn position(&self, aligned: usize) -> (usize, usize) {
+        let mut rows = 0;
+
+        for line in 0.. {
+            let line_rows = self.fillers(line) + 1;
+            if aligned < rows + line_rows {
+                return (line, aligned - rows);
+            }
+            rows += line_rows;
+        }
+
+        unreachable!("lines are counted until the row is reached")
+    }
+}
+
+/// Hunks changing `left` into `right`, with the diff of each side
+pub fn side_diffs(left: &[String], right: &[String]) -> (Vec<Hunk>, SideDiff, SideDiff) {
+    let hunks = diff_lines(left, right);
+    let (mut left_diff, mut right_diff) = (
+        SideDiff::new(DiffSide::Left),
+        SideDiff::new(DiffSide::Right),
+    );
+
+    for hunk in &hunks {
+        for line in hunk.base_lines.clone() {
+            left_diff.changed.insert(line, Vec::new());
+        }
+        for line in hunk.lines.clone() {
+            right_diff.changed.insert(line, Vec::new());
+        }
+
+        // Lines replaced one by one only differ by the changed chars
+        for (left_line, right_line) in hunk.base_lines.clone().zip(hunk.lines.clone()) {
+            let (left_chars, right_chars) = diff_chars(&left[left_line], &right[right_line]);
+            left_diff.changed.insert(left_line, left_chars);
+            right_diff.changed.insert(right_line, right_chars);
+        }
+
+        let (left_count, right_count) = (hunk.base_lines.len(), hunk.lines.len());
+        if left_count > right_count {
+            *right_diff.fillers.entry(hunk.lines.end).or_default() += left_count - right_count;
+        } else if right_count > left_count {
+            *left_diff.fillers.entry(hunk.base_lines.end).or_default() += right_count - left_count;
+        }
+    }
+
+    (hunks, left_diff, right_diff)
+}
+
+/// Two documents drawn side by side with their differences, see [`Action::Diff`]
+pub struct DiffView {
+    /// Side not being edited, the current editor being the other side
+    pub other: Editor,
+    /// Side of the current editor
+    pub side: DiffSide,
+    /// Hunks changing the left lines into the right ones
+    hunks: Vec<Hunk>,
+    /// Revisions of the left and right documents the diff was computed at
+    revisions: Option<(usize, usize)>,
+}
+
+impl DiffView {
+    /// Compare the documents again if one of them changed
+    fn update(&mut self, current: &mut Editor) {
+        let (left, right) = match self.side {
+            DiffSide::Left => (current, &mut self.other),
+            DiffSide::Right => (&mut self.other, current),
+        };
+
+        let revisions = (left.document.revision(), right.document.revision());
+        if self.revisions == Some(revisions) {
+            return;
+        }
+
+        let (hunks, left_diff, right_diff) =
+            side_diffs(left.document.lines(), right.document.lines());
+        self.hunks = hunks;
+        left.side_diff = Some(left_diff);
+        right.side_diff = Some(right_diff);
+        self.revisions = Some(revisions);
+    }
+
+    /// Lines of the hunk at the line `line` of the current side, on the current side then on
+    /// the other side
+    fn hunk_at(&self, line: usize) -> Option<(Range<usize>, Range<usize>)> {
+        self.hunks.iter().find_map(|hunk| {
+            let (current, other) = match self.side {
+                DiffSide::Left => (hunk.base_lines.clone(), hunk.lines.clone()),
+                DiffSide::Right => (hunk.lines.clone(), hunk.base_lines.clone()),
+            };
+
+            // Lines only on the other side are drawn as fillers above the line after them
+            let at_line = current.contains(&line) || (current.is_empty() && current.start == line);
+            at_line.then_some((current, other))
+        })
+    }
+}
+
+impl App {
+    /// Compare the file at `left` to the file at `right`, for `editor -d`
+    pub fn diff_files(&mut self, left: PathBuf, right: PathBuf) {
+        self.handle_action(Action::Open { path: left });
+        self.start_diff(Editor::from_path(right));
+    }
+
+    /// Compare the current document to another one, see [`Action::Diff`]
+    pub(crate) fn diff_against(&mut self, target: Option<String>) {
+        match self.diff_target(target) {
+            Ok(other) => self.start_diff(other),
+            Err(err) => self.messages.error(format!("diff: {}", err)),
+        }
+        self.draw();
+    }
+
+    /// Editor of the document the current one is compared to, its version on disk, in `HEAD`
+    /// or another file
+    fn diff_target(&mut self, target: Option<String>) -> Result<Editor, String> {
+        let path = self.editor.document.path().map(PathBuf::from);
+        let not_a_file = format!("{} is not a file", self.editor.document.display_name());
+
+        let (name, lines) = match target.as_deref() {
+            Some("HEAD") => {
+                let path = path.ok_or(not_a_file)?;
+                let file = TrackedFile::open(&path).map_err(|err| err.to_string())?;
+                let text = file
+                    .head_text()
+                    .map_err(|err| err.to_string())?
+                    .ok_or_else(|| GitError::Untracked(path.clone()).to_string())?;
+
+                (
+                    format!("{}@HEAD", file.relative().display()),
+                    text.lines().map(String::from).collect(),
+                )
+            }
+            Some(other) => {
+                let other = PathBuf::from(other);
+                let editor = match self
+                    .buffers
+                    .iter()
+                    .position(|editor| editor.document.path() == Some(other.as_path()))
+                {
+                    Some(index) => self.buffers.remove(index).unwrap(),
+                    None => Editor::from_path(other),
+                };
+                return Ok(editor);
+            }
+            None => {
+                let path = path.ok_or(not_a_file)?;
+                let document = Document::from_path(path.clone());
+
+                (
+                    format!("{} (on disk)", path.display()),
+                    document.lines().to_vec(),
+                )
+            }
+        };
+
+        let mut document = Document::from_lines(name, lines);
+        document.set_read_only(true);
+        Ok(Editor::from_document(document))
+    }
+
+    /// Show `other` right of the current editor, with their differences
+    pub(crate) fn start_diff(&mut self, mut other: Editor) {
+        self.close_diff();
+
+        for editor in [&mut self.editor, &mut other] {
+            editor.handle_action(DocumentAction::UnfoldAll);
+        }
+        other.scroll_locked = true;
+
+        self.diff_view = Some(DiffView {
+            other,
+            side: DiffSide::Left,
+            hunks: Vec::new(),
+            revisions: None,
+        });
+    }
+
+    /// Stop comparing the current document, keeping the other one in the buffers if it is a file
+    pub(crate) fn close_diff(&mut self) {
+        let Some(mut view) = self.diff_view.take() else {
+            return;
+        };
+
+        for editor in [&mut self.editor, &mut view.other] {
+            editor.side_diff = None;
+            editor.scroll_locked = false;
+        }
+
+        if view.other.document.path().is_some() {
+            self.buffers.push_front(view.other);
+        }
+    }
+
+    /// Edit the other side of the diff view, its cursor going to the line of the current one
+    pub(crate) fn switch_diff_side(&mut self) {
+        let Some(view) = &mut self.diff_view else {
+            self.messages.info("no diff view");
+            return;
+        };
+        view.update(&mut self.editor);
+
+        let cursor = self.editor.document.selection().end().1;
+        let aligned = self.editor.side_diff.as_ref().map_or(cursor, |diff| {
+            diff.aligned_row((cursor, diff.fillers(cursor)))
+        });
+
+        swap_editors(&mut self.editor, &mut view.other, self.transactions);
+        view.side = match view.side {
+            DiffSide::Left => DiffSide::Right,
+            DiffSide::Right => DiffSide::Left,
+        };
+        self.editor.scroll_locked = false;
+        view.other.scroll_locked = true;
+
+        let (line, _) = self
+            .editor
+            .side_diff
+            .as_ref()
+            .map_or((aligned, 0), |diff| diff.position(aligned));
+        self.editor
+            .handle_action(DocumentAction::Goto { line: line + 1 });
+        self.draw();
+    }
+
+    /// Replace the hunk at the cursor by the lines of the other side, or the lines of the other
+    /// side by the ones of the current side if `put`, see [`Action::DiffGet`]
+    pub(crate) fn copy_hunk(&mut self, put: bool) {
+        let Some(view) = &mut self.diff_view else {
+            self.messages.info("no diff view");
+            return;
+        };
+        view.update(&mut self.editor);
+
+        let cursor = self.editor.document.selection().end().1;
+        let Some((current, other)) = view.hunk_at(cursor) else {
+            self.messages.info("no hunk at the cursor");
+            return;
+        };
+
+        let (from, to, from_lines, to_lines) = match put {
+            true => (&self.editor, &mut view.other, current, other),
+            false => (&view.other, &mut self.editor, other, current),
+        };
+
+        if to.document.read_only() {
+            self.messages
+                .error(format!("{} is read-only", to.document.display_name()));
+            return;
+        }
+
+        let lines = from.document.lines()[from_lines].to_vec();
+        to.document.replace_lines(to_lines, lines);
+        self.draw();
+    }
+
+    /// Draw the current editor and the other side of the diff view in the halves of `rect`, the
+    /// other side scrolling with the current one
+    pub(crate) fn draw_diff_view(&mut self, rect: TermRect) {
+        let Some(view) = &mut self.diff_view else {
+            return;
+        };
+        view.update(&mut self.editor);
+
+        let other = &mut view.other;
+        other.document.set_tab_width(self.settings.tab_width);
+        other.line_numbers = self.settings.line_numbers;
+        // Filler rows align lines, not the rows of wrapped lines
+        self.editor.soft_wrap = false;
+        other.soft_wrap = false;
+
+        let half = rect.width() / 2;
+        let left = TermRect::new(rect.pos, (half, rect.heigth()));
+        let right = TermRect::new(
+            (rect.x() + half, rect.y()),
+            (rect.width() - half, rect.heigth()),
+        );
+        let (current_rect, other_rect) = match view.side {
+            DiffSide::Left => (left, right),
+            DiffSide::Right => (right, left),
+        };
+
+        self.editor
+            .draw(&self.theme, self.term.slice(current_rect), self.mode);
+
+        if let (Some(current_diff), Some(other_diff)) = (&self.editor.side_diff, &other.side_diff) {
+            let aligned = current_diff.aligned_row(self.editor.view_top());
+            other.scroll_to(other_diff.position(aligned), self.editor.view_column());
+        }
+        other.draw(&self.theme, self.term.slice(other_rect), Mode::Normal);
+    }
+}
@@ -1,36 +1,111 @@
-use std::path::PathBuf;
+use std::{ops::Range, path::PathBuf};
 
-use editor_action::DocumentAction;
-use editor_document::Document;
+use editor_action::{DocumentAction, LineNumbers};
+use editor_document::{text, ConflictRegion, Document};
+use editor_git::{FileDiff, GitError, Hunk, LineBlame, TrackedFile};
 use editor_mode::Mode;
-use editor_terminal::{Color, TermRect, TermSlice};
+use editor_terminal::{Color, Style, TermRect, TermSlice};
 use editor_theme::Theme;
 use glam::{u16vec2, U16Vec2};
 
+use crate::{
+    diff_view::{DiffSide, SideDiff},
+    git::GitView,
+    gutter::{self, GutterColumn},
+};
+
+/// Drawn on the rows standing for lines of the other side of a diff view
+const FILLER: char = '╱';
+
 pub struct Editor {
     pub document: Document,
+    /// Column and line at the top left of the view
     offset: (usize, usize),
+    /// Row of the line `offset.1` at the top of the view, when it is wrapped
+    top_row: usize,
+    /// Split long lines in rows instead of scrolling horizontally
+    pub soft_wrap: bool,
+    pub line_numbers: LineNumbers,
+    /// Columns of the gutter, left to right
+    pub gutter: Vec<GutterColumn>,
+    /// Changes of the document since the version in the git index
+    pub diff: Option<FileDiff>,
+    /// Revision of the document the diff was updated at, see [`Document::revision`]
+    diff_revision: Option<usize>,
+    /// Hunk whose base lines are shown below it until the next key
+    pub preview: Option<Hunk>,
+    /// Revision of the document the preview was taken at, it is hidden once the lines change
+    preview_revision: usize,
+    /// Last commit changing each line, shown in the gutter by [`GutterColumn::Blame`]
+    pub blame: Option<Vec<Option<LineBlame>>>,
+    /// Revision of the document the blame was computed at
+    blame_revision: usize,
+    /// What the buffer shows when it was opened by a git command
+    pub git_view: Option<GitView>,
+    /// Differences with the other side when the buffer is a side of a diff view
+    pub side_diff: Option<SideDiff>,
+    /// The view is only scrolled by [`Editor::scroll_to`] instead of following the cursor, for
+    /// the side of a diff view following the other one
+    pub scroll_locked: bool,
+}
+
+/// Part of a line drawn on a row of the view, see [`text::rows`]
+pub struct Row {
+    pub line: usize,
+    /// Index of the row in the rows of the line
+    pub index: usize,
+    /// Char index the row starts at
+    pub start: usize,
+    /// Char index the next row starts at, `None` for the last row of the line
+    pub end: Option<usize>,
+    /// Row drawn above the line standing for lines of the other side of a diff view
+    pub filler: bool,
 }
 
 impl Editor {
     pub fn new_scratch() -> Self {
-        Self {
-            document: Document::new_scratch(),
-            offset: (0, 0).into(),
-        }
+        Self::from_document(Document::new_scratch())
     }
 
     pub fn from_path(path: PathBuf) -> Self {
+        Self::from_document(Document::from_path(path))
+    }
+
+    pub fn from_document(document: Document) -> Self {
         Self {
-            document: Document::from_path(path),
+            diff: document.path().and_then(FileDiff::load),
+            diff_revision: None,
+            preview: None,
+            preview_revision: 0,
+            blame: None,
+            blame_revision: 0,
+            git_view: None,
+            side_diff: None,
+            scroll_locked: false,
+            document,
             offset: (0, 0).into(),
+            top_row: 0,
+            soft_wrap: false,
+            line_numbers: LineNumbers::Absolute,
+            gutter: GutterColumn::DEFAULT.to_vec(),
         }
     }
 
     pub fn draw(&mut self, theme: &Theme, mut term: TermSlice, mode: Mode) {
-        self.update_offset(term.rect().size);
+        self.update_diff();
+        self.update_blame();
+
+        let gutter_width = self
+            .gutter
+            .iter()
+            .map(|column| column.width(self))
+            .sum::<usize>() as u16;
 
-        let gutter_width = (number_width(self.document.lines().len()) + 2) as u16;
+        let code_width = term.rect().width().saturating_sub(gutter_width) as usize;
+        self.document
+            .set_wrap_width(self.soft_wrap.then_some(code_width));
+
+        self.update_offset(term.rect().size);
 
         self.draw_gutter(
             theme,
@@ -49,51 +124,40 @@ impl Editor {
             mode,
         );
 
-        self.draw_code(
-            theme,
-            term.slice(TermRect::new(
-                (gutter_width, 0),
-                term.rect().size.saturating_sub(u16vec2(gutter_width, 1)),
-            )),
+        let code_rect = TermRect::new(
+            (gutter_width, 0),
+            term.rect().size.saturating_sub(u16vec2(gutter_width, 1)),
         );
+        self.draw_code(theme, term.slice(code_rect));
+        self.draw_preview(theme, term.slice(code_rect));
     }
 
     fn draw_gutter(&mut self, theme: &Theme, mut term: TermSlice) {
         let size = term.rect().size;
 
-        let selection = self.document.selection();
-
-        for y in 0..size.y {
-            let line = self.offset.1 + y as usize;
+        let cursor_line = self.document.selection().end().1;
 
-            if line == selection.end().1 {
-                term.set_text_color(theme.gutter_current_line);
-            } else {
-                term.set_text_color(theme.gutter_line);
-            }
-
-            let line_number = match line {
-                _ if line < self.document.lines().len() => {
-                    format!(
-                        " {: >width$} ",
-                        line + 1,
-                        width = size.x.saturating_sub(2) as usize
-                    )
-                }
-
-                _ if line == self.document.lines().len() => {
-                    format!(
-                        " {: >width$} ",
-                        '~',
-                        width = size.x.saturating_sub(2) as usize
-                    )
-                }
-                _ => " ".repeat(size.x as usize),
+        for (y, row) in self.visible_rows(size.y as usize).into_iter().enumerate() {
+            let (color, style) = match row.line == cursor_line {
+                true => (theme.gutter_current_line, theme.gutter_current_line_style),
+                false => (theme.gutter_line, Style::new()),
             };
 
             term.set_background_color(theme.gutter_background);
-            term.write_to((0, y), &line_number);
+            term.set_style(style);
+
+            let mut x = 0;
+            for column in &self.gutter {
+                let width = column.width(self);
+                let (cell, cell_color) = column.cell(self, theme, &row);
+
+                term.set_text_color(cell_color.unwrap_or(color));
+                term.write_to((x, y as u16), &text::pad(&cell, width));
+                x += width as u16;
+            }
         }
+
+        term.reset_style();
     }
 
     fn draw_infos(&mut self, theme: &Theme, mut term: TermSlice, mode: Mode) {
@@ -105,11 +169,14 @@ impl Editor {
         term.set_background_color(theme.code_info_background);
         term.set_text_color(theme.code_info_text);
 
+        term.set_style(theme.code_info_mode_style);
+        term.write_to((0, 0), &format!(" {}", mode_abreviation));
+        term.reset_style();
+
         term.write_to(
-            (0, 0),
+            (text::width(mode_abreviation) as u16 + 1, 0),
             &format!(
-                " {} {} {} {:>width$}:{} ",
-                mode_abreviation,
+                " {} {} {:>width$}:{} ",
                 path,
                 match self.document.dirty() {
                     true => "[+]",
@@ -118,7 +185,10 @@ impl Editor {
                 selection.end().1 + 1,
                 selection.end().0 + 1,
                 width = (term.rect().width() as usize).saturating_sub(
-                    mode_abreviation.len() + path.len() + number_width(selection.end().0 + 1) + 9
+                    text::width(mode_abreviation)
+                        + text::width(&path)
+                        + gutter::number_width(selection.end().0 + 1)
+                        + 9
                 )
             ),
         );
@@ -129,105 +199,494 @@ impl Editor {
 
         let selection = self.document.selection();
         let (min, max) = (selection.min(), selection.max());
+        let conflicts = self.document.conflicts();
 
-        term.set_background_color(theme.code_background);
-        term.set_text_color(theme.code_text);
+        for (y, row) in self.visible_rows(size.y as usize).into_iter().enumerate() {
+            let y = y as u16;
+            let line_index = row.line;
 
-        for y in 0..size.y {
-            let line = self
-                .document
-                .get_line(y as usize + self.offset.1)
-                .unwrap_or("");
+            if row.filler {
+                term.set_background_color(theme.code_background);
+                term.set_text_color(theme.diff_filler);
+                term.write_to((0, y), &FILLER.to_string().repeat(size.x as usize));
+                continue;
+            }
+
+            let line = self.document.get_line(line_index).unwrap_or("");
+            let selected =
+                |x: usize| (min.1, min.0) <= (line_index, x) && (line_index, x) <= (max.1, max.0);
+            let background = match conflicts
+                .iter()
+                .find_map(|conflict| conflict.region(line_index))
+            {
+                Some(ConflictRegion::Marker) => theme.conflict_marker,
+                Some(ConflictRegion::Ours) => theme.conflict_ours,
+                Some(ConflictRegion::Base) => theme.conflict_base,
+                Some(ConflictRegion::Theirs) => theme.conflict_theirs,
+                None => theme.code_background,
+            };
+            let (background, changed_background, changed_chars) =
+                self.diff_colors(theme, line_index, background);
+
+            // Screen column, selection state, background and text of runs of graphemes, the end
+            // of the row being padded with spaces which can be selected like the cursor past the
+            // last char
+            let mut runs: Vec<(u16, bool, Color, String)> = Vec::new();
+            let tab_width = self.document.tab_width();
+            let count = line.chars().count();
+            let row_column = text::column(line, row.start, tab_width);
+            let end = row.end.unwrap_or(count);
+            let end_column = text::column(line, end, tab_width) - row_column;
+
+            // Padding of a wrapped row is selected if the selection goes on to the next row
+            let padding_selected = |i: usize| match row.end {
+                Some(end) => selected(end.saturating_sub(1)) && selected(end),
+                None => selected(count + i),
+            };
 
-            let mut chars = line
-                .chars()
-                .skip(self.offset.0 as usize)
-                .chain(" ".chars().cycle());
+            let graphemes = text::columns(line, tab_width)
+                .skip_while(|(x, ..)| *x < row.start)
+                .take_while(|(x, ..)| *x < end)
+                .map(|(x, column, grapheme, width)| {
+                    let background = match changed_chars.iter().any(|range| range.contains(&x)) {
+                        true => changed_background,
+                        false => background,
+                    };
+                    (
+                        selected(x),
+                        background,
+                        column - row_column,
+                        grapheme,
+                        width,
+                    )
+                })
+                .chain((0..).map(|i| (padding_selected(i), background, end_column + i, " ", 1)));
 
-            let offseted_y = y as usize + self.offset.1;
+            for (selected, background, column, grapheme, width) in graphemes {
+                if column >= self.offset.0 + size.x as usize {
+                    break;
+                }
+                if column + width <= self.offset.0 {
+                    continue;
+                }
 
-            if (min.1..max.1 + 1).contains(&offseted_y) {
-                let before_selection_len = if offseted_y == min.1 {
-                    min.0.saturating_sub(self.offset.0) as usize
-                } else {
-                    0
+                // Tabs are drawn as spaces up to the next tab stop
+                let grapheme = match grapheme {
+                    "\t" => " ".repeat(width),
+                    grapheme => grapheme.to_string(),
                 };
 
-                let under_selection_len = if offseted_y == max.1 {
-                    (size.x as usize).saturating_sub(
-                        before_selection_len
-                            + ((size.x as usize)
-                                .saturating_sub(max.0.saturating_sub(self.offset.0) + 1)),
-                    )
-                } else {
-                    (size.x as usize).saturating_sub(before_selection_len)
+                // Partly hidden by the horizontal offset
+                let (screen_x, grapheme) = match column.checked_sub(self.offset.0) {
+                    Some(screen_x) => (screen_x, grapheme),
+                    None => (0, " ".repeat(column + width - self.offset.0)),
                 };
 
-                let after_selection_len =
-                    (size.x as usize).saturating_sub(before_selection_len + under_selection_len);
-
-                if before_selection_len != 0 {
-                    term.write_to(
-                        (0, y),
-                        &(0..before_selection_len)
-                            .map(|_| chars.next().unwrap_or(' '))
-                            .collect::<String>(),
-                    );
+                match runs.last_mut() {
+                    Some((_, run_selected, run_background, text))
+                        if *run_selected == selected && *run_background == background =>
+                    {
+                        text.push_str(&grapheme)
+                    }
+                    _ => runs.push((screen_x as u16, selected, background, grapheme)),
                 }
+            }
 
-                if under_selection_len != 0 {
+            for (screen_x, selected, background, text) in runs {
+                if selected {
                     term.set_background_color(theme.cursor);
                     term.set_text_color(Color::Black);
-                    term.write_to(
-                        (before_selection_len as u16, y),
-                        &(0..under_selection_len)
-                            .map(|_| chars.next().unwrap_or(' '))
-                            .collect::<String>(),
-                    );
-                    term.set_background_color(theme.code_background);
+                } else {
+                    term.set_background_color(background);
                     term.set_text_color(theme.code_text);
                 }
+                term.write_to((screen_x, y), &text);
+            }
+
+            if let Some(fold) = self.document.closed_fold(line_index) {
+                let summary = format!("⋯ {} lines", fold.end - fold.start);
 
-                if after_selection_len != 0 {
-                    term.write_to(
-                        ((before_selection_len + under_selection_len) as u16, y),
-                        &(0..after_selection_len)
-                            .map(|_| chars.next().unwrap_or(' '))
-                            .collect::<String>(),
-                    );
+                if let Some(screen_x) = (end_column + 1).checked_sub(self.offset.0) {
+                    term.set_background_color(theme.code_background);
+                    term.set_text_color(theme.fold_summary);
+                    term.write_to((screen_x as u16, y), &summary);
                 }
-            } else {
-                term.write_to((0, y), &chars.take(size.x as usize).collect::<String>());
             }
         }
     }
 
+    /// Background of the line `line` and of its changed chars with their ranges when it differs
+    /// from the other side of a diff view, `background` otherwise
+    fn diff_colors<'a>(
+        &'a self,
+        theme: &Theme,
+        line: usize,
+        background: Color,
+    ) -> (Color, Color, &'a [Range<usize>]) {
+        let Some(diff) = &self.side_diff else {
+            return (background, background, &[]);
+        };
+        let Some(changed) = diff.changed.get(&line) else {
+            return (background, background, &[]);
+        };
+
+        match diff.side {
+            DiffSide::Left => (
+                theme.diff_removed_background,
+                theme.diff_removed_inline,
+                changed,
+            ),
+            DiffSide::Right => (
+                theme.diff_added_background,
+                theme.diff_added_inline,
+                changed,
+            ),
+        }
+    }
+
+    /// Draw the lines of the previewed hunk, removed ones then added ones, below it or above
+    /// the bottom of the view if they don't fit
+    fn draw_preview(&self, theme: &Theme, mut term: TermSlice) {
+        let (Some(hunk), Some(diff)) = (&self.preview, &self.diff) else {
+            return;
+        };
+        let (Some(removed), Some(added)) = (
+            diff.base().get(hunk.base_lines.clone()),
+            self.document.lines().get(hunk.lines.clone()),
+        ) else {
+            return;
+        };
+        let size = term.rect().size;
+
+        let removed = removed.iter().map(|line| ('-', line, theme.git_removed));
+        let added = added.iter().map(|line| ('+', line, theme.git_added));
+        let lines = removed.chain(added).collect::<Vec<_>>();
+
+        let last_line = hunk.lines.end.saturating_sub(1).max(hunk.marker_line());
+        let y = self
+            .visible_rows(size.y as usize)
+            .iter()
+            .rposition(|row| row.line == last_line)
+            .map_or(0, |y| y + 1);
+        let height = lines.len().min(size.y as usize);
+        let y = y.min(size.y as usize - height);
+
+        let tab = " ".repeat(self.document.tab_width());
+        term.set_background_color(theme.command_suggestion_background);
+
+        for (i, (sign, line, color)) in lines.into_iter().take(height).enumerate() {
+            let line = format!("{} {}", sign, line.replace('\t', &tab));
+
+            term.set_text_color(color);
+            term.write_to((0, (y + i) as u16), &text::pad(&line, size.x as usize));
+        }
+    }
+
     pub fn handle_action(&mut self, action: DocumentAction) {
         self.document.handle_action(action);
     }
 
+    /// Read again the version of the document in the git index, after its path changed
+    pub fn reload_diff(&mut self) {
+        self.diff = self.document.path().and_then(FileDiff::load);
+        self.diff_revision = None;
+        self.preview = None;
+    }
+
+    /// Compare the lines to the git index version if they or the index changed since the last
+    /// update, hiding the preview of a hunk that may no longer exist
+    fn update_diff(&mut self) {
+        if self.diff.as_ref().is_some_and(FileDiff::index_changed) {
+            self.reload_diff();
+        }
+
+        let revision = self.document.revision();
+        if self.preview_revision != revision {
+            self.preview = None;
+        }
+
+        if let Some(diff) = &mut self.diff {
+            if self.diff_revision != Some(revision) {
+                diff.update(self.document.lines());
+                self.diff_revision = Some(revision);
+            }
+        }
+    }
+
+    /// Show the blame column in the gutter, or hide it, returns whether it is shown
+    pub fn toggle_blame(&mut self) -> Result<bool, GitError> {
+        if self.blame.is_some() {
+            self.blame = None;
+            self.gutter.retain(|column| *column != GutterColumn::Blame);
+            return Ok(false);
+        }
+
+        self.blame = Some(self.compute_blame()?);
+        self.blame_revision = self.document.revision();
+        self.gutter.insert(0, GutterColumn::Blame);
+        Ok(true)
+    }
+
+    /// Last commit changing the line of the cursor, `None` if it changed since `HEAD`
+    pub fn blame_at_cursor(&mut self) -> Result<Option<LineBlame>, GitError> {
+        self.update_blame();
+        let cursor = self.document.selection().end().1;
+
+        let blame = match &self.blame {
+            Some(blame) => blame.get(cursor).cloned().flatten(),
+            None => self.compute_blame()?.get(cursor).cloned().flatten(),
+        };
+        Ok(blame)
+    }
+
+    fn compute_blame(&self) -> Result<Vec<Option<LineBlame>>, GitError> {
+        let path = self.document.path().ok_or_else(|| {
+            GitError::NotInRepository(PathBuf::from(self.document.display_name()))
+        })?;
+
+        TrackedFile::open(path)?.blame(self.document.lines())
+    }
+
+    /// Blame the lines again if they changed since it was computed, keeping the last blame if
+    /// it fails
+    fn update_blame(&mut self) {
+        if self.blame.is_none() || self.blame_revision == self.document.revision() {
+            return;
+        }
+
+        if let Ok(blame) = self.compute_blame() {
+            self.blame = Some(blame);
+        }
+        self.blame_revision = self.document.revision();
+    }
+
+    /// Hunk of the git diff shown on the line of the cursor
+    fn hunk_at_cursor(&mut self) -> Option<Hunk> {
+        self.update_diff();
+
+        let cursor = self.document.selection().end().1;
+        self.diff.as_ref()?.hunk_at(cursor).cloned()
+    }
+
+    /// Show the hunk at the cursor, returns whether there was one
+    pub fn preview_hunk(&mut self) -> bool {
+        self.preview = self.hunk_at_cursor();
+        self.preview_revision = self.document.revision();
+        self.preview.is_some()
+    }
+
+    /// Replace the hunk at the cursor by the lines of the git index, returns whether
+    /// there was one
+    pub fn revert_hunk(&mut self) -> bool {
+        let (Some(hunk), Some(diff)) = (self.hunk_at_cursor(), &self.diff) else {
+            return false;
+        };
+
+        let base_lines = diff.base()[hunk.base_lines].to_vec();
+        self.document.replace_lines(hunk.lines, base_lines);
+        self.preview = None;
+        true
+    }
+
+    /// Add the hunk at the cursor to the git index, returns whether there was one
+    pub fn stage_hunk(&mut self) -> Result<bool, GitError> {
+        let (Some(hunk), Some(path)) = (self.hunk_at_cursor(), self.document.path()) else {
+            return Ok(false);
+        };
+
+        let staged = match &self.diff {
+            Some(diff) => {
+                TrackedFile::open(path)?.stage_hunk(diff.base(), &hunk, self.document.lines())
+            }
+            None => return Ok(false),
+        };
+
+        // Read the index again even on failure, it may have changed since the diff was made
+        self.reload_diff();
+        staged.map(|()| true)
+    }
+
+    /// Remove the changes of the line at the cursor from the git index, returns whether
+    /// the line was changed in the index
+    pub fn unstage_hunk(&mut self) -> Result<bool, GitError> {
+        self.update_diff();
+        let (Some(diff), Some(path)) = (&self.diff, self.document.path()) else {
+            return Ok(false);
+        };
+
+        let line = diff.base_line(self.document.selection().end().1);
+        let unstaged = TrackedFile::open(path)?.unstage_hunk(line)?;
+        self.reload_diff();
+        Ok(unstaged)
+    }
+
+    /// Move the cursor to the next hunk of the git diff after it, or the previous one before it,
+    /// returns whether there was one
+    pub fn goto_hunk(&mut self, next: bool) -> bool {
+        self.update_diff();
+
+        let cursor = self.document.selection().end().1;
+        let lines = self
+            .diff
+            .iter()
+            .flat_map(|diff| diff.hunks())
+            .map(|hunk| hunk.marker_line());
+
+        let line = match next {
+            true => lines.filter(|line| *line > cursor).min(),
+            false => lines.filter(|line| *line < cursor).max(),
+        };
+
+        if let Some(line) = line {
+            self.handle_action(DocumentAction::Goto { line: line + 1 });
+        }
+        line.is_some()
+    }
+
+    /// Rows of the view from the top, see [`Row`]
+    fn visible_rows(&self, height: usize) -> Vec<Row> {
+        let mut rows = Vec::new();
+        let mut line = self.offset.1;
+        let mut skipped = self.top_row;
+
+        while rows.len() < height {
+            rows.extend(self.line_rows(line).into_iter().skip(skipped));
+
+            line += 1;
+            skipped = 0;
+        }
+
+        rows.truncate(height);
+        rows
+    }
+
+    /// Rows of the line `line`, the filler rows above it in a diff view then its wrapped rows
+    fn line_rows(&self, line: usize) -> Vec<Row> {
+        let starts = self.row_starts(line);
+        let fillers = match &self.side_diff {
+            Some(diff) if !starts.is_empty() => diff.fillers(line),
+            _ => 0,
+        };
+
+        let fillers = (0..fillers).map(|_| Row {
+            line,
+            index: 0,
+            start: 0,
+            end: None,
+            filler: true,
+        });
+        let rows = starts.iter().enumerate().map(|(index, start)| Row {
+            line,
+            index,
+            start: *start,
+            end: starts.get(index + 1).copied(),
+            filler: false,
+        });
+
+        fillers.chain(rows).collect()
+    }
+
+    /// Char indices the rows of the line `line` start at, see [`text::rows`]
+    ///
+    /// Lines hidden by a closed fold have no row and the first line of a closed fold isn't
+    /// wrapped, its summary following it
+    fn row_starts(&self, line: usize) -> Vec<usize> {
+        if self.document.is_hidden(line) {
+            return Vec::new();
+        }
+        if self.document.closed_fold(line).is_some() {
+            return vec![0];
+        }
+
+        text::rows(
+            self.document.get_line(line).unwrap_or(""),
+            self.document.layout(),
+        )
+    }
+
+    /// Line and row `count` rows above the row `row` of the line `line`
+    fn rows_above(&self, (mut line, mut row): (usize, usize), count: usize) -> (usize, usize) {
+        for _ in 0..count {
+            if row > 0 {
+                row -= 1;
+            } else if let Some(previous) = (0..line).rev().find(|y| !self.document.is_hidden(*y)) {
+                line = previous;
+                row = self.line_rows(line).len() - 1;
+            } else {
+                break;
+            }
+        }
+
+        (line, row)
+    }
+
+    /// Line and row at the top of the view, the row counting the filler rows of the line
+    pub fn view_top(&self) -> (usize, usize) {
+        (self.offset.1, self.top_row)
+    }
+
+    /// Column at the left of the view
+    pub fn view_column(&self) -> usize {
+        self.offset.0
+    }
+
+    /// Scroll the view to show the row `row` of the line `line` at the top and `column` at the
+    /// left, see [`Editor::scroll_locked`]
+    pub fn scroll_to(&mut self, (line, row): (usize, usize), column: usize) {
+        self.offset = (column, line);
+        self.top_row = row;
+    }
+
     /// Update `self.offset` if `self.document.cursor()` is near edges
+    ///
+    /// The horizontal offset is in columns and stays at 0 when lines are wrapped,
+    /// the view scrolling by rows
     fn update_offset(&mut self, size: U16Vec2) {
-        let cursor = self.document.selection().end();
+        if self.scroll_locked {
+            return;
+        }
 
-        if cursor.0 + 7 > self.offset.0 + size.x as usize {
-            self.offset.0 = (cursor.0 + 7).saturating_sub(size.x as usize);
+        let (x, y) = self.document.selection().end();
+        let line = self.document.get_line(y).unwrap_or("");
+        let column = text::column(line, x, self.document.tab_width());
+
+        if self.soft_wrap {
+            self.offset.0 = 0;
+        } else {
+            if column + 7 > self.offset.0 + size.x as usize {
+                self.offset.0 = (column + 7).saturating_sub(size.x as usize);
+            }
+
+            if column < self.offset.0 + 5 {
+                self.offset.0 = column.saturating_sub(5);
+            }
         }
 
-        if cursor.1 + 4 > self.offset.1 + size.y as usize {
-            self.offset.1 = (cursor.1 + 4).saturating_sub(size.y as usize);
+        // A fold closed over the top line, the top goes to the start of the fold
+        if self.document.is_hidden(self.offset.1) {
+            self.offset.1 = (0..self.offset.1)
+                .rev()
+                .find(|y| !self.document.is_hidden(*y))
+                .unwrap_or(0);
+            self.top_row = 0;
         }
 
-        if cursor.0 < self.offset.0 + 5 {
-            self.offset.0 = cursor.0.saturating_sub(5);
+        // The rows of the top line may have changed since the last draw
+        self.top_row = self
+            .top_row
+            .min(self.line_rows(self.offset.1).len().saturating_sub(1));
+
+        let fillers = self.line_rows(y).iter().filter(|row| row.filler).count();
+        let cursor = (y, fillers + text::row_of(&self.row_starts(y), x));
+
+        let lowest_top = self.rows_above(cursor, (size.y as usize).saturating_sub(4));
+        if (self.offset.1, self.top_row) < lowest_top {
+            (self.offset.1, self.top_row) = lowest_top;
         }
 
-        if cursor.1 < self.offset.1 + 4 {
-            self.offset.1 = cursor.1.saturating_sub(4);
+        let highest_top = self.rows_above(cursor, 4);
+        if (self.offset.1, self.top_row) > highest_top {
+            (self.offset.1, self.top_row) = highest_top;
         }
     }
 }
-
-fn number_width(number: usize) -> usize {
-    number.checked_ilog10().unwrap_or(0) as usize + 1
-}
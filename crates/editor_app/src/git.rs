@@ -0,0 +1,296 @@
+use std::path::{Path, PathBuf};
+
+use editor_action::{Action, DocumentAction, GitCommand};
+use editor_git::{CommitInfo, FileStatus, GitError, GitRepository, TrackedFile};
+
+use crate::{editor::Editor, App};
+
+/// Lines of the git status buffer above the list of files
+const STATUS_HEADER: usize = 3;
+
+/// What a buffer opened by a git command shows, for the actions on its lines
+pub enum GitView {
+    /// Changed files of the repository at `workdir`, listed after [`STATUS_HEADER`] lines
+    Status {
+        workdir: PathBuf,
+        files: Vec<FileStatus>,
+    },
+    /// Message of the next commit of the repository at `workdir`, committed when the buffer is
+    /// closed
+    CommitMessage { workdir: PathBuf },
+    /// Commits changing the file at `path`, one per line
+    Log {
+        path: PathBuf,
+        commits: Vec<CommitInfo>,
+    },
+}
+
+impl App {
+    /// Run a git command, see [`Action::Git`]
+    pub(crate) fn handle_git(&mut self, command: GitCommand) {
+        let result = match command {
+            GitCommand::Status => self
+                .current_repository()
+                .and_then(|repository| self.show_git_status(repository.workdir())),
+            GitCommand::Add => self.git_add(),
+            GitCommand::Commit => self.git_commit(),
+            GitCommand::Log => self.git_log(),
+            GitCommand::Init => self.git_init(),
+        };
+
+        if let Err(err) = result {
+            self.messages
+                .error(format!("git {}: {}", command.as_str(), err));
+        }
+        self.draw();
+    }
+
+    /// Stage or unstage the file at the cursor of the git status buffer, see
+    /// [`Action::ToggleStaged`]
+    pub(crate) fn toggle_staged(&mut self) {
+        let Some(GitView::Status { workdir, files }) = &self.editor.git_view else {
+            self.messages.info("not in the git status buffer");
+            return;
+        };
+
+        let line = self.editor.document.selection().end().1;
+        let Some(file) = line.checked_sub(STATUS_HEADER).and_then(|i| files.get(i)) else {
+            self.messages.info("no file at the cursor");
+            return;
+        };
+
+        let workdir = workdir.clone();
+        let result =
+            TrackedFile::open(&workdir.join(&file.path)).and_then(|tracked| match file.unstaged {
+                Some(_) => tracked.stage(),
+                None => tracked.unstage(),
+            });
+
+        match result.and_then(|()| self.show_git_status(&workdir)) {
+            Ok(()) => {
+                self.editor
+                    .handle_action(DocumentAction::Goto { line: line + 1 });
+                self.reload_diffs();
+            }
+            Err(err) => self.messages.error(format!("toggle_staged: {}", err)),
+        }
+        self.draw();
+    }
+
+    /// Open the file or the commit at the cursor of a git buffer, see [`Action::OpenEntry`]
+    pub(crate) fn open_entry(&mut self) {
+        let line = self.editor.document.selection().end().1;
+
+        match &self.editor.git_view {
+            Some(GitView::Status { workdir, files }) => {
+                match line.checked_sub(STATUS_HEADER).and_then(|i| files.get(i)) {
+                    Some(file) => {
+                        let path = workdir.join(&file.path);
+                        self.handle_action(Action::Open { path });
+                    }
+                    None => self.messages.info("no file at the cursor"),
+                }
+            }
+            Some(GitView::Log { path, commits }) => match commits.get(line) {
+                Some(commit) => {
+                    let (path, commit) = (path.clone(), commit.clone());
+                    if let Err(err) = self.show_file_at(&path, &commit) {
+                        self.messages.error(format!("open_entry: {}", err));
+                    }
+                }
+                None => self.messages.info("no commit at the cursor"),
+            },
+            _ => self.messages.info("nothing to open at the cursor"),
+        }
+        self.draw();
+    }
+
+    /// Commit the message of the buffer being closed if it is a commit message
+    pub(crate) fn commit_on_close(&mut self) {
+        let Some(GitView::CommitMessage { workdir }) = &self.editor.git_view else {
+            return;
+        };
+
+        let message = self.editor.document.lines().join("\n");
+        match GitRepository::discover(workdir).and_then(|repository| repository.commit(&message)) {
+            Ok(id) => {
+                let summary = message
+                    .lines()
+                    .find(|line| !line.is_empty() && !line.starts_with('#'));
+                self.messages
+                    .info(format!("committed {} {}", id, summary.unwrap_or_default()));
+                self.reload_diffs();
+            }
+            Err(GitError::EmptyMessage) => self.messages.info("commit aborted, empty message"),
+            Err(err) => self.messages.error(format!("git commit: {}", err)),
+        }
+    }
+
+    /// Show the last commit changing each line of the current file, see [`Action::Blame`]
+    pub(crate) fn toggle_blame(&mut self) {
+        if let Err(err) = self.editor.toggle_blame() {
+            self.messages.error(format!("blame: {}", err));
+        }
+        self.draw();
+    }
+
+    /// Open the commit that last changed the line at the cursor, see [`Action::ShowLineCommit`]
+    pub(crate) fn show_line_commit(&mut self) {
+        match self.editor.blame_at_cursor() {
+            Ok(Some(blame)) => match self
+                .current_repository()
+                .and_then(|repository| repository.show_commit(&blame.id))
+            {
+                Ok(lines) => self.show_virtual(&format!("commit {}", blame.short_id), lines),
+                Err(err) => self.messages.error(format!("show_line_commit: {}", err)),
+            },
+            Ok(None) => self.messages.info("the line isn't committed"),
+            Err(err) => self.messages.error(format!("show_line_commit: {}", err)),
+        }
+        self.draw();
+    }
+
+    /// Repository of the current file, or of the working directory for buffers without a path
+    fn current_repository(&self) -> Result<GitRepository, GitError> {
+        match &self.editor.git_view {
+            Some(GitView::Status { workdir, .. } | GitView::CommitMessage { workdir }) => {
+                GitRepository::discover(workdir)
+            }
+            _ => GitRepository::discover(&self.current_directory()),
+        }
+    }
+
+    fn current_directory(&self) -> PathBuf {
+        match self.editor.document.path().and_then(Path::parent) {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    /// Path of the current file, or of the file whose log is shown
+    fn current_path(&self) -> Option<PathBuf> {
+        match &self.editor.git_view {
+            Some(GitView::Log { path, .. }) => Some(path.clone()),
+            _ => self.editor.document.path().map(Path::to_path_buf),
+        }
+    }
+
+    fn current_file(&self) -> Result<TrackedFile, GitError> {
+        match self.current_path() {
+            Some(path) => TrackedFile::open(&path),
+            None => Err(GitError::NotInRepository(PathBuf::from(
+                self.editor.document.display_name(),
+            ))),
+        }
+    }
+
+    fn show_git_status(&mut self, workdir: &Path) -> Result<(), GitError> {
+        let files = GitRepository::discover(workdir)?.status()?;
+
+        let mut lines = vec![
+            format!("Changes of {}", workdir.display()),
+            "`-` stages or unstages the file at the cursor, enter opens it".to_string(),
+            String::new(),
+        ];
+        lines.extend(
+            files
+                .iter()
+                .map(|file| format!("{} {}", file.code(), file.path.display())),
+        );
+        if files.is_empty() {
+            lines.push("nothing to commit, working tree clean".to_string());
+        }
+
+        self.show_virtual("git status", lines);
+        self.editor.git_view = Some(GitView::Status {
+            workdir: workdir.to_path_buf(),
+            files,
+        });
+
+        Ok(())
+    }
+
+    fn git_add(&mut self) -> Result<(), GitError> {
+        let file = self.current_file()?;
+        file.stage()?;
+
+        self.messages
+            .info(format!("staged {}", file.relative().display()));
+        self.editor.reload_diff();
+        Ok(())
+    }
+
+    /// Open the commit message buffer, the commit is made when it is closed after being written
+    fn git_commit(&mut self) -> Result<(), GitError> {
+        let repository = self.current_repository()?;
+        let path = repository.prepare_message()?;
+
+        let is_message = |editor: &Editor| editor.document.path() == Some(path.as_path());
+        self.buffers.retain(|editor| !is_message(editor));
+
+        let mut editor = Editor::from_path(path.clone());
+        editor.git_view = Some(GitView::CommitMessage {
+            workdir: repository.workdir().to_path_buf(),
+        });
+        if is_message(&self.editor) {
+            self.replace_editor(editor);
+        } else {
+            self.show_editor(editor);
+        }
+
+        Ok(())
+    }
+
+    fn git_log(&mut self) -> Result<(), GitError> {
+        let file = self.current_file()?;
+        let commits = file.log()?;
+
+        let lines = commits
+            .iter()
+            .map(|commit| format!("{} {} ({})", commit.short_id, commit.summary, commit.author))
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            self.messages
+                .info(format!("{} has no commits", file.relative().display()));
+            return Ok(());
+        }
+
+        let path = self.current_path().unwrap_or_default();
+        self.show_virtual(&format!("git log {}", file.relative().display()), lines);
+        self.editor.git_view = Some(GitView::Log { path, commits });
+
+        Ok(())
+    }
+
+    fn git_init(&mut self) -> Result<(), GitError> {
+        let directory = self.current_directory();
+        let repository = GitRepository::init(&directory)?;
+
+        self.messages.info(format!(
+            "initialized an empty git repository in {}",
+            repository.workdir().display()
+        ));
+        Ok(())
+    }
+
+    /// Show the version of the file at `path` in `commit`
+    fn show_file_at(&mut self, path: &Path, commit: &CommitInfo) -> Result<(), GitError> {
+        let file = TrackedFile::open(path)?;
+        let Some(text) = file.text_at(&commit.id)? else {
+            return Err(GitError::Untracked(path.to_path_buf()));
+        };
+
+        let name = format!("{}@{}", file.relative().display(), commit.short_id);
+        self.show_virtual(&name, text.lines().map(String::from).collect());
+
+        Ok(())
+    }
+
+    /// Compare the files of all the buffers to the git index again, after it changed
+    fn reload_diffs(&mut self) {
+        self.editor.reload_diff();
+        for editor in &mut self.buffers {
+            editor.reload_diff();
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use editor_action::LineNumbers;
+use editor_document::text;
+use editor_git::Change;
+use editor_terminal::Color;
+use editor_theme::Theme;
+
+use crate::editor::{Editor, Row};
+
+/// Drawn instead of the line number on the rows continuing a wrapped line
+const CONTINUATION_MARKER: char = '↪';
+
+/// Drawn on the first line of a closed fold
+const FOLD_MARKER: char = '▸';
+
+/// Drawn on the lines added or modified since the version in the git index
+const CHANGE_MARKER: char = '▎';
+
+/// Drawn on the line above lines removed since the version in the git index
+const REMOVAL_MARKER: char = '▁';
+
+/// Width of the blame column, a short commit id and an author name
+const BLAME_WIDTH: usize = 20;
+
+/// Column of the gutter drawn left of the code, see [`Editor::gutter`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GutterColumn {
+    /// Last commit changing each line, see [`Editor::blame`]
+    Blame,
+    /// Changes since the version of the file in the git index
+    GitDiff,
+    /// Number of each line, see [`LineNumbers`]
+    LineNumbers,
+    /// Marker of the lines starting a closed fold
+    Folds,
+}
+
+impl GutterColumn {
+    /// Line numbers between the git changes and the fold markers
+    pub const DEFAULT: [Self; 3] = [Self::GitDiff, Self::LineNumbers, Self::Folds];
+
+    pub fn width(&self, editor: &Editor) -> usize {
+        match self {
+            Self::Blame => BLAME_WIDTH,
+            Self::GitDiff | Self::Folds => 1,
+            Self::LineNumbers => number_width(editor.document.lines().len()),
+        }
+    }
+
+    /// Text of the column on `row` and its color, the one of the line numbers if `None`
+    pub fn cell(&self, editor: &Editor, theme: &Theme, row: &Row) -> (String, Option<Color>) {
+        let document = &editor.document;
+        if row.filler {
+            return (String::new(), None);
+        }
+
+        match self {
+            Self::Blame => match editor.blame.as_ref().and_then(|blame| blame.get(row.line)) {
+                _ if row.index > 0 => (String::new(), None),
+                Some(Some(blame)) => {
+                    let cell = format!("{} {}", blame.short_id, blame.author);
+                    // Authors may have wide characters, keep a column before the code
+                    let cell = text::truncate(&cell, BLAME_WIDTH - 1).to_string();
+                    (cell, Some(theme.fold_summary))
+                }
+                Some(None) => ("not committed".to_string(), Some(theme.fold_summary)),
+                None => (String::new(), None),
+            },
+            Self::GitDiff => match editor
+                .diff
+                .as_ref()
+                .and_then(|diff| diff.change_at(row.line))
+            {
+                Some(Change::Added) => (CHANGE_MARKER.to_string(), Some(theme.git_added)),
+                Some(Change::Modified) => (CHANGE_MARKER.to_string(), Some(theme.git_modified)),
+                Some(Change::Removed) => (REMOVAL_MARKER.to_string(), Some(theme.git_removed)),
+                None => (String::new(), None),
+            },
+            Self::LineNumbers => {
+                let number = match row.line {
+                    _ if row.index > 0 => CONTINUATION_MARKER.to_string(),
+                    line if line < document.lines().len() => line_number(editor, line).to_string(),
+                    line if line == document.lines().len() => "~".to_string(),
+                    _ => String::new(),
+                };
+
+                (format!("{: >1$}", number, self.width(editor)), None)
+            }
+            Self::Folds => match document.closed_fold(row.line) {
+                Some(_) => (FOLD_MARKER.to_string(), Some(theme.fold_summary)),
+                None => (String::new(), None),
+            },
+        }
+    }
+}
+
+/// Number displayed for the line `line`, relative ones counting the visible lines to the cursor
+fn line_number(editor: &Editor, line: usize) -> usize {
+    let cursor = editor.document.selection().end().1;
+    let relative = (line.min(cursor) + 1..=line.max(cursor))
+        .filter(|y| !editor.document.is_hidden(*y))
+        .count();
+
+    match editor.line_numbers {
+        LineNumbers::Absolute => line + 1,
+        LineNumbers::Relative => relative,
+        LineNumbers::Hybrid if line == cursor => line + 1,
+        LineNumbers::Hybrid => relative,
+    }
+}
+
+pub fn number_width(number: usize) -> usize {
+    number.checked_ilog10().unwrap_or(0) as usize + 1
+}
@@ -0,0 +1,64 @@
+use editor_action::{Action, ActionInfo};
+use editor_input::Inputs;
+
+/// Lines of the `:help` buffer, every public action with its arguments and key bindings
+pub fn help_lines(inputs: &Inputs) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for info in Action::action_infos() {
+        lines.push(info.usage());
+
+        for line in info.description.lines() {
+            lines.push(format!("    {}", line));
+        }
+
+        for argument in &info.arguments {
+            lines.push(format!("    {}: {}", argument.name, argument.description));
+        }
+
+        let bindings = inputs
+            .bindings()
+            .filter(|(_, _, action)| action.command_name() == info.names[0])
+            .map(|(mode, input, _)| format!("{} {}", mode, input))
+            .collect::<Vec<_>>();
+
+        if !bindings.is_empty() {
+            lines.push(format!("    keys: {}", bindings.join(", ")));
+        }
+
+        lines.push(String::new());
+    }
+
+    lines
+}
+
+/// Line of `help_lines` where the documentation of `info` starts
+pub fn help_line_of(lines: &[String], info: &ActionInfo) -> Option<usize> {
+    let usage = info.usage();
+
+    lines.iter().position(|line| *line == usage)
+}
+
+/// Lines of the `:keymap` buffer, the key bindings of each mode
+pub fn keymap_lines(inputs: &Inputs) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current_mode = None;
+
+    for (mode, input, action) in inputs.bindings() {
+        if current_mode != Some(mode) {
+            if current_mode.is_some() {
+                lines.push(String::new());
+            }
+            lines.push(mode.to_string());
+            current_mode = Some(mode);
+        }
+
+        lines.push(format!(
+            "    {:<12}{}",
+            input.to_string(),
+            action.command_name()
+        ));
+    }
+
+    lines
+}
@@ -0,0 +1,188 @@
+use std::{env, fs, path::PathBuf};
+
+use log::error;
+
+/// Maximum number of entries kept in a history
+const MAX_ENTRIES: usize = 1000;
+
+/// Previously validated prompt lines, persisted in the state directory
+///
+/// Each prompt keeps its own list in a separate file, see [`History::load`]
+pub struct History {
+    /// Oldest entry first
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// Load the history named `name` from the state directory
+    pub fn load(name: &str) -> Self {
+        Self::load_path(state_dir().map(|dir| dir.join(format!("{}_history", name))))
+    }
+
+    /// Load the history persisted at `path`, if any
+    fn load_path(path: Option<PathBuf>) -> Self {
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| content.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        Self { entries, path }
+    }
+
+    /// Create an empty history that isn't persisted
+    pub fn in_memory() -> Self {
+        Self {
+            entries: Vec::new(),
+            path: None,
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Add `entry` as the most recent one, removing its previous occurrence
+    pub fn push(&mut self, entry: String) {
+        if entry.is_empty() {
+            return;
+        }
+
+        self.entries.retain(|other| *other != entry);
+        self.entries.push(entry);
+
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+
+        self.save();
+    }
+
+    /// Index of the most recent entry before `before` starting with `prefix`
+    pub fn previous(&self, before: Option<usize>, prefix: &str) -> Option<usize> {
+        self.entries[..before.unwrap_or(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.starts_with(prefix))
+    }
+
+    /// Index of the oldest entry after `after` starting with `prefix`
+    pub fn next(&self, after: usize, prefix: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .skip(after + 1)
+            .position(|entry| entry.starts_with(prefix))
+            .map(|i| i + after + 1)
+    }
+
+    /// Index of the most recent entry before `before` containing `query`
+    pub fn search(&self, before: Option<usize>, query: &str) -> Option<usize> {
+        self.entries[..before.unwrap_or(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        if let Some(parent) = path.parent() {
+            if let Err(err) = fs::create_dir_all(parent) {
+                error!(
+                    "Failed to create state directory {}, {:?}",
+                    parent.display(),
+                    err
+                );
+                return;
+            }
+        }
+
+        if let Err(err) = fs::write(path, self.entries.join("\n")) {
+            error!("Failed to write history to {}, {:?}", path.display(), err);
+        }
+    }
+}
+
+/// `$XDG_STATE_HOME/editor`, defaulting to `~/.local/state/editor`
+fn state_dir() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .map(|dir| dir.join("editor"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str]) -> History {
+        let mut history = History::in_memory();
+        for entry in entries {
+            history.push(entry.to_string());
+        }
+        history
+    }
+
+    #[test]
+    fn navigates_the_entries_starting_with_a_prefix() {
+        let history = history(&["open a", "quit", "open b", "write"]);
+
+        assert_eq!(history.previous(None, "op"), Some(2));
+        assert_eq!(history.previous(Some(2), "op"), Some(0));
+        assert_eq!(history.previous(Some(0), "op"), None);
+        assert_eq!(history.next(0, "op"), Some(2));
+        assert_eq!(history.next(2, "op"), None);
+        assert_eq!(history.previous(None, ""), Some(3));
+        assert_eq!(history.previous(None, "missing"), None);
+    }
+
+    #[test]
+    fn keeps_the_most_recent_occurrence() {
+        let history = history(&["a", "b", "a", "", "c", "b"]);
+
+        assert_eq!(history.entries(), ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn drops_the_oldest_entries_past_the_maximum() {
+        let mut history = History::in_memory();
+        for i in 0..MAX_ENTRIES + 5 {
+            history.push(i.to_string());
+        }
+
+        assert_eq!(history.entries().len(), MAX_ENTRIES);
+        assert_eq!(history.entries()[0], "5");
+        assert_eq!(
+            history.entries()[MAX_ENTRIES - 1],
+            (MAX_ENTRIES + 4).to_string()
+        );
+    }
+
+    #[test]
+    fn persists_the_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/command_history");
+
+        let mut history = History::load_path(Some(path.clone()));
+        assert!(history.entries().is_empty());
+        history.push("open a".to_string());
+        history.push("quit".to_string());
+        history.push("open a".to_string());
+
+        let history = History::load_path(Some(path));
+        assert_eq!(history.entries(), ["quit", "open a"]);
+    }
+
+    #[test]
+    fn searches_the_entries_containing_a_query() {
+        let history = history(&["open notes.txt", "write", "open main.rs", "quit"]);
+
+        assert_eq!(history.search(None, "en"), Some(2));
+        assert_eq!(history.search(Some(2), "en"), Some(0));
+        assert_eq!(history.search(Some(0), "en"), None);
+        assert_eq!(history.search(None, "rit"), Some(1));
+        assert_eq!(history.search(None, ""), Some(3));
+    }
+}
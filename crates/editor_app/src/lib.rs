@@ -1,16 +1,49 @@
+mod buffers;
 mod command_bar;
+mod diff_view;
 mod editor;
+mod git;
+mod gutter;
+mod help;
+mod history;
+mod messages;
+mod registers;
+mod remote;
+mod settings;
+#[cfg(test)]
+mod tests;
+
+use std::{collections::VecDeque, fs};
 
 use command_bar::CommandBar;
-use editor_action::Action;
-use editor_input::Inputs;
+use editor_action::{Action, ActionInfo};
+use editor_document::{text, WriteError, WriteReport};
+use editor_input::{Input, Inputs};
 use editor_mode::{Focused, Mode};
-use editor_terminal::{Event, Term, TermRect};
+use editor_remote::RemoteServer;
+use editor_script::Scripts;
+use editor_terminal::{Event, KeyCode, KeyEventKind, Term, TermRect};
 use editor_theme::Theme;
 use glam::u16vec2;
-use log::warn;
+use log::debug;
+
+use crate::{
+    diff_view::DiffView,
+    editor::Editor,
+    history::History,
+    messages::Messages,
+    registers::{Registers, DEFAULT_REGISTER, MACRO_REGISTER},
+    settings::Settings,
+};
+
+/// Maximum number of macros replaying at the same time, stops macros replaying themselves
+const MAX_MACRO_DEPTH: usize = 16;
 
-use crate::editor::Editor;
+/// Maximum number of script commands running at the same time, stops commands running themselves
+const MAX_COMMAND_DEPTH: usize = 16;
+
+/// Largest count applied to an action, larger ones are clamped to it
+const MAX_COUNT: usize = 10_000;
 
 pub struct App {
     should_quit: bool,
@@ -20,20 +53,144 @@ pub struct App {
     theme: Theme,
     inputs: Inputs,
     editor: Editor,
+    /// Buffers other than the current one, in switching order
+    buffers: VecDeque<Editor>,
     command_bar: CommandBar,
+    messages: Messages,
+    show_history: bool,
+    settings: Settings,
+    /// Count typed before an action, see [`Action::CountDigit`]
+    count: Option<usize>,
+    /// Actions of the change being made in insert mode, see [`Action::RepeatLastChange`]
+    change: Option<Vec<Action>>,
+    last_change: Vec<Action>,
+    confirmation: Option<Confirmation>,
+    registers: Registers,
+    /// Register chosen for the next action, see [`Action::SelectRegister`]
+    register: Option<char>,
+    /// The next typed character names a register
+    awaiting_register: bool,
+    /// Register and actions of the macro being recorded, see [`Action::RecordMacro`]
+    recording: Option<(char, Vec<(Action, usize)>)>,
+    /// Number of macros being replayed
+    macro_depth: usize,
+    /// Number of script commands running, see [`Action::UserCommand`]
+    command_depth: usize,
+    /// Number of actions being repeated by a count, drawing waits for the last repetition
+    repeating: usize,
+    /// Undo transactions open on the current document, moved to the new current document when
+    /// the buffer changes, see [`App::replace_editor`]
+    transactions: usize,
+    scripts: Scripts,
+    /// Socket of the remote control, see [`editor_remote`]
+    remote: Option<RemoteServer>,
+    /// Document compared side by side with the current one, see [`Action::Diff`]
+    diff_view: Option<DiffView>,
+}
+
+/// A yes/no question displayed in the message row,
+/// `actions` are executed if the user answers yes
+struct Confirmation {
+    prompt: String,
+    actions: Vec<Action>,
 }
 
 impl App {
     pub fn new() -> Self {
+        let (scripts, errors) = Scripts::load();
+
+        let term = Term::new();
+
+        // Remote requests interrupt the wait for terminal events
+        let waker = term.waker();
+        let wake = move || {
+            if let Some(waker) = &waker {
+                waker.wake();
+            }
+        };
+        let (remote, remote_error) = match RemoteServer::start(editor_remote::socket_path(), wake) {
+            Ok(remote) => (Some(remote), None),
+            Err(err) => (None, Some(err)),
+        };
+
+        let mut app = Self::with_parts(term, CommandBar::new(), scripts, remote);
+
+        for err in errors {
+            app.messages.error(err);
+        }
+        if let Some(err) = remote_error {
+            app.messages
+                .warning(format!("remote control disabled, {}", err));
+        }
+        app.bind_script_keys();
+
+        app
+    }
+
+    /// Create an app drawing to `term`, usually [`Term::headless`], without scripts, remote
+    /// control or persisted command history so that it doesn't depend on the environment
+    pub fn headless(term: Term) -> Self {
+        Self::with_parts(
+            term,
+            CommandBar::with_history(History::in_memory()),
+            Scripts::empty(),
+            None,
+        )
+    }
+
+    fn with_parts(
+        term: Term,
+        command_bar: CommandBar,
+        scripts: Scripts,
+        remote: Option<RemoteServer>,
+    ) -> Self {
         Self {
             should_quit: false,
             mode: Mode::Normal,
             focused: Focused::Editor,
-            term: Term::new(),
+            term,
             theme: Theme::default(),
             inputs: Inputs::default(),
             editor: Editor::new_scratch(),
-            command_bar: CommandBar::new(),
+            buffers: VecDeque::new(),
+            command_bar,
+            messages: Messages::new(),
+            show_history: false,
+            settings: Settings::default(),
+            count: None,
+            change: None,
+            last_change: Vec::new(),
+            confirmation: None,
+            registers: Registers::default(),
+            register: None,
+            awaiting_register: false,
+            recording: None,
+            macro_depth: 0,
+            command_depth: 0,
+            repeating: 0,
+            transactions: 0,
+            scripts,
+            remote,
+            diff_view: None,
+        }
+    }
+
+    /// Add the key bindings requested by the scripts, once their commands are registered
+    fn bind_script_keys(&mut self) {
+        for binding in self.scripts.bindings() {
+            let Some(input) = Input::parse(&binding.key) else {
+                self.messages
+                    .error(format!("bind: invalid key `{}`", binding.key));
+                continue;
+            };
+
+            let result = Action::parse(&binding.command)
+                .map_err(|err| err.to_string())
+                .and_then(|action| self.inputs.bind(&binding.mode, input, action));
+
+            if let Err(err) = result {
+                self.messages.error(format!("bind: {}", err));
+            }
         }
     }
 
@@ -42,25 +199,91 @@ impl App {
         self.term.flush();
 
         while !self.should_quit {
-            if let Ok(event) = self.term.wait_for_event() {
-                self.handle_event(&event);
+            let event = self.term.wait(self.messages.timeout());
 
-                self.term.flush();
-            };
+            self.update(event.and_then(Result::ok).as_ref());
+        }
+    }
+
+    /// Handle `event` and the pending remote requests,
+    /// redrawing if a message was added or expired, then display the changes
+    pub fn update(&mut self, event: Option<&Event>) {
+        let message_count = self.messages.count();
+
+        if let Some(event) = event {
+            self.handle_event(event);
+        }
+
+        while let Some(request) = self.remote.as_ref().and_then(RemoteServer::try_recv) {
+            self.handle_remote_request(request);
         }
+
+        if self.messages.update() || self.messages.count() != message_count {
+            self.draw();
+        }
+
+        self.term.flush();
     }
 
     fn draw(&mut self) {
-        self.editor
-            .draw(&self.theme, self.term.slice(self.editor_rect()), self.mode);
+        if self.repeating > 0 {
+            return;
+        }
+
+        self.editor.document.set_tab_width(self.settings.tab_width);
+        self.editor.soft_wrap = self.settings.soft_wrap;
+        self.editor.line_numbers = self.settings.line_numbers;
+        match self.diff_view {
+            Some(_) => self.draw_diff_view(self.editor_rect()),
+            None => self
+                .editor
+                .draw(&self.theme, self.term.slice(self.editor_rect()), self.mode),
+        }
         if self.focused == Focused::CommandBar {
             self.command_bar
                 .draw(&self.theme, self.term.slice(self.command_bar_rect()));
+        } else if let Some(confirmation) = &self.confirmation {
+            let mut term = self.term.slice(self.message_rect());
+
+            term.set_background_color(self.theme.command_bar_background);
+            term.set_text_color(self.theme.command_bar_text);
+            term.write_to(
+                (0, 0),
+                &text::pad(&confirmation.prompt, term.rect().width() as usize),
+            );
+        } else {
+            self.messages
+                .draw_current(&self.theme, self.term.slice(self.message_rect()));
         }
+
+        if self.show_history {
+            self.messages
+                .draw_history(&self.theme, self.term.slice(self.history_rect()));
+        }
+    }
+
+    fn message_visible(&self) -> bool {
+        self.messages.current().is_some() || self.confirmation.is_some()
+    }
+
+    fn message_rect(&self) -> TermRect {
+        TermRect::new(
+            (0, self.term.size().y.saturating_sub(1)),
+            (self.term.size().x, 1),
+        )
+    }
+
+    fn history_rect(&self) -> TermRect {
+        let height = (self.messages.history().len() as u16)
+            .min(self.term.size().y / 2)
+            .max(1);
+        let y = self.term.size().y.saturating_sub(height + 1);
+
+        TermRect::new((0, y), (self.term.size().x, height))
     }
 
     fn editor_rect(&self) -> TermRect {
-        if self.focused == Focused::CommandBar {
+        if self.focused == Focused::CommandBar || self.message_visible() {
             TermRect::new((0, 0), self.term.size().saturating_sub(u16vec2(0, 1)))
         } else {
             TermRect::new((0, 0), self.term.size())
@@ -79,8 +302,62 @@ impl App {
     fn handle_event(&mut self, event: &Event) {
         match event {
             Event::Key(key_event) => {
-                if let Some(action) = self.inputs.key_event(&key_event, self.focused, self.mode) {
-                    self.handle_action(action);
+                if key_event.kind == KeyEventKind::Release {
+                    return;
+                }
+
+                if let Some(confirmation) = self.confirmation.take() {
+                    self.draw();
+                    if key_event.code == KeyCode::Char('y') {
+                        for action in confirmation.actions {
+                            self.handle_action(action);
+                        }
+                    }
+                    return;
+                }
+
+                if self.show_history {
+                    self.show_history = false;
+                    self.draw();
+                    return;
+                }
+
+                if self.editor.preview.take().is_some() {
+                    self.draw();
+                }
+
+                if self.awaiting_register {
+                    self.awaiting_register = false;
+                    if let KeyCode::Char(name) = key_event.code {
+                        self.register = Some(name);
+                    }
+                    return;
+                }
+
+                match self.inputs.key_event(&key_event, self.focused, self.mode) {
+                    // A leading zero isn't a count
+                    Some(Action::CountDigit { digit: 0 }) if self.count.is_none() => {}
+                    Some(Action::CountDigit { digit }) => {
+                        let count = self
+                            .count
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|count| count.checked_add(digit));
+                        self.count = Some(count.map_or(MAX_COUNT, |count| count.min(MAX_COUNT)));
+                    }
+                    Some(action) => {
+                        let count = self.count.take().unwrap_or(1);
+                        let action = self.with_register(action);
+
+                        if let Some((_, actions)) = &mut self.recording {
+                            if !matches!(action, Action::RecordMacro { .. }) {
+                                actions.push((action.clone(), count));
+                            }
+                        }
+
+                        self.handle_action_repeated(action, count);
+                    }
+                    None => {}
                 }
             }
             Event::Resize(_, _) => self.draw(),
@@ -88,12 +365,68 @@ impl App {
         }
     }
 
+    /// Handle `action` `count` times, only document actions and changes are repeated
+    fn handle_action_repeated(&mut self, action: Action, count: usize) {
+        let count = match action {
+            Action::Document(_)
+            | Action::RepeatLastChange
+            | Action::Paste { .. }
+            | Action::ReplayMacro { .. } => count.min(MAX_COUNT),
+            _ => 1,
+        };
+
+        // Replaying a macro several times is still undone at once
+        let is_replay = matches!(action, Action::ReplayMacro { .. });
+        if is_replay {
+            self.begin_transaction();
+        }
+
+        if count > 1 {
+            self.repeating += 1;
+        }
+        for _ in 0..count {
+            self.handle_action(action.clone());
+        }
+        if count > 1 {
+            self.repeating -= 1;
+            self.draw();
+        }
+
+        if is_replay {
+            self.end_transaction();
+        }
+    }
+
     fn handle_action(&mut self, action: Action) {
         use editor_action::{Action::*, DocumentAction::*};
 
         match action {
             Document(action) => match self.focused {
                 Focused::Editor => {
+                    if action.starts_insert() && self.mode != Mode::Insert {
+                        // Recorded in the change of the insert session, for `.` to open a line
+                        self.handle_action(EnterInsertMode);
+                        self.handle_action(action.into());
+                        return;
+                    }
+
+                    if action.is_edit() && self.editor.document.read_only() {
+                        self.messages.error(format!(
+                            "{} is read-only",
+                            self.editor.document.display_name()
+                        ));
+                        return;
+                    }
+
+                    // Only the actions on the document are replayed by `.`, not the ones
+                    // saving it or switching buffers
+                    match &mut self.change {
+                        Some(change) => change.push(action.clone().into()),
+                        None if action.is_edit() => self.last_change = vec![action.clone().into()],
+                        None => {}
+                    }
+
+                    self.editor.document.set_tab_width(self.settings.tab_width);
                     self.editor.handle_action(action);
                     self.draw();
                 }
@@ -103,49 +436,289 @@ impl App {
                         self.draw();
                     }
                     action => {
-                        warn!("Unexpected multiline document action ({:?}) used while command bar focused (ignored)", action);
+                        debug!("Unexpected multiline document action ({:?}) used while command bar focused (ignored)", action);
                     }
                 },
             },
             Quit => self.should_quit = true,
-            Open { path } => {
-                self.editor = Editor::from_path(path.into());
+            Open { path } => self.open(path),
+            Close => self.close(),
+            BufferNext => self.buffer_next(),
+            BufferPrevious => self.buffer_previous(),
+            Help { action } => {
+                let lines = help::help_lines(&self.inputs);
+                let line = action
+                    .and_then(|action| ActionInfo::find(&action.0))
+                    .and_then(|info| help::help_line_of(&lines, &info));
+
+                self.show_virtual("help", lines);
+                if let Some(line) = line {
+                    self.editor.handle_action(Goto { line: line + 1 });
+                }
+                self.draw();
+            }
+            Keymap => {
+                self.show_virtual("keymap", help::keymap_lines(&self.inputs));
+                self.draw();
+            }
+            SaveAs { path } => {
+                let result = self.editor.document.write_to(path.clone());
+                self.editor.reload_diff();
+                self.report_write(result, SaveAs { path });
+            }
+            Write => {
+                let result = self.editor.document.write();
+                self.report_write(result, Write);
+            }
+            Rename { path } => match self.editor.document.rename(path.clone()) {
+                Ok(()) => {
+                    self.editor.reload_diff();
+                    self.messages.info(format!("renamed to {}", path.display()));
+                }
+                Err(err) => self.report_write_error(err, Rename { path }),
+            },
+            CreateDirectory { path } => {
+                if let Err(err) = fs::create_dir_all(&path) {
+                    self.messages
+                        .error(format!("failed to create {}: {}", path.display(), err));
+                }
+            }
+            ShowMessages => {
+                self.show_history = true;
+                self.draw();
+            }
+            Set { setting, value } => match value {
+                Some(value) => match self.settings.set(setting, &value) {
+                    Ok(()) => self.draw(),
+                    Err(err) => self.messages.error(err),
+                },
+                None => self.messages.info(format!(
+                    "{} = {}",
+                    setting.as_str(),
+                    self.settings.get(setting)
+                )),
+            },
+            NextHunk => {
+                if !self.editor.goto_hunk(true) {
+                    self.messages.info("no next hunk");
+                }
+                self.draw();
+            }
+            PreviousHunk => {
+                if !self.editor.goto_hunk(false) {
+                    self.messages.info("no previous hunk");
+                }
+                self.draw();
+            }
+            PreviewHunk => {
+                if !self.editor.preview_hunk() {
+                    self.messages.info("no hunk at the cursor");
+                }
+                self.draw();
+            }
+            RevertHunk => {
+                if !self.editor.revert_hunk() {
+                    self.messages.info("no hunk at the cursor");
+                }
+                self.draw();
+            }
+            StageHunk => {
+                match self.editor.stage_hunk() {
+                    Ok(true) => {}
+                    Ok(false) => self.messages.info("no hunk at the cursor"),
+                    Err(err) => self.messages.error(format!("stage_hunk: {}", err)),
+                }
+                self.draw();
+            }
+            UnstageHunk => {
+                match self.editor.unstage_hunk() {
+                    Ok(true) => {}
+                    Ok(false) => self.messages.info("no staged hunk at the cursor"),
+                    Err(err) => self.messages.error(format!("unstage_hunk: {}", err)),
+                }
+                self.draw();
+            }
+            Git { command } => self.handle_git(command),
+            ToggleStaged => self.toggle_staged(),
+            OpenEntry => self.open_entry(),
+            Blame => self.toggle_blame(),
+            ShowLineCommit => self.show_line_commit(),
+            Diff { target } => self.diff_against(target.map(|target| target.0)),
+            DiffOff => {
+                self.close_diff();
+                self.draw();
+            }
+            DiffSwitch => self.switch_diff_side(),
+            DiffGet => self.copy_hunk(false),
+            DiffPut => self.copy_hunk(true),
+            ToggleLineNumbers => {
+                self.settings.line_numbers = self.settings.line_numbers.next();
                 self.draw();
             }
             Redraw => self.draw(),
             Validate => match self.focused {
-                Focused::Editor => {
-                    warn!("Validate command does nothing when editor is focused")
-                }
+                Focused::Editor => debug!("Validate command does nothing when editor is focused"),
                 Focused::CommandBar => {
                     self.handle_action(Action::FocusEditor.into());
-                    if let Some(command) = self.command_bar.validate() {
-                        self.handle_action(command);
+                    match self.command_bar.validate() {
+                        Some(Ok(actions)) => {
+                            for (action, count) in actions {
+                                self.handle_action_repeated(action, count);
+                            }
+                        }
+                        Some(Err(err)) => self.messages.error(err.to_string()),
+                        None => {}
                     }
                 }
             },
             Cancel => match self.focused {
-                Focused::Editor => {
-                    warn!("Cancel command does nothing when editor is focused")
-                }
+                Focused::Editor => debug!("Cancel command does nothing when editor is focused"),
                 Focused::CommandBar => {
                     self.handle_action(Action::FocusEditor.into());
                     self.command_bar.cancel();
                 }
             },
+            CompleteNext | CompletePrevious => match self.focused {
+                Focused::Editor => debug!("Completion does nothing when editor is focused"),
+                Focused::CommandBar => {
+                    self.command_bar.complete(matches!(action, CompleteNext));
+                    self.draw();
+                }
+            },
+            HistoryPrevious | HistoryNext | HistorySearch => match self.focused {
+                Focused::Editor => debug!("History navigation does nothing when editor is focused"),
+                Focused::CommandBar => {
+                    match action {
+                        HistoryPrevious => self.command_bar.history_previous(),
+                        HistoryNext => self.command_bar.history_next(),
+                        _ => self.command_bar.history_search(),
+                    }
+                    self.draw();
+                }
+            },
             EnterNormalMode => {
+                if self.mode == Mode::Insert {
+                    self.end_transaction();
+                }
+
+                if let Some(mut change) = self.change.take() {
+                    change.push(EnterNormalMode);
+                    let is_edit =
+                        |action: &Action| matches!(action, Document(action) if action.is_edit());
+
+                    if change.iter().any(is_edit) {
+                        self.last_change = change;
+                    }
+                }
+
                 self.mode = Mode::Normal;
                 self.draw();
             }
             EnterInsertMode => {
+                if self.mode != Mode::Insert {
+                    self.begin_transaction();
+                }
+
+                self.change = Some(vec![EnterInsertMode]);
                 self.mode = Mode::Insert;
                 self.draw();
             }
+            SelectRegister => self.awaiting_register = true,
+            Yank { register } => {
+                let register = register.unwrap_or(DEFAULT_REGISTER);
+                self.registers
+                    .set(register, self.editor.document.selected_text());
+            }
+            Paste { register } => {
+                let register = register.unwrap_or(DEFAULT_REGISTER);
+                match self.registers.get(register) {
+                    Some(text) => {
+                        let text = text.to_string();
+                        self.handle_action(InsertText { text }.into());
+                    }
+                    None => self
+                        .messages
+                        .error(format!("register {} is empty", register)),
+                }
+            }
+            RecordMacro { register } => match self.recording.take() {
+                Some((register, actions)) => {
+                    self.registers
+                        .set(register, registers::macro_text(&actions));
+                    self.messages
+                        .info(format!("recorded macro in register {}", register));
+                }
+                None => {
+                    let register = register.unwrap_or(MACRO_REGISTER);
+                    self.recording = Some((register, Vec::new()));
+                    self.messages
+                        .info(format!("recording macro in register {}", register));
+                }
+            },
+            ReplayMacro { register } => {
+                let register = register.unwrap_or(MACRO_REGISTER);
+                let Some(text) = self.registers.get(register) else {
+                    self.messages
+                        .error(format!("register {} is empty", register));
+                    return;
+                };
+
+                if self.macro_depth >= MAX_MACRO_DEPTH {
+                    self.messages
+                        .error(format!("macro {} replays itself", register));
+                    return;
+                }
+
+                match Action::parse_sequence(text) {
+                    Ok(actions) => {
+                        self.macro_depth += 1;
+                        self.begin_transaction();
+                        for (action, count) in actions {
+                            self.handle_action_repeated(action, count);
+                        }
+                        self.end_transaction();
+                        self.macro_depth -= 1;
+                    }
+                    Err(err) => self.messages.error(format!("macro {}: {}", register, err)),
+                }
+            }
+            UserCommand { name, arguments } => {
+                if self.command_depth >= MAX_COMMAND_DEPTH {
+                    self.messages
+                        .error(format!("{}: the command runs itself", name));
+                    return;
+                }
+
+                match self
+                    .scripts
+                    .run(&name, arguments, &mut self.editor.document)
+                {
+                    Ok(output) => {
+                        for message in output.messages {
+                            self.messages.info(message);
+                        }
+                        self.command_depth += 1;
+                        for (action, count) in output.actions {
+                            self.handle_action_repeated(action, count);
+                        }
+                        self.command_depth -= 1;
+                    }
+                    Err(err) => self.messages.error(err),
+                }
+                self.draw();
+            }
+            RepeatLastChange => {
+                for action in self.last_change.clone() {
+                    self.handle_action(action);
+                }
+            }
+            CountDigit { .. } => {}
             EnterSelectionMode => {
                 self.mode = Mode::Selection;
                 self.draw();
             }
             FocusCommandBar => {
+                editor_action::set_open_buffers(self.buffer_paths());
                 self.focused = Focused::CommandBar;
                 self.draw();
             }
@@ -155,4 +728,62 @@ impl App {
             }
         }
     }
+
+    /// Fill the register of `action` with the one chosen by [`Action::SelectRegister`]
+    fn with_register(&mut self, action: Action) -> Action {
+        let Some(name) = self.register.take() else {
+            return action;
+        };
+
+        match action {
+            Action::Yank { register: None } => Action::Yank {
+                register: Some(name),
+            },
+            Action::Paste { register: None } => Action::Paste {
+                register: Some(name),
+            },
+            Action::RecordMacro { register: None } => Action::RecordMacro {
+                register: Some(name),
+            },
+            Action::ReplayMacro { register: None } => Action::ReplayMacro {
+                register: Some(name),
+            },
+            action => action,
+        }
+    }
+
+    /// Group the following changes into a single undo step until [`App::end_transaction`],
+    /// even if the current buffer changes in between
+    fn begin_transaction(&mut self) {
+        self.transactions += 1;
+        self.editor.document.begin_transaction();
+    }
+
+    fn end_transaction(&mut self) {
+        if self.transactions > 0 {
+            self.transactions -= 1;
+            self.editor.document.end_transaction();
+        }
+    }
+
+    fn report_write(&mut self, result: Result<WriteReport, WriteError>, retry: Action) {
+        match result {
+            Ok(report) => self.messages.info(report.to_string()),
+            Err(err) => self.report_write_error(err, retry),
+        }
+    }
+
+    /// Display the error, asking to create the missing directory and `retry` if possible
+    fn report_write_error(&mut self, err: WriteError, retry: Action) {
+        match err {
+            WriteError::MissingParent(parent) => {
+                self.confirmation = Some(Confirmation {
+                    prompt: format!("{} doesn't exist, create it? [y/n]", parent.display()),
+                    actions: vec![Action::CreateDirectory { path: parent }, retry],
+                });
+                self.draw();
+            }
+            err => self.messages.error(err.to_string()),
+        }
+    }
 }
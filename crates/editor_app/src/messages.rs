@@ -0,0 +1,208 @@
+use std::time::{Duration, Instant};
+
+use editor_document::text;
+use editor_terminal::{Color, TermSlice};
+use editor_theme::Theme;
+use log::{error, info, warn};
+
+/// Maximum number of messages kept in the history
+const MAX_MESSAGES: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// How long a message of this severity stays in the message row
+    fn timeout(&self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(3),
+            Severity::Warning => Duration::from_secs(5),
+            Severity::Error => Duration::from_secs(8),
+        }
+    }
+
+    fn color(&self, theme: &Theme) -> Color {
+        match self {
+            Severity::Info => theme.message_info,
+            Severity::Warning => theme.message_warning,
+            Severity::Error => theme.message_error,
+        }
+    }
+}
+
+pub struct Message {
+    pub severity: Severity,
+    pub text: String,
+}
+
+/// User visible notifications
+///
+/// The last message is displayed in the command bar row until its timeout expires,
+/// the last [`MAX_MESSAGES`] are kept in the history, see [`Messages::draw_history`]
+pub struct Messages {
+    /// Oldest message first
+    history: Vec<Message>,
+    /// Number of messages pushed, including the ones dropped from the history
+    count: usize,
+    /// When the last message of the history should stop being displayed
+    deadline: Option<Instant>,
+}
+
+impl Messages {
+    pub fn new() -> Self {
+        Self {
+            history: Vec::new(),
+            count: 0,
+            deadline: None,
+        }
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(Severity::Info, text.into());
+    }
+
+    pub fn warning(&mut self, text: impl Into<String>) {
+        self.push(Severity::Warning, text.into());
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(Severity::Error, text.into());
+    }
+
+    pub fn push(&mut self, severity: Severity, text: String) {
+        match severity {
+            Severity::Info => info!("{}", text),
+            Severity::Warning => warn!("{}", text),
+            Severity::Error => error!("{}", text),
+        }
+
+        self.deadline = Some(Instant::now() + severity.timeout());
+        self.history.push(Message { severity, text });
+        self.count += 1;
+
+        if self.history.len() > MAX_MESSAGES {
+            self.history.drain(..self.history.len() - MAX_MESSAGES);
+        }
+    }
+
+    /// The message to display in the command bar row, if any
+    pub fn current(&self) -> Option<&Message> {
+        self.deadline.and(self.history.last())
+    }
+
+    pub fn history(&self) -> &[Message] {
+        &self.history
+    }
+
+    /// Number of messages pushed so far, to know if there is a new one
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Time left before the current message expires
+    pub fn timeout(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Stop displaying the current message if it expired,
+    /// returns `true` if the current message changed
+    pub fn update(&mut self) -> bool {
+        match self.deadline {
+            Some(deadline) if deadline <= Instant::now() => {
+                self.deadline = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn draw_current(&self, theme: &Theme, mut term: TermSlice) {
+        let Some(message) = self.current() else {
+            return;
+        };
+
+        term.set_background_color(theme.command_bar_background);
+        term.set_text_color(message.severity.color(theme));
+        term.write_to(
+            (0, 0),
+            &text::pad(&message.text, term.rect().width() as usize),
+        );
+    }
+
+    /// Draw the most recent messages, the last one at the bottom of the slice
+    pub fn draw_history(&self, theme: &Theme, mut term: TermSlice) {
+        let height = term.rect().heigth() as usize;
+
+        term.set_background_color(theme.command_suggestion_background);
+
+        for y in 0..height {
+            let message = (self.history.len() + y)
+                .checked_sub(height)
+                .and_then(|i| self.history.get(i));
+
+            let (color, message_text) = match message {
+                Some(message) => (message.severity.color(theme), message.text.as_str()),
+                None => (theme.command_suggestion_text, ""),
+            };
+
+            term.set_text_color(color);
+            term.write_to(
+                (0, y as u16),
+                &text::pad(message_text, term.rect().width() as usize),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_expire_after_their_timeout() {
+        let mut messages = Messages::new();
+        messages.info("saved");
+
+        assert_eq!(messages.current().unwrap().text, "saved");
+        assert!(messages.timeout().unwrap() <= Severity::Info.timeout());
+        assert!(!messages.update());
+
+        messages.deadline = Some(Instant::now());
+        assert!(messages.update());
+        assert!(messages.current().is_none());
+        assert!(messages.timeout().is_none());
+        assert!(!messages.update());
+        assert_eq!(messages.history().len(), 1);
+    }
+
+    #[test]
+    fn errors_stay_longer_than_infos() {
+        let mut messages = Messages::new();
+        messages.error("failed");
+
+        assert!(messages.timeout().unwrap() > Severity::Warning.timeout());
+        assert!(Severity::Warning.timeout() > Severity::Info.timeout());
+        assert_eq!(messages.current().unwrap().severity, Severity::Error);
+    }
+
+    #[test]
+    fn drops_the_oldest_messages_past_the_maximum() {
+        let mut messages = Messages::new();
+        for i in 0..MAX_MESSAGES + 5 {
+            messages.warning(i.to_string());
+        }
+
+        assert_eq!(messages.history().len(), MAX_MESSAGES);
+        assert_eq!(messages.history()[0].text, "5");
+        assert_eq!(
+            messages.current().unwrap().text,
+            (MAX_MESSAGES + 4).to_string()
+        );
+        assert_eq!(messages.count(), MAX_MESSAGES + 5);
+    }
+}
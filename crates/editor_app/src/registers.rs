@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use editor_action::Action;
+
+/// Register used by yank and paste when none is selected
+pub const DEFAULT_REGISTER: char = '"';
+/// Register used by macros when none is selected
+pub const MACRO_REGISTER: char = '@';
+
+/// Named pieces of text, filled by yanks and macro recordings
+///
+/// Macros are stored as command lines (`move_right; insert_text a`) so they can be pasted,
+/// edited and yanked back like any other text
+#[derive(Default)]
+pub struct Registers {
+    values: HashMap<char, String>,
+}
+
+impl Registers {
+    pub fn get(&self, name: char) -> Option<&str> {
+        self.values.get(&name).map(String::as_str)
+    }
+
+    pub fn set(&mut self, name: char, value: String) {
+        self.values.insert(name, value);
+    }
+}
+
+/// Text of a macro, one command per action with the count of document actions appended
+pub fn macro_text(actions: &[(Action, usize)]) -> String {
+    actions
+        .iter()
+        .flat_map(|(action, count)| match action {
+            Action::Document(_) if *count > 1 => vec![format!("{} {}", action.to_command(), count)],
+            action => vec![action.to_command(); *count],
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
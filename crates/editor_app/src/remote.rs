@@ -0,0 +1,131 @@
+use std::path::Path;
+
+use editor_action::{Action, DocumentAction};
+use editor_remote::{Request, RpcError};
+use serde_json::{json, Value};
+
+use crate::{editor::Editor, App};
+
+impl App {
+    /// Handle a request of the remote control, see [`editor_remote`]
+    ///
+    /// - `open {path, line?}` opens a file, at `line` if given
+    /// - `goto {line}` moves the cursor of the current buffer to `line`
+    /// - `run_action {command}` runs a command line like `move_down 3; write`
+    /// - `get_buffer_text {path?}` gives the text of the buffer of `path`, or the current one
+    /// - `list_buffers` gives the name, path and dirty state of each buffer, current one first
+    pub(crate) fn handle_remote_request(&mut self, request: Request) {
+        let result = match request.method.as_str() {
+            "open" => self.remote_open(&request.params),
+            "goto" => self.remote_goto(&request.params),
+            "run_action" => self.remote_run_action(&request.params),
+            "get_buffer_text" => self.remote_buffer_text(&request.params),
+            "list_buffers" => Ok(self.remote_buffers()),
+            method => Err(RpcError::method_not_found(method)),
+        };
+
+        request.reply(result);
+        self.draw();
+    }
+
+    fn remote_open(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let path = Path::new(string_param(params, "path")?);
+        let line = optional_line_param(params)?;
+
+        // Switch to the buffer of the file even if it was opened with another path
+        let path = match self.find_editor(path) {
+            Some(editor) => editor.document.path().unwrap_or(path).to_path_buf(),
+            None => path.to_path_buf(),
+        };
+
+        self.handle_action(Action::Open { path });
+        if let Some(line) = line {
+            self.editor.handle_action(DocumentAction::Goto { line });
+        }
+
+        Ok(Value::Null)
+    }
+
+    fn remote_goto(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let line = optional_line_param(params)?
+            .ok_or_else(|| RpcError::invalid_params("missing parameter `line`"))?;
+
+        self.editor.handle_action(DocumentAction::Goto { line });
+
+        Ok(Value::Null)
+    }
+
+    fn remote_run_action(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let command = string_param(params, "command")?;
+        let actions = Action::parse_sequence(command)
+            .map_err(|err| RpcError::invalid_params(err.to_string()))?;
+
+        for (action, count) in actions {
+            self.handle_action_repeated(action, count);
+        }
+
+        Ok(Value::Null)
+    }
+
+    fn remote_buffer_text(&self, params: &Value) -> Result<Value, RpcError> {
+        let editor = match params.get("path").and_then(Value::as_str) {
+            Some(path) => self
+                .find_editor(Path::new(path))
+                .ok_or_else(|| RpcError::failed(format!("no buffer for {}", path)))?,
+            None => &self.editor,
+        };
+
+        Ok(Value::String(editor.document.lines().join("\n")))
+    }
+
+    fn remote_buffers(&self) -> Value {
+        self.all_editors()
+            .enumerate()
+            .map(|(i, editor)| {
+                json!({
+                    "name": editor.document.display_name(),
+                    "path": editor.document.path(),
+                    "dirty": editor.document.dirty(),
+                    "current": i == 0,
+                })
+            })
+            .collect()
+    }
+
+    /// Editor of the file at `path`, which may be written differently than the path of its
+    /// document like `./a.rs` and `a.rs`
+    fn find_editor(&self, path: &Path) -> Option<&Editor> {
+        let path = editor_remote::absolute_path(path).ok()?;
+
+        self.all_editors().find(|editor| {
+            editor
+                .document
+                .path()
+                .and_then(|path| editor_remote::absolute_path(path).ok())
+                .is_some_and(|other| other == path)
+        })
+    }
+
+    /// The current editor followed by the other buffers
+    fn all_editors(&self) -> impl Iterator<Item = &Editor> {
+        std::iter::once(&self.editor).chain(&self.buffers)
+    }
+}
+
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::invalid_params(format!("missing string parameter `{}`", name)))
+}
+
+/// The 1 based `line` parameter if present
+fn optional_line_param(params: &Value) -> Result<Option<usize>, RpcError> {
+    match params.get("line") {
+        None | Some(Value::Null) => Ok(None),
+        Some(line) => line
+            .as_u64()
+            .map(|line| Some(line as usize))
+            .ok_or_else(|| RpcError::invalid_params("`line` must be a positive integer")),
+    }
+}
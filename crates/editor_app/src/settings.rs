@@ -0,0 +1,43 @@
+use editor_action::{ArgumentParse, LineNumbers, Setting};
+
+/// Options changeable at runtime with `:set <setting> <value>`
+pub struct Settings {
+    pub tab_width: usize,
+    pub soft_wrap: bool,
+    pub line_numbers: LineNumbers,
+}
+
+impl Settings {
+    pub fn get(&self, setting: Setting) -> String {
+        match setting {
+            Setting::TabWidth => self.tab_width.to_string(),
+            Setting::SoftWrap => self.soft_wrap.to_string(),
+            Setting::LineNumbers => self.line_numbers.as_str().to_string(),
+        }
+    }
+
+    pub fn set(&mut self, setting: Setting, value: &str) -> Result<(), String> {
+        match setting {
+            Setting::TabWidth => self.tab_width = parse_value(setting, value)?,
+            Setting::SoftWrap => self.soft_wrap = parse_value(setting, value)?,
+            Setting::LineNumbers => self.line_numbers = parse_value(setting, value)?,
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            soft_wrap: false,
+            line_numbers: LineNumbers::Absolute,
+        }
+    }
+}
+
+fn parse_value<T: ArgumentParse>(setting: Setting, value: &str) -> Result<T, String> {
+    T::parse(value)
+        .ok_or_else(|| format!("set: invalid value `{}` for {}", value, setting.as_str()))
+}
@@ -0,0 +1,686 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use editor_git::{FileChange, FileDiff, FileStatus, LineBlame};
+use serde_json::{json, Value};
+
+use super::Harness;
+use editor_action::{Action, LineNumbers};
+use editor_document::Document;
+use editor_input::Input;
+use editor_mode::Mode;
+
+use crate::{editor::Editor, git::GitView, gutter::GutterColumn};
+
+#[test]
+fn insert_mode_edits_the_document() {
+    let mut harness = Harness::with_lines((30, 6), &["hello", "world"]);
+
+    harness.keys("ihey <esc>");
+
+    assert_eq!(harness.lines(), ["hey hello", "world"]);
+    harness.assert_screen(&[
+        " 1 hey hello",
+        " 2 world",
+        " ~",
+        "",
+        "",
+        " NOR [test] [+]           1:5",
+    ]);
+}
+
+#[test]
+fn undo_reverts_a_whole_insert_session() {
+    let mut harness = Harness::with_lines((30, 6), &["hello"]);
+
+    harness.keys("iab<esc>ic<esc>u");
+    assert_eq!(harness.lines(), ["abhello"]);
+
+    harness.keys("u");
+    assert_eq!(harness.lines(), ["hello"]);
+    assert!(!harness.screen()[5].contains("[+]"));
+
+    harness.keys("U");
+    assert_eq!(harness.lines(), ["abhello"]);
+}
+
+#[test]
+fn count_repeats_document_actions() {
+    let mut harness = Harness::with_lines((30, 6), &["hello"]);
+
+    harness.keys("3lix<esc>");
+
+    assert_eq!(harness.lines(), ["helxlo"]);
+
+    // Huge counts are clamped instead of overflowing
+    let mut harness = Harness::with_lines((30, 6), &["a", "b", "c"]);
+    harness.keys("99999999999999999999999999j");
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 3));
+}
+
+#[test]
+fn command_bar_runs_commands() {
+    let mut harness = Harness::with_lines((30, 6), &["a", "b", "c"]);
+
+    harness.keys(":goto 3<enter>ix<esc>");
+
+    assert_eq!(harness.lines(), ["a", "b", "xc"]);
+}
+
+#[test]
+fn invalid_commands_show_an_error() {
+    let mut harness = Harness::with_lines((40, 6), &["a"]);
+
+    harness.keys(":nope<enter>");
+
+    assert_eq!(harness.screen()[5], "unknown command `nope`");
+}
+
+#[test]
+fn macros_replay_recorded_keys() {
+    let mut harness = Harness::with_lines((30, 6), &["a", "b", "c"]);
+
+    harness.keys("Qix<esc>hjQ");
+    assert_eq!(harness.lines(), ["xa", "b", "c"]);
+
+    harness.keys("2q");
+    assert_eq!(harness.lines(), ["xa", "xb", "xc"]);
+
+    harness.keys("u");
+    assert_eq!(harness.lines(), ["xa", "b", "c"]);
+}
+
+#[test]
+fn macros_switching_buffers_close_their_undo_steps() {
+    let mut harness = Harness::with_lines((30, 6), &["a"]);
+    harness
+        .app
+        .buffers
+        .push_back(Editor::from_document(Document::from_lines(
+            "other".to_string(),
+            vec!["b".to_string()],
+        )));
+
+    harness.keys("Qix<esc>:bn<enter>iy<esc>Q");
+    harness.keys("q");
+    assert_eq!(harness.app.transactions, 0);
+    let lines = harness.lines();
+
+    // Each insert session is still its own undo step on both buffers
+    for _ in 0..2 {
+        harness.keys(":bn<enter>");
+        let lines = harness.lines();
+        harness.keys("iz<esc>iw<esc>u");
+        assert_ne!(harness.lines(), lines);
+        harness.keys("u");
+        assert_eq!(harness.lines(), lines);
+    }
+    assert_eq!(harness.lines(), lines);
+}
+
+#[test]
+fn yank_and_paste() {
+    let mut harness = Harness::with_lines((30, 6), &["hello"]);
+
+    harness.keys("vlly<esc>\"ayp\"ap");
+
+    assert_eq!(harness.lines(), ["hehehello"]);
+}
+
+#[test]
+fn help_opens_a_buffer() {
+    let mut harness = Harness::with_lines((40, 6), &["a"]);
+
+    harness.keys(":help quit<enter>");
+
+    let cursor_line = harness.app.editor.document.selection().end().1;
+    assert_eq!(harness.lines()[cursor_line], "quit, q");
+    assert!(harness.screen()[5].starts_with(" NOR [help]"));
+
+    harness.keys(":bn<enter>");
+    assert_eq!(harness.lines(), ["a"]);
+
+    // Generated buffers can't be edited, so they can always be closed
+    harness.keys(":keymap<enter>ix<esc>:close<enter>");
+    assert_eq!(harness.lines(), ["a"]);
+}
+
+#[test]
+fn messages_shows_the_last_messages() {
+    let mut harness = Harness::with_lines((30, 8), &["a"]);
+    for i in 0..5 {
+        harness.app.messages.info(format!("message {}", i));
+    }
+    harness.app.messages.error("failed");
+
+    harness.keys(":messages<enter>");
+    assert_eq!(
+        harness.screen()[3..7],
+        ["message 2", "message 3", "message 4", "failed"]
+    );
+
+    // More messages than the history keeps, the new ones are still displayed
+    for i in 0..2000 {
+        harness.app.messages.info(format!("later {}", i));
+    }
+    harness.keys("<esc>-");
+    assert_eq!(harness.screen()[7], "not in the git status buffer");
+}
+
+#[test]
+fn moving_redraws_few_cells() {
+    let mut harness = Harness::with_lines((20, 6), &["first line", "second line"]);
+    let written = harness.screen.borrow().written();
+
+    harness.keys("j");
+
+    // The cursor cells, the gutter line numbers and the infos bar
+    assert!(harness.screen.borrow().written() - written < 40);
+}
+
+#[test]
+fn cursor_moves_by_grapheme_and_column() {
+    let mut harness = Harness::with_lines((30, 6), &["a漢字e\u{301}b", "abcdef"]);
+
+    harness.keys("lll");
+    assert_eq!(harness.app.editor.document.selection().end(), (3, 0));
+    assert_eq!(harness.screen()[0], " 1 a漢字e\u{301}b");
+    let cursor = harness.screen.borrow().cell((8, 0)).unwrap();
+    assert_eq!(cursor.symbol, "e\u{301}");
+    assert_eq!(cursor.background_color, harness.app.theme.cursor);
+
+    // Same column on the next line and back
+    harness.keys("j");
+    assert_eq!(harness.app.editor.document.selection().end(), (5, 1));
+    harness.keys("k");
+    assert_eq!(harness.app.editor.document.selection().end(), (3, 0));
+
+    harness.keys("i<backspace><esc>");
+    assert_eq!(harness.lines(), ["a漢e\u{301}b", "abcdef"]);
+}
+
+#[test]
+fn tabs_expand_to_the_tab_width() {
+    let mut harness = Harness::with_lines((30, 6), &["\tab\tc", "abcdefghij"]);
+
+    assert_eq!(harness.screen()[0], " 1     ab  c");
+
+    harness.keys(":set tab-width 2<enter>");
+    assert_eq!(harness.screen()[0], " 1   ab  c");
+
+    // The cursor keeps its column through the tab
+    harness.keys("jlllk");
+    assert_eq!(harness.app.editor.document.selection().end(), (2, 0));
+}
+
+#[test]
+fn indent_and_outdent_follow_the_document_style() {
+    let mut harness = Harness::with_lines((30, 6), &["a:", "  b", "c"]);
+
+    harness.keys(">");
+    assert_eq!(harness.lines(), ["  a:", "  b", "c"]);
+
+    harness.keys("j<lt><lt>");
+    assert_eq!(harness.lines(), ["  a:", "b", "c"]);
+
+    harness.keys("jvk>");
+    assert_eq!(harness.lines(), ["  a:", "  b", "  c"]);
+
+    // Spaces up to the next level
+    harness.keys("<esc>:g 3<enter>li<tab><esc>");
+    assert_eq!(harness.lines()[2], "   c");
+}
+
+#[test]
+fn new_lines_follow_the_indentation() {
+    let mut harness = Harness::with_lines((30, 6), &["  fn a() {}"]);
+
+    harness.keys(":g 1<enter>lllllllllli<C-j>b<esc>");
+    assert_eq!(harness.lines(), ["  fn a() {", "    b", "  }"]);
+
+    // A closing bracket on a blank line closes the block
+    harness.keys("i<C-j>}<esc>");
+    assert_eq!(harness.lines(), ["  fn a() {", "    b", "  }", "  }"]);
+}
+
+#[test]
+fn open_line_below_and_above() {
+    let mut harness = Harness::with_lines((30, 6), &["a {", "  b"]);
+
+    harness.keys("oc<esc>");
+    assert_eq!(harness.lines(), ["a {", "  c", "  b"]);
+
+    harness.keys("Od<esc>");
+    assert_eq!(harness.lines(), ["a {", "  d", "  c", "  b"]);
+
+    // Repeating opens a line again
+    harness.keys(".");
+    assert_eq!(harness.lines(), ["a {", "  d", "  d", "  c", "  b"]);
+}
+
+#[test]
+fn repeat_only_replays_document_actions() {
+    let mut harness = Harness::with_lines((30, 6), &["abc", "def"]);
+    harness
+        .app
+        .inputs
+        .bind(
+            "insert",
+            Input::parse("C-t").unwrap(),
+            Action::ToggleLineNumbers,
+        )
+        .unwrap();
+
+    harness.keys("ix<C-t>y<esc>");
+    let line_numbers = harness.app.settings.line_numbers;
+    assert_ne!(line_numbers, LineNumbers::Absolute);
+
+    harness.keys("j.");
+    assert_eq!(harness.lines(), ["xyabc", "dexyf"]);
+    assert_eq!(harness.app.settings.line_numbers, line_numbers);
+    assert_eq!(harness.app.mode, Mode::Normal);
+}
+
+#[test]
+fn soft_wrap_splits_lines_at_words() {
+    let mut harness = Harness::with_lines((14, 6), &["the quick brown fox jumps", "end"]);
+
+    harness.keys(":set soft-wrap on<enter>");
+    assert_eq!(
+        harness.screen()[..5],
+        [" 1 the quick", " ↪ brown fox", " ↪ jumps", " 2 end", " ~"]
+    );
+
+    // Vertical moves go through the rows
+    harness.keys("lj");
+    assert_eq!(harness.app.editor.document.selection().end(), (11, 0));
+    harness.keys("jj");
+    assert_eq!(harness.app.editor.document.selection().end(), (1, 1));
+    harness.keys("k");
+    assert_eq!(harness.app.editor.document.selection().end(), (21, 0));
+}
+
+#[test]
+fn folds_hide_indented_blocks() {
+    let mut harness = Harness::with_lines((30, 7), &["fn a() {", "    b", "    c", "}", "d"]);
+
+    harness.keys("jz");
+    assert_eq!(
+        harness.screen()[..4],
+        [" 1▸fn a() { ⋯ 2 lines", " 4 }", " 5 d", " ~"]
+    );
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 0));
+
+    // Vertical moves skip the closed fold
+    harness.keys("j");
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 3));
+    harness.keys("k");
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 0));
+
+    harness.keys("z");
+    assert_eq!(harness.screen()[1], " 2     b");
+
+    harness.keys("Z");
+    assert_eq!(harness.screen()[1], " 4 }");
+    harness.keys(":unfold_all<enter>");
+    assert_eq!(harness.screen()[1], " 2     b");
+}
+
+#[test]
+fn folding_over_the_top_of_the_view() {
+    let body = (1..=40).map(|i| format!("    {}", i)).collect::<Vec<_>>();
+    let mut lines = vec!["fn main() {"];
+    lines.extend(body.iter().map(String::as_str));
+    lines.push("}");
+    let mut harness = Harness::with_lines((30, 8), &lines);
+
+    harness.keys("30jz");
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 0));
+    assert_eq!(
+        harness.screen()[..2],
+        ["  1▸fn main() { ⋯ 40 lines", " 42 }"]
+    );
+}
+
+#[test]
+fn relative_and_hybrid_line_numbers() {
+    let mut harness = Harness::with_lines((20, 6), &["a", "b", "c"]);
+
+    harness.keys("j:set line-numbers relative<enter>");
+    assert_eq!(harness.screen()[..3], [" 1 a", " 0 b", " 1 c"]);
+
+    harness.keys(":toggle_line_numbers<enter>");
+    assert_eq!(harness.screen()[..3], [" 1 a", " 2 b", " 1 c"]);
+
+    harness.keys(":toggle_line_numbers<enter>");
+    assert_eq!(harness.screen()[..3], [" 1 a", " 2 b", " 3 c"]);
+}
+
+#[test]
+fn git_diff_markers_follow_the_changes() {
+    let mut harness = Harness::with_lines((20, 6), &["a", "B", "c"]);
+    harness.app.editor.diff = Some(FileDiff::from_base(
+        ["a", "b", "c", "d"].map(String::from).to_vec(),
+    ));
+
+    harness.keys("]");
+    assert_eq!(harness.screen()[..3], [" 1 a", "▎2 B", "▁3 c"]);
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 1));
+    harness.keys("][");
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 1));
+
+    harness.keys("ki<C-j><esc>");
+    assert_eq!(harness.screen()[..2], ["▎1", " 2 a"]);
+}
+
+#[test]
+fn preview_and_revert_hunks() {
+    let mut harness = Harness::with_lines((20, 6), &["a", "B", "c"]);
+    harness.app.editor.diff = Some(FileDiff::from_base(
+        ["a", "b", "c"].map(String::from).to_vec(),
+    ));
+
+    harness.keys("j:preview_hunk<enter>");
+    assert_eq!(harness.screen()[2..4], [" 3 - b", " ~ + B"]);
+
+    // Closed by the next key
+    harness.keys("<esc>");
+    assert_eq!(harness.screen()[2], " 3 c");
+
+    harness.keys(":revert_hunk<enter>");
+    assert_eq!(harness.lines(), ["a", "b", "c"]);
+    harness.keys("u");
+    assert_eq!(harness.lines(), ["a", "B", "c"]);
+}
+
+#[test]
+fn previews_hide_once_the_lines_change() {
+    let mut harness = Harness::with_lines((20, 6), &["a", "b", "c", "D"]);
+    harness.app.editor.diff = Some(FileDiff::from_base(
+        ["a", "b", "c", "d"].map(String::from).to_vec(),
+    ));
+
+    harness.keys("3j:preview_hunk<enter>");
+    assert_eq!(harness.screen()[4], " ~ + D");
+
+    // Changed without a key, like by a remote request, the hunk is out of the lines
+    harness.app.editor.document.replace_lines(1..4, Vec::new());
+    harness.app.draw();
+    harness.app.term.flush();
+
+    assert_eq!(harness.app.editor.preview, None);
+    assert_eq!(harness.screen()[..2], ["▁1 a", " ~"]);
+}
+
+#[test]
+fn git_status_entries_open_their_file() {
+    let mut harness = Harness::with_lines((30, 6), &["Changes", "", "", " M src/lib.rs"]);
+    harness.app.editor.document.set_read_only(true);
+    harness.app.editor.git_view = Some(GitView::Status {
+        workdir: PathBuf::from("/repo"),
+        files: vec![FileStatus {
+            path: PathBuf::from("src/lib.rs"),
+            staged: None,
+            unstaged: Some(FileChange::Modified),
+        }],
+    });
+
+    harness.keys("ix<esc>");
+    assert_eq!(harness.lines()[0], "Changes");
+
+    harness.keys("jjj<enter>");
+    assert_eq!(
+        harness.app.editor.document.path(),
+        Some(Path::new("/repo/src/lib.rs"))
+    );
+}
+
+#[test]
+fn accept_a_version_of_conflicts() {
+    let mut harness = Harness::with_lines(
+        (30, 12),
+        &[
+            "a",
+            "<<<<<<< HEAD",
+            "ours",
+            "=======",
+            "theirs",
+            ">>>>>>> branch",
+            "<<<<<<< HEAD",
+            "ours",
+            "=======",
+            "theirs",
+            ">>>>>>> branch",
+        ],
+    );
+
+    harness.keys("}");
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 1));
+    harness.keys(":accept_theirs<enter>");
+    assert_eq!(harness.lines()[..3], ["a", "theirs", "<<<<<<< HEAD"]);
+
+    harness.keys("}:accept_both<enter>");
+    assert_eq!(harness.lines(), ["a", "theirs", "ours", "theirs"]);
+
+    harness.keys("u{");
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 2));
+}
+
+#[test]
+fn blame_column_shows_commits_and_authors() {
+    let mut harness = Harness::with_lines((40, 5), &["a", "b", "c"]);
+    let blame = |author: &str| LineBlame {
+        id: "0123456789abcdef".to_string(),
+        short_id: "0123456".to_string(),
+        author: author.to_string(),
+    };
+    harness.app.editor.blame = Some(vec![
+        Some(blame("A very long author name")),
+        None,
+        Some(blame("漢字漢字漢字漢字")),
+    ]);
+    harness.app.editor.gutter.insert(0, GutterColumn::Blame);
+
+    harness.keys(":redraw<enter>");
+    assert_eq!(
+        harness.screen()[..3],
+        [
+            "0123456 A very long  1 a",
+            "not committed        2 b",
+            "0123456 漢字漢字漢   3 c"
+        ]
+    );
+}
+
+#[test]
+fn diff_view_aligns_the_sides() {
+    let mut harness = Harness::with_lines((40, 7), &["a", "b", "c", "d"]);
+    let other = Document::from_lines(
+        "other".to_string(),
+        ["a", "B", "d", "e"].map(String::from).to_vec(),
+    );
+    harness.app.start_diff(Editor::from_document(other));
+
+    harness.keys(":redraw<enter>");
+    assert_eq!(
+        harness.screen()[..5],
+        [
+            " 1 a                 1 a",
+            " 2 b                 2 B",
+            " 3 c                   ╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱",
+            " 4 d                 3 d",
+            "   ╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱╱ 4 e",
+        ]
+    );
+    let screen = harness.screen.borrow();
+    let changed = screen.cell((23, 1)).unwrap().background_color;
+    assert_eq!(changed, harness.app.theme.diff_added_inline);
+    drop(screen);
+
+    harness.keys("j:diff_get<enter>");
+    assert_eq!(harness.lines(), ["a", "B", "d"]);
+
+    // The cursor goes to the same row of the other side
+    harness.keys("jj<C-w>:diff_put<enter>");
+    assert_eq!(harness.app.editor.document.selection().end(), (0, 3));
+    harness.keys("<C-w>");
+    assert_eq!(harness.lines(), ["a", "B", "d", "e"]);
+
+    harness.keys(":diff_off<enter>");
+    assert_eq!(harness.app.buffers.len(), 0);
+    assert_eq!(harness.screen()[0], " 1 a");
+}
+
+#[test]
+fn diff_completes_the_open_buffers() {
+    let mut harness = Harness::new((40, 6));
+    harness
+        .app
+        .buffers
+        .push_back(Editor::from_path(PathBuf::from("/nonexistent/other.rs")));
+
+    harness.keys(":diff /nonexistent/o<tab>");
+    assert_eq!(harness.screen()[5], ":diff /nonexistent/other.rs");
+}
+
+#[test]
+fn script_commands_run_from_the_command_bar_and_bindings() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("upper.rhai"),
+        r#"
+            fn upper(args) {
+                let index = selection()[1];
+                set_line(index, line(index).to_upper());
+            }
+
+            register_command("upper_line", "Upper case the current line", Fn("upper"));
+            bind("normal", "C-u", "upper_line");
+        "#,
+    )
+    .unwrap();
+    let mut harness = Harness::with_lines((40, 6), &["a", "b"]);
+    harness.load_scripts(dir.path());
+
+    harness.keys(":upper_line<enter>");
+    assert_eq!(harness.lines(), ["A", "b"]);
+
+    harness.keys("j<C-u>");
+    assert_eq!(harness.lines(), ["A", "B"]);
+}
+
+#[test]
+fn help_shows_the_keys_of_script_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("greet.rhai"),
+        r#"
+            fn greet(args) { message("hello"); }
+
+            register_command("greet", "Say hello", Fn("greet"));
+            bind("normal", "C-g", "greet");
+        "#,
+    )
+    .unwrap();
+    let mut harness = Harness::with_lines((40, 6), &["a"]);
+    harness.load_scripts(dir.path());
+
+    harness.keys(":help greet<enter>");
+    let start = harness.app.editor.document.selection().end().1;
+    assert_eq!(
+        harness.lines()[start..start + 4],
+        [
+            "greet [arguments]",
+            "    Say hello",
+            "    arguments: Arguments given to the command",
+            "    keys: normal C-g",
+        ]
+    );
+
+    harness.keys(":keymap<enter>");
+    assert!(harness
+        .lines()
+        .iter()
+        .any(|line| line.trim_start().starts_with("C-g") && line.ends_with("greet")));
+}
+
+#[test]
+fn script_commands_cant_hang_the_editor() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("loops.rhai"),
+        r#"
+            fn again(args) { action("run_again"); }
+            fn spin(args) { loop {} }
+
+            register_command("run_again", "Run itself", Fn("again"));
+            register_command("spin", "Never return", Fn("spin"));
+            bind("normal", "C-a", "run_again");
+        "#,
+    )
+    .unwrap();
+    let mut harness = Harness::with_lines((60, 6), &["a"]);
+    harness.load_scripts(dir.path());
+
+    harness.keys("<C-a>");
+    assert_eq!(harness.screen()[5], "run_again: the command runs itself");
+
+    harness.keys(":spin<enter>");
+    assert!(harness.screen()[5].starts_with("spin: Too many operations"));
+}
+
+#[test]
+fn remote_requests_drive_the_app() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("notes.txt");
+    fs::write(&file, "one\ntwo\nthree").unwrap();
+
+    let socket = dir.path().join("remote/editor.sock");
+    let mut harness = Harness::new((30, 6));
+    harness.app.remote = Some(editor_remote::RemoteServer::start(socket.clone(), || {}).unwrap());
+
+    let open_path = file.display().to_string();
+    let other_path = dir.path().join("remote/../notes.txt").display().to_string();
+    let client = std::thread::spawn(move || {
+        let call = |method, params| editor_remote::call(&socket, method, params);
+
+        // Paths written differently than the one of the document still find its buffer
+        [
+            call("open", json!({"path": open_path, "line": 2})),
+            call("run_action", json!({"command": "insert_text x"})),
+            call("goto", json!({"line": 3})),
+            call("get_buffer_text", json!({ "path": other_path })),
+            call("list_buffers", Value::Null).map(|buffers| buffers[0]["dirty"].clone()),
+            call("open", json!({ "path": other_path })),
+            call("get_buffer_text", json!({"path": "missing.txt"})),
+            call("goto", json!({"line": "x"})),
+            call("run_action", json!({"command": "not_an_action"})),
+            call("open", Value::Null),
+            call("delete_everything", Value::Null),
+        ]
+    });
+
+    while !client.is_finished() {
+        harness.app.update(None);
+    }
+    let results = client.join().unwrap();
+
+    assert_eq!(results[0], Ok(Value::Null));
+    assert_eq!(results[3], Ok(json!("one\nxtwo\nthree")));
+    assert_eq!(results[4], Ok(json!(true)));
+    assert_eq!(results[5], Ok(Value::Null));
+    assert_eq!(harness.app.editor.document.selection().end().1, 2);
+    assert!(harness.app.editor.document.dirty());
+    assert!(harness.app.buffers.is_empty());
+
+    let codes = results[6..]
+        .iter()
+        .map(|result| result.as_ref().unwrap_err().rsplit_once(' ').unwrap().1)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        codes,
+        ["(-32000)", "(-32602)", "(-32602)", "(-32602)", "(-32601)"]
+    );
+}
@@ -0,0 +1,45 @@
+use editor_action::SingleLineDocumentAction;
+use editor_terminal::{Term, TermRect};
+use editor_theme::Theme;
+
+use crate::{command_bar::CommandBar, history::History};
+
+fn type_line(command_bar: &mut CommandBar, line: &str) {
+    for char in line.chars() {
+        command_bar.handle_action(SingleLineDocumentAction::Insert { char });
+    }
+}
+
+fn draw(command_bar: &mut CommandBar, size: (u16, u16)) -> Vec<String> {
+    let (mut term, screen) = Term::headless(size);
+    command_bar.draw(&Theme::default(), term.slice(TermRect::new((0, 0), size)));
+    term.flush();
+
+    let lines = screen.borrow().lines();
+    lines
+}
+
+#[test]
+fn draws_the_line_under_the_suggestions() {
+    let mut command_bar = CommandBar::with_history(History::in_memory());
+    type_line(&mut command_bar, "rena");
+
+    assert_eq!(
+        draw(&mut command_bar, (50, 3)),
+        ["rename  Move the document file to a new path", "", ":rena"]
+    );
+}
+
+#[test]
+fn completion_selects_a_suggestion() {
+    let mut command_bar = CommandBar::with_history(History::in_memory());
+    type_line(&mut command_bar, "bu");
+    command_bar.complete(true);
+
+    let screen = draw(&mut command_bar, (50, 4));
+
+    // The first suggestion is selected and written to the line
+    let selected = screen[3].strip_prefix(':').unwrap();
+    assert!(selected.starts_with("buffer_"));
+    assert!(screen[0].starts_with(selected));
+}
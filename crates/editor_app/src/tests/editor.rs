@@ -0,0 +1,135 @@
+use editor_action::{DocumentAction, SingleLineDocumentAction};
+use editor_document::Document;
+use editor_mode::Mode;
+use editor_terminal::{Color, Style, Term, TermRect};
+use editor_theme::Theme;
+
+use crate::editor::Editor;
+
+fn draw(editor: &mut Editor, size: (u16, u16)) -> Vec<String> {
+    let (mut term, screen) = Term::headless(size);
+    editor.draw(
+        &Theme::default(),
+        term.slice(TermRect::new((0, 0), size)),
+        Mode::Normal,
+    );
+    term.flush();
+
+    let lines = screen.borrow().lines();
+    lines
+}
+
+#[test]
+fn draws_gutter_code_and_infos() {
+    let mut editor = Editor::from_document(Document::from_lines(
+        "notes".to_string(),
+        vec!["first".to_string(), "second".to_string()],
+    ));
+
+    assert_eq!(
+        draw(&mut editor, (24, 5)),
+        [" 1 first", " 2 second", " ~", "", " NOR [notes]        1:1",]
+    );
+}
+
+#[test]
+fn highlights_the_cursor() {
+    let theme = Theme::default();
+    let mut editor = Editor::from_document(Document::from_lines(
+        "notes".to_string(),
+        vec!["abc".to_string()],
+    ));
+    editor.handle_action(DocumentAction::SingleLine(
+        SingleLineDocumentAction::MoveRight,
+    ));
+
+    let (mut term, screen) = Term::headless((20, 3));
+    editor.draw(
+        &theme,
+        term.slice(TermRect::new((0, 0), (20, 3))),
+        Mode::Normal,
+    );
+    term.flush();
+
+    let screen = screen.borrow();
+    let cursor = screen.cell((4, 0)).unwrap();
+    assert_eq!(cursor.symbol, "b");
+    assert_eq!(cursor.background_color, theme.cursor);
+    assert_eq!(cursor.text_color, Color::Black);
+    assert_eq!(
+        screen.cell((3, 0)).unwrap().background_color,
+        theme.code_background
+    );
+}
+
+#[test]
+fn scrolls_to_keep_the_cursor_visible() {
+    let lines = (1..=20).map(|i| format!("line {}", i)).collect();
+    let mut editor = Editor::from_document(Document::from_lines("long".to_string(), lines));
+    editor.handle_action(DocumentAction::Goto { line: 15 });
+
+    let screen = draw(&mut editor, (20, 10));
+
+    assert!(screen[..9].iter().any(|line| line.ends_with("line 15")));
+    assert!(!screen[..9].iter().any(|line| line.ends_with("line 1")));
+    assert!(screen[9].ends_with("15:1"));
+}
+
+#[test]
+fn styles_the_mode_and_current_line_number() {
+    let theme = Theme::default();
+    let mut editor = Editor::from_document(Document::from_lines(
+        "notes".to_string(),
+        vec!["abc".to_string(), "def".to_string()],
+    ));
+
+    let (mut term, screen) = Term::headless((20, 3));
+    editor.draw(
+        &theme,
+        term.slice(TermRect::new((0, 0), (20, 3))),
+        Mode::Normal,
+    );
+    term.flush();
+
+    let screen = screen.borrow();
+    assert_eq!(
+        screen.cell((1, 0)).unwrap().style,
+        theme.gutter_current_line_style
+    );
+    assert_eq!(screen.cell((1, 1)).unwrap().style, Style::new());
+    assert_eq!(
+        screen.cell((1, 2)).unwrap().style,
+        theme.code_info_mode_style
+    );
+    assert_eq!(screen.cell((5, 2)).unwrap().style, Style::new());
+}
+
+#[test]
+fn highlights_the_versions_of_conflicts() {
+    let theme = Theme::default();
+    let lines = [
+        "<<<<<<< HEAD",
+        "ours",
+        "=======",
+        "theirs",
+        ">>>>>>> branch",
+    ];
+    let mut editor = Editor::from_document(Document::from_lines(
+        "notes".to_string(),
+        lines.map(String::from).to_vec(),
+    ));
+
+    let (mut term, screen) = Term::headless((20, 6));
+    editor.draw(
+        &theme,
+        term.slice(TermRect::new((0, 0), (20, 6))),
+        Mode::Normal,
+    );
+    term.flush();
+
+    let screen = screen.borrow();
+    let background = |y: u16| screen.cell((10, y)).unwrap().background_color;
+    assert_eq!(background(0), theme.conflict_marker);
+    assert_eq!(background(1), theme.conflict_ours);
+    assert_eq!(background(3), theme.conflict_theirs);
+}
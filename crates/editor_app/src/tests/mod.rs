@@ -0,0 +1,117 @@
+//! End-to-end tests driving a headless [`App`] with key sequences
+
+mod app;
+mod command_bar;
+mod editor;
+
+use std::{cell::RefCell, path::Path, rc::Rc};
+
+use editor_document::Document;
+use editor_input::Input;
+use editor_script::Scripts;
+use editor_terminal::{Event, KeyCode, KeyEvent, KeyModifiers, Term, VirtualBackend};
+
+use crate::{editor::Editor, App};
+
+pub struct Harness {
+    pub app: App,
+    pub screen: Rc<RefCell<VirtualBackend>>,
+}
+
+impl Harness {
+    pub fn new(size: (u16, u16)) -> Self {
+        let (term, screen) = Term::headless(size);
+        let mut app = App::headless(term);
+        app.draw();
+        app.term.flush();
+
+        Self { app, screen }
+    }
+
+    /// Create with the current buffer containing `lines`
+    pub fn with_lines(size: (u16, u16), lines: &[&str]) -> Self {
+        let mut harness = Self::new(size);
+        harness.app.editor = Editor::from_document(Document::from_lines(
+            "test".to_string(),
+            lines.iter().map(|line| line.to_string()).collect(),
+        ));
+        harness.app.draw();
+        harness.app.term.flush();
+
+        harness
+    }
+
+    /// Load the scripts of `dir` like at startup, they must load without errors
+    pub fn load_scripts(&mut self, dir: &Path) -> &mut Self {
+        let (scripts, errors) = Scripts::load_dir(dir);
+        assert_eq!(errors, Vec::<String>::new());
+
+        self.app.scripts = scripts;
+        self.app.bind_script_keys();
+
+        self
+    }
+
+    /// Type `keys`, see [`parse_keys`]
+    pub fn keys(&mut self, keys: &str) -> &mut Self {
+        for key in parse_keys(keys) {
+            self.app.update(Some(&Event::Key(key)));
+        }
+
+        self
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.app.editor.document.lines().clone()
+    }
+
+    pub fn screen(&self) -> Vec<String> {
+        self.screen.borrow().lines()
+    }
+
+    pub fn assert_screen(&self, expected: &[&str]) {
+        let screen = self.screen();
+
+        assert_eq!(
+            screen,
+            expected,
+            "\nscreen:\n{}\n",
+            screen
+                .iter()
+                .map(|line| format!("|{}|", line))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+}
+
+/// Key events typed by `keys`, `<...>` being a key written like the display of [`Input`]
+/// (`<C-s>`, `<esc>`, `<enter>`) and `<lt>` a literal `<`
+pub fn parse_keys(keys: &str) -> Vec<KeyEvent> {
+    let mut events = Vec::new();
+    let mut chars = keys.chars();
+
+    while let Some(char) = chars.next() {
+        let event = match char {
+            '<' => {
+                let name = chars
+                    .by_ref()
+                    .take_while(|&char| char != '>')
+                    .collect::<String>();
+
+                match name.as_str() {
+                    "lt" => KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE),
+                    name => Input::parse(name)
+                        .unwrap_or_else(|| panic!("invalid key `<{}>`", name))
+                        .to_key_event(),
+                }
+            }
+            char if char.is_uppercase() => KeyEvent::new(KeyCode::Char(char), KeyModifiers::SHIFT),
+            char => KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE),
+        };
+
+        events.push(event);
+    }
+
+    events
+}
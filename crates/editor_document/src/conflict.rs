@@ -0,0 +1,146 @@
+use std::ops::Range;
+
+/// Length of the markers git writes around the versions of a merge conflict
+const MARKER_LENGTH: usize = 7;
+
+/// Versions of a merge conflict between markers, as git writes them in conflicted files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// Line of the `<<<<<<<` marker, before our version
+    pub start: usize,
+    /// Line of the `|||||||` marker before the base version, only written in the diff3 style
+    pub base: Option<usize>,
+    /// Line of the `=======` marker, before their version
+    pub separator: usize,
+    /// Line of the `>>>>>>>` marker
+    pub end: usize,
+}
+
+/// Part of a conflict a line is in, see [`Conflict::region`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictRegion {
+    Marker,
+    Ours,
+    Base,
+    Theirs,
+}
+
+impl Conflict {
+    /// Lines of our version, the one of the current branch
+    pub fn ours(&self) -> Range<usize> {
+        self.start + 1..self.base.unwrap_or(self.separator)
+    }
+
+    /// Lines of the version of the common ancestor
+    pub fn base_lines(&self) -> Option<Range<usize>> {
+        Some(self.base? + 1..self.separator)
+    }
+
+    /// Lines of their version, the one being merged
+    pub fn theirs(&self) -> Range<usize> {
+        self.separator + 1..self.end
+    }
+
+    /// All the lines of the conflict, markers included
+    pub fn lines(&self) -> Range<usize> {
+        self.start..self.end + 1
+    }
+
+    pub fn region(&self, line: usize) -> Option<ConflictRegion> {
+        match line {
+            _ if !self.lines().contains(&line) => None,
+            _ if self.ours().contains(&line) => Some(ConflictRegion::Ours),
+            _ if self.theirs().contains(&line) => Some(ConflictRegion::Theirs),
+            _ if self.base_lines().is_some_and(|base| base.contains(&line)) => {
+                Some(ConflictRegion::Base)
+            }
+            _ => Some(ConflictRegion::Marker),
+        }
+    }
+}
+
+/// Conflicts of `lines` in order, ignoring markers out of place
+pub fn conflicts(lines: &[String]) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    // Lines of the `<<<<<<<`, `|||||||` and `=======` markers of the conflict being read
+    let mut current: Option<(usize, Option<usize>, Option<usize>)> = None;
+
+    for (y, line) in lines.iter().enumerate() {
+        current = match current {
+            _ if is_marker(line, '<') => Some((y, None, None)),
+            Some((start, None, None)) if is_marker(line, '|') => Some((start, Some(y), None)),
+            Some((start, base, None)) if is_marker(line, '=') => Some((start, base, Some(y))),
+            Some((start, base, Some(separator))) if is_marker(line, '>') => {
+                conflicts.push(Conflict {
+                    start,
+                    base,
+                    separator,
+                    end: y,
+                });
+                None
+            }
+            current => current,
+        };
+    }
+
+    conflicts
+}
+
+/// Whether `line` is a marker made of `c`, followed by a space and a label for all but `=======`
+fn is_marker(line: &str, c: char) -> bool {
+    let Some(rest) = line.strip_prefix(&c.to_string().repeat(MARKER_LENGTH)) else {
+        return false;
+    };
+
+    rest.is_empty() || rest.starts_with(' ')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_conflicts_with_and_without_base() {
+        let lines = [
+            "a",
+            "<<<<<<< HEAD",
+            "ours",
+            "=======",
+            "theirs",
+            ">>>>>>> branch",
+            "<<<<<<< HEAD",
+            "||||||| base",
+            "base",
+            "=======",
+            ">>>>>>> branch",
+            "========",
+        ]
+        .map(String::from);
+
+        let conflicts = conflicts(&lines);
+        assert_eq!(
+            conflicts,
+            [
+                Conflict {
+                    start: 1,
+                    base: None,
+                    separator: 3,
+                    end: 5
+                },
+                Conflict {
+                    start: 6,
+                    base: Some(7),
+                    separator: 9,
+                    end: 10
+                },
+            ]
+        );
+
+        assert_eq!(conflicts[0].region(2), Some(ConflictRegion::Ours));
+        assert_eq!(conflicts[0].region(3), Some(ConflictRegion::Marker));
+        assert_eq!(conflicts[0].region(4), Some(ConflictRegion::Theirs));
+        assert_eq!(conflicts[1].ours(), 7..7);
+        assert_eq!(conflicts[1].region(8), Some(ConflictRegion::Base));
+        assert!(conflicts[1].theirs().is_empty());
+    }
+}
@@ -1,17 +1,29 @@
 use std::{
+    fmt::{self, Display, Formatter},
     fs::{self, File},
-    io::{BufRead, BufReader},
-    path::PathBuf,
+    io::{self, BufRead, BufReader},
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
 };
 
 use editor_action::DocumentAction;
 use log::error;
 
-use crate::{selection::InternalSelection, Selection};
+use crate::{
+    conflict::{self, Conflict},
+    fold::{self, Fold},
+    indent::{self, Indent},
+    selection::InternalSelection,
+    text::{self, Layout},
+    undo::{Snapshot, UndoHistory},
+    Selection,
+};
 
 pub enum DocumentName {
     Scratch,
     Path(PathBuf),
+    /// Generated content like the help, not backed by a file
+    Virtual(String),
 }
 
 pub struct Document {
@@ -19,9 +31,38 @@ pub struct Document {
     lines: Vec<String>,
     selection: InternalSelection,
     dirty: bool,
+    history: UndoHistory,
+    indent: Indent,
+    layout: Layout,
+    /// Folds hiding their lines, see [`Document::folds`]
+    closed_folds: Vec<Fold>,
+    revision: usize,
+    /// Revision at which the lines were last changed, an undo going back to an older one
+    lines_revision: usize,
+    /// Lines revision of the last write, the document isn't dirty when back to it
+    saved_revision: usize,
+    /// Edit actions are ignored, see [`Document::set_read_only`]
+    read_only: bool,
 }
 
 impl Document {
+    fn new(name: DocumentName, lines: Vec<String>) -> Self {
+        Self {
+            name,
+            indent: Indent::detect(&lines).unwrap_or(Indent::DEFAULT),
+            lines,
+            selection: InternalSelection::new(),
+            dirty: false,
+            history: UndoHistory::new(),
+            layout: Layout::default(),
+            closed_folds: Vec::new(),
+            revision: 0,
+            lines_revision: 0,
+            saved_revision: 0,
+            read_only: false,
+        }
+    }
+
     pub fn from_path(path: PathBuf) -> Self {
         let lines = match File::open(&path) {
             Ok(file) => BufReader::new(file)
@@ -31,21 +72,16 @@ impl Document {
             Err(_) => Vec::new(),
         };
 
-        Self {
-            name: DocumentName::Path(path),
-            lines,
-            selection: InternalSelection::new(),
-            dirty: false,
-        }
+        Self::new(DocumentName::Path(path), lines)
     }
 
     pub fn new_scratch() -> Self {
-        Self {
-            name: DocumentName::Scratch,
-            lines: Vec::new(),
-            selection: InternalSelection::new(),
-            dirty: false,
-        }
+        Self::new(DocumentName::Scratch, Vec::new())
+    }
+
+    /// Create a document not backed by a file, displayed as `[name]`
+    pub fn from_lines(name: String, lines: Vec<String>) -> Self {
+        Self::new(DocumentName::Virtual(name), lines)
     }
 
     fn get_line_mut(&mut self, index: usize) -> &mut String {
@@ -66,6 +102,18 @@ impl Document {
         match &self.name {
             DocumentName::Scratch => "[scratch]".to_string(),
             DocumentName::Path(path) => path.display().to_string(),
+            DocumentName::Virtual(name) => format!("[{}]", name),
+        }
+    }
+
+    pub fn name(&self) -> &DocumentName {
+        &self.name
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match &self.name {
+            DocumentName::Path(path) => Some(path),
+            _ => None,
         }
     }
 
@@ -81,7 +129,358 @@ impl Document {
         self.dirty
     }
 
+    /// Counter incremented by the changes of the lines, to know when what is computed from them
+    /// is outdated
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Ignore the edit actions, for documents showing lines that can't be changed like an old
+    /// version of a file
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Indentation style used by the indent actions, detected when the document is created
+    pub fn indent(&self) -> Indent {
+        self.indent
+    }
+
+    pub fn set_indent(&mut self, indent: Indent) {
+        self.indent = indent;
+    }
+
+    /// Number of columns between tab stops, see [`text::columns`]
+    pub fn tab_width(&self) -> usize {
+        self.layout.tab_width
+    }
+
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.layout.tab_width = tab_width.max(1);
+    }
+
+    /// How the lines are laid out by the view, vertical moves going through its rows
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Wrap the lines at `wrap_width` columns, or stop wrapping them if `None`
+    pub fn set_wrap_width(&mut self, wrap_width: Option<usize>) {
+        self.layout.wrap_width = wrap_width.map(|width| width.max(1));
+    }
+
+    /// Blocks of indented lines that can be folded, see [`fold::folds`]
+    pub fn folds(&self) -> Vec<Fold> {
+        fold::folds(&self.lines, self.layout.tab_width)
+    }
+
+    /// Merge conflicts left in the lines by git, see [`Conflict`]
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        conflict::conflicts(&self.lines)
+    }
+
+    pub fn conflict_at(&self, line: usize) -> Option<Conflict> {
+        self.conflicts()
+            .into_iter()
+            .find(|conflict| conflict.lines().contains(&line))
+    }
+
+    /// Outermost closed fold starting at the line `line`
+    pub fn closed_fold(&self, line: usize) -> Option<Fold> {
+        self.closed_folds
+            .iter()
+            .filter(|fold| fold.start == line)
+            .max_by_key(|fold| fold.end)
+            .copied()
+    }
+
+    /// Whether the line `line` is hidden by a closed fold
+    pub fn is_hidden(&self, line: usize) -> bool {
+        self.closed_folds
+            .iter()
+            .any(|fold| fold.hidden().contains(&line))
+    }
+
+    /// Text covered by the selection, lines joined with `\n`
+    pub fn selected_text(&self) -> String {
+        let (min, max) = self.selection.true_min_max(&self.lines);
+
+        (min.1..=max.1)
+            .filter_map(|y| {
+                let line = self.lines.get(y)?;
+                let start = if y == min.1 { min.0 } else { 0 };
+                let text = if y == max.1 {
+                    line.chars().take(max.0).skip(start).collect::<String>()
+                } else {
+                    line.chars().skip(start).collect()
+                };
+                Some(text)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replace the line at `index`, adding empty lines before it if needed
+    pub fn set_line(&mut self, index: usize, text: String) {
+        self.save_undo_state();
+        let line_count = self.lines.len();
+        *self.get_line_mut(index) = text;
+        self.edit_folds(
+            index..=index,
+            self.lines.len() as isize - line_count as isize,
+        );
+        self.dirty = true;
+    }
+
+    /// Insert a line before the one at `index`, at the end if `index` is past it
+    pub fn insert_line(&mut self, index: usize, text: String) {
+        self.save_undo_state();
+        self.lines.insert(index.min(self.lines.len()), text);
+        self.edit_folds(index..=index, 1);
+        self.dirty = true;
+    }
+
+    pub fn remove_line(&mut self, index: usize) -> Option<String> {
+        if index >= self.lines.len() {
+            return None;
+        }
+
+        self.save_undo_state();
+        self.edit_folds(index..=index, -1);
+        self.dirty = true;
+        Some(self.lines.remove(index))
+    }
+
+    /// Replace the lines in `range` by `lines`, moving the cursor to the start of the range
+    pub fn replace_lines(&mut self, range: Range<usize>, lines: Vec<String>) {
+        let range = range.start.min(self.lines.len())..range.end.min(self.lines.len());
+
+        self.save_undo_state();
+        let added = lines.len() as isize - range.len() as isize;
+        self.edit_folds(range.start..=range.end.max(range.start + 1) - 1, added);
+        self.splice_lines(range, lines);
+    }
+
+    /// [`Document::replace_lines`] without saving the undo state or moving the folds
+    fn splice_lines(&mut self, range: Range<usize>, lines: Vec<String>) {
+        self.lines.splice(range.clone(), lines);
+
+        self.selection.collapse_to((0, range.start));
+        self.dirty = true;
+    }
+
+    /// Select from `start` to `end`, both as (column, line)
+    pub fn select(&mut self, start: (usize, usize), end: (usize, usize)) {
+        self.selection.select(start, end);
+    }
+
+    /// Group the following changes until [`Document::end_transaction`] into a single undo step
+    pub fn begin_transaction(&mut self) {
+        self.history.begin_transaction();
+    }
+
+    pub fn end_transaction(&mut self) {
+        self.history.end_transaction();
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            lines: self.lines.clone(),
+            selection: self.selection,
+            revision: self.lines_revision,
+        }
+    }
+
+    fn save_undo_state(&mut self) {
+        let (lines, selection, revision) = (&self.lines, self.selection, self.lines_revision);
+        self.history.save(|| Snapshot {
+            lines: lines.clone(),
+            selection,
+            revision,
+        });
+
+        self.revision += 1;
+        self.lines_revision = self.revision;
+    }
+
+    /// Like [`Document::save_undo_state`] with `snapshot` taken before the change
+    fn save_snapshot(&mut self, snapshot: Snapshot) {
+        self.history.save(|| snapshot);
+
+        self.revision += 1;
+        self.lines_revision = self.revision;
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.lines = snapshot.lines;
+        self.selection = snapshot.selection;
+        self.closed_folds.clear();
+        self.revision += 1;
+        self.lines_revision = snapshot.revision;
+        self.dirty = self.lines_revision != self.saved_revision;
+    }
+
+    /// Open the closed folds containing the edited `lines` and move the ones after them
+    /// by the `added` lines
+    fn edit_folds(&mut self, lines: RangeInclusive<usize>, added: isize) {
+        self.closed_folds.retain_mut(|fold| {
+            if fold.start > *lines.end() {
+                fold.start = fold.start.saturating_add_signed(added);
+                fold.end = fold.end.saturating_add_signed(added);
+                true
+            } else {
+                fold.end < *lines.start()
+            }
+        });
+    }
+
+    /// Open the closed folds hiding the line `line`
+    fn reveal(&mut self, line: usize) {
+        self.closed_folds
+            .retain(|fold| !fold.hidden().contains(&line));
+    }
+
+    /// Close the innermost open fold containing the cursor, moving it to the start of the fold
+    fn close_fold(&mut self) {
+        let (_, y) = self.selection.true_end(&self.lines);
+        let fold = self
+            .folds()
+            .into_iter()
+            .filter(|fold| fold.contains(y) && !self.closed_folds.contains(fold))
+            .max_by_key(|fold| fold.start);
+
+        if let Some(fold) = fold {
+            self.closed_folds.push(fold);
+            self.selection.collapse_to((0, fold.start));
+        }
+    }
+
+    /// Apply the vertical `motion` to the selection until its end is out of the closed folds
+    fn move_rows(&mut self, motion: impl Fn(&mut InternalSelection, &[String], Layout)) {
+        motion(&mut self.selection, &self.lines, self.layout);
+
+        while self.is_hidden(self.selection.true_end(&self.lines).1) {
+            let previous = self.selection.true_end(&self.lines);
+            motion(&mut self.selection, &self.lines, self.layout);
+
+            if self.selection.true_end(&self.lines) == previous {
+                break;
+            }
+        }
+    }
+
+    /// Indentation of a line following `line`, one more level if it opens a block
+    fn new_line_indentation(&self, line: &str) -> String {
+        let extension = self
+            .path()
+            .and_then(|path| path.extension())
+            .and_then(|extension| extension.to_str());
+
+        match indent::opens_block(line, extension) {
+            true => format!("{}{}", indent::indentation(line), self.indent.unit()),
+            false => indent::indentation(line).to_string(),
+        }
+    }
+
+    /// Remove a leading tab or a level of spaces from the line `y`, keeping the selection
+    /// on the same text
+    fn outdent_line(&mut self, y: usize) {
+        let width = match self.indent {
+            Indent::Tabs => self.layout.tab_width,
+            Indent::Spaces(width) => width,
+        };
+        let Some(line) = self.lines.get_mut(y) else {
+            return;
+        };
+
+        let removed = match line.starts_with('\t') {
+            true => 1,
+            false => line
+                .chars()
+                .take(width)
+                .take_while(|char| *char == ' ')
+                .count(),
+        };
+        if removed == 0 {
+            return;
+        }
+
+        line.replace_range(..removed, "");
+        self.selection.offset_line(y, -(removed as isize));
+        self.dirty = true;
+    }
+
+    /// Insert `text` at the start of the selection and collapse it after the text
+    fn insert_text(&mut self, text: &str) {
+        let (x, y) = self.selection.true_start(&self.lines);
+
+        let line = self.get_line_mut(y);
+        let after_cursor = line.split_off(text::byte_index(line, x));
+
+        let mut inserted = text.split('\n');
+        // `split` always yields at least one item
+        let first = inserted.next().unwrap_or_default();
+        line.push_str(first);
+
+        let mut end = (x + first.chars().count(), y);
+        for text_line in inserted {
+            end = (text_line.chars().count(), end.1 + 1);
+            self.lines.insert(end.1, text_line.to_string());
+        }
+        self.lines[end.1].push_str(&after_cursor);
+
+        self.selection.collapse_to(end);
+        self.dirty = true;
+    }
+
     pub fn handle_action(&mut self, action: DocumentAction) {
+        let (min, max) = self.selection.true_min_max(&self.lines);
+        let line_count = self.lines.len();
+        let is_edit = action.is_edit();
+
+        if is_edit && self.read_only {
+            return;
+        }
+
+        self.apply_action(action);
+
+        if is_edit {
+            let added = self.lines.len() as isize - line_count as isize;
+            self.edit_folds(min.1..=max.1, added);
+        }
+
+        // Vertical moves went over the closed folds, other actions open the ones they went in
+        let (start, end) = self.selection.true_min_max(&self.lines);
+        self.reveal(start.1);
+        self.reveal(end.1);
+    }
+
+    fn apply_action(&mut self, action: DocumentAction) {
+        // Edits which change nothing, like outdenting an unindented line, add no undo step
+        let before = match action.is_edit() {
+            true if self.history.saves_next() => Some((self.snapshot(), self.dirty)),
+            true => {
+                self.save_undo_state();
+                None
+            }
+            false => None,
+        };
+
+        self.apply_document_action(action);
+
+        if let Some((snapshot, dirty)) = before {
+            match snapshot.lines == self.lines {
+                true => self.dirty = dirty,
+                false => self.save_snapshot(snapshot),
+            }
+        }
+    }
+
+    fn apply_document_action(&mut self, action: DocumentAction) {
         use editor_action::{DocumentAction::*, SingleLineDocumentAction::*};
 
         match action {
@@ -91,15 +490,18 @@ impl Document {
                 Insert { char } => {
                     let true_start = self.selection.true_start(&self.lines);
 
-                    let line = self.get_line_mut(true_start.1);
+                    // A closing bracket typed on a blank line closes the block of the line
+                    let blank = self
+                        .get_line(true_start.1)
+                        .is_some_and(|line| !line.is_empty() && line.trim().is_empty());
+                    if indent::is_closer(char) && blank {
+                        self.outdent_line(true_start.1);
+                    }
 
-                    let index = line
-                        .char_indices()
-                        .nth(true_start.0)
-                        .map(|(i, _)| i)
-                        .unwrap_or(line.len());
+                    let true_start = self.selection.true_start(&self.lines);
+                    let line = self.get_line_mut(true_start.1);
 
-                    line.insert(index, char);
+                    line.insert(text::byte_index(line, true_start.0), char);
 
                     self.selection.move_selection_right(&self.lines);
 
@@ -116,7 +518,11 @@ impl Document {
                     let line = self.get_line_mut(true_start.1);
 
                     if true_start.0 != line.chars().count() {
-                        line.remove(true_start.0);
+                        let end = text::next_grapheme(line, true_start.0);
+                        line.replace_range(
+                            text::byte_index(line, true_start.0)..text::byte_index(line, end),
+                            "",
+                        );
                         self.dirty = true;
                     } else {
                         if let Some(after_cursor) = (true_start.1 + 1 < self.lines.len())
@@ -134,23 +540,27 @@ impl Document {
                     }
                 }
             },
-            MoveUp => self.selection.move_up(),
-            MoveDown => self.selection.move_down(&self.lines),
+            MoveUp => self.move_rows(InternalSelection::move_up),
+            MoveDown => self.move_rows(InternalSelection::move_down),
 
             ExtendEndLeft => self.selection.extend_end_left(&self.lines),
             ExtendEndRight => self.selection.extend_end_right(&self.lines),
-            ExtendEndUp => self.selection.extend_end_up(),
-            ExtendEndDown => self.selection.extend_end_down(&self.lines),
+            ExtendEndUp => self.move_rows(InternalSelection::extend_end_up),
+            ExtendEndDown => self.move_rows(InternalSelection::extend_end_down),
 
             ExtendStartLeft => self.selection.extend_start_left(&self.lines),
-            ExtendStartRight => self.selection.extend_start_left(&self.lines),
-            ExtendStartUp => self.selection.extend_start_left(&self.lines),
-            ExtendStartDown => self.selection.extend_start_left(&self.lines),
+            ExtendStartRight => self.selection.extend_start_right(&self.lines),
+            ExtendStartUp => self.move_rows(InternalSelection::extend_start_up),
+            ExtendStartDown => self.move_rows(InternalSelection::extend_start_down),
 
             MoveSelectionLeft => self.selection.move_selection_left(&self.lines),
             MoveSelectionRight => self.selection.move_selection_right(&self.lines),
-            MoveSelectionUp => self.selection.move_selection_up(),
-            MoveSelectionDown => self.selection.move_selection_down(&self.lines),
+            MoveSelectionUp => self.selection.move_selection_up(&self.lines, self.layout),
+            MoveSelectionDown => self.selection.move_selection_down(&self.lines, self.layout),
+
+            Goto { line } => self
+                .selection
+                .goto_line(line.saturating_sub(1), &self.lines),
 
             InsertLineBeforeCursor => {
                 let true_start = self.selection.true_start(&self.lines);
@@ -158,39 +568,414 @@ impl Document {
                 let selection_length = self.selection.len(&self.lines);
 
                 let line = self.get_line_mut(true_start.1);
+                let after_cursor = line.split_off(text::byte_index(line, true_start.0));
+                let after_cursor = after_cursor.trim_start();
+
+                let before_cursor = line.clone();
+                let indentation = self.new_line_indentation(&before_cursor);
+                let mut new_lines = vec![format!("{}{}", indentation, after_cursor)];
+
+                // Put the closing bracket of a block opened on the line after the new one
+                if indent::opens_block(&before_cursor, None)
+                    && after_cursor.starts_with(indent::is_closer)
+                {
+                    new_lines = vec![
+                        indentation.clone(),
+                        format!("{}{}", indent::indentation(&before_cursor), after_cursor),
+                    ];
+                }
 
-                let after_cursor = line.split_off(
-                    line.char_indices()
-                        .nth(true_start.0)
-                        .map(|(i, _)| i)
-                        .unwrap_or(line.len()),
-                );
-
-                self.lines.insert(true_start.1 + 1, after_cursor);
+                for (i, line) in new_lines.into_iter().enumerate() {
+                    self.lines.insert(true_start.1 + 1 + i, line);
+                }
 
-                self.selection.move_selection_right(&self.lines);
+                self.selection
+                    .collapse_to((indentation.chars().count(), true_start.1 + 1));
                 for _ in 0..selection_length {
                     self.selection.extend_end_right(&self.lines);
                 }
 
                 self.dirty = true;
             }
-            Write => {
-                if !self.dirty {
-                    return;
-                }
 
-                match &self.name {
-                    DocumentName::Scratch => {}
-                    DocumentName::Path(path) => {
-                        if let Err(err) = fs::write(&path, self.lines.join("\n")) {
-                            error!("Failed to write document to {}, {:?}", path.display(), err);
-                        } else {
-                            self.dirty = false;
-                        };
+            OpenLineBelow => {
+                let (_, y) = self.selection.true_end(&self.lines);
+                let indentation = self.new_line_indentation(self.get_line(y).unwrap_or(""));
+
+                let y = (y + 1).min(self.lines.len());
+                self.lines.insert(y, indentation.clone());
+                self.selection.collapse_to((indentation.chars().count(), y));
+                self.dirty = true;
+            }
+
+            OpenLineAbove => {
+                let (_, y) = self.selection.true_start(&self.lines);
+                let indentation = indent::indentation(self.get_line(y).unwrap_or("")).to_string();
+
+                let y = y.min(self.lines.len());
+                self.lines.insert(y, indentation.clone());
+                self.selection.collapse_to((indentation.chars().count(), y));
+                self.dirty = true;
+            }
+
+            InsertText { text } => self.insert_text(&text),
+
+            InsertTab => {
+                let text = match self.indent {
+                    crate::Indent::Tabs => "\t".to_string(),
+                    crate::Indent::Spaces(width) => {
+                        let (x, y) = self.selection.true_start(&self.lines);
+                        let column =
+                            text::column(self.get_line(y).unwrap_or(""), x, self.layout.tab_width);
+                        " ".repeat(width - column % width.max(1))
+                    }
+                };
+                self.insert_text(&text);
+            }
+
+            Indent => {
+                let (min, max) = self.selection.true_min_max(&self.lines);
+                let unit = self.indent.unit();
+                self.selection.select(min, max);
+
+                for y in min.1..=max.1.min(self.lines.len().saturating_sub(1)) {
+                    if self.lines[y].is_empty() {
+                        continue;
                     }
+                    self.lines[y].insert_str(0, &unit);
+                    self.selection.offset_line(y, unit.chars().count() as isize);
+                    self.dirty = true;
+                }
+            }
+
+            Outdent => {
+                let (min, max) = self.selection.true_min_max(&self.lines);
+                self.selection.select(min, max);
+
+                for y in min.1..=max.1.min(self.lines.len().saturating_sub(1)) {
+                    self.outdent_line(y);
+                }
+            }
+
+            Fold => self.close_fold(),
+            Unfold => {
+                let (_, y) = self.selection.true_end(&self.lines);
+                self.closed_folds.retain(|fold| fold.start != y);
+            }
+            ToggleFold => {
+                let (_, y) = self.selection.true_end(&self.lines);
+                match self.closed_fold(y) {
+                    Some(_) => self.closed_folds.retain(|fold| fold.start != y),
+                    None => self.close_fold(),
+                }
+            }
+            FoldLevel { level } => {
+                self.closed_folds = self
+                    .folds()
+                    .into_iter()
+                    .filter(|fold| fold.level >= level)
+                    .collect();
+
+                // Keep the cursor visible at the start of the outermost fold hiding it
+                let (_, y) = self.selection.true_end(&self.lines);
+                let outermost = self
+                    .closed_folds
+                    .iter()
+                    .filter(|fold| fold.hidden().contains(&y))
+                    .min_by_key(|fold| fold.start);
+                if let Some(fold) = outermost {
+                    self.selection.collapse_to((0, fold.start));
+                }
+            }
+            UnfoldAll => self.closed_folds.clear(),
+
+            NextConflict | PreviousConflict => {
+                let (_, y) = self.selection.true_end(&self.lines);
+                let starts = self.conflicts().into_iter().map(|conflict| conflict.start);
+
+                let line = match action {
+                    NextConflict => starts.filter(|start| *start > y).min(),
+                    _ => starts.filter(|start| *start < y).max(),
+                };
+                if let Some(line) = line {
+                    self.selection.goto_line(line, &self.lines);
+                }
+            }
+            AcceptOurs | AcceptTheirs | AcceptBoth => {
+                let (_, y) = self.selection.true_end(&self.lines);
+                let Some(conflict) = self.conflict_at(y) else {
+                    return;
+                };
+
+                let ranges = match action {
+                    AcceptOurs => vec![conflict.ours()],
+                    AcceptTheirs => vec![conflict.theirs()],
+                    _ => vec![conflict.ours(), conflict.theirs()],
+                };
+                let lines = ranges
+                    .into_iter()
+                    .flat_map(|range| self.lines[range].to_vec())
+                    .collect();
+
+                self.splice_lines(conflict.lines(), lines);
+            }
+
+            Undo => {
+                if let Some(snapshot) = self.history.undo(self.snapshot()) {
+                    self.restore(snapshot);
+                }
+            }
+            Redo => {
+                if let Some(snapshot) = self.history.redo(self.snapshot()) {
+                    self.restore(snapshot);
                 }
             }
         }
     }
+
+    /// Write the document to its path
+    pub fn write(&mut self) -> Result<WriteReport, WriteError> {
+        let DocumentName::Path(path) = &self.name else {
+            return Err(WriteError::NoPath);
+        };
+
+        let report = write_lines(path, &self.lines)?;
+        self.dirty = false;
+        self.saved_revision = self.lines_revision;
+
+        Ok(report)
+    }
+
+    /// Write the document to `path` and make it the path of the document
+    pub fn write_to(&mut self, path: PathBuf) -> Result<WriteReport, WriteError> {
+        let report = write_lines(&path, &self.lines)?;
+        self.name = DocumentName::Path(path);
+        self.dirty = false;
+        self.saved_revision = self.lines_revision;
+
+        Ok(report)
+    }
+
+    /// Change the path of the document, moving the file on disk if it exists, refusing to
+    /// replace another file
+    pub fn rename(&mut self, path: PathBuf) -> Result<(), WriteError> {
+        check_parent(&path)?;
+        if path.exists() {
+            return Err(WriteError::AlreadyExists(path));
+        }
+
+        if let DocumentName::Path(old_path) = &self.name {
+            if old_path.exists() {
+                fs::rename(old_path, &path).map_err(WriteError::Io)?;
+            }
+        }
+
+        self.name = DocumentName::Path(path);
+
+        Ok(())
+    }
+}
+
+/// Summary of a successful write, displayed as `written 120 lines, 3.4 KiB`
+#[derive(Clone, Copy, Debug)]
+pub struct WriteReport {
+    pub lines: usize,
+    pub bytes: usize,
+}
+
+impl Display for WriteReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "written {} lines, ", self.lines)?;
+
+        match self.bytes {
+            bytes if bytes < 1024 => write!(f, "{} B", bytes),
+            bytes if bytes < 1024 * 1024 => write!(f, "{:.1} KiB", bytes as f64 / 1024.0),
+            bytes => write!(f, "{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WriteError {
+    /// The document has no path to be written to
+    NoPath,
+    /// The parent directory of the path doesn't exist
+    MissingParent(PathBuf),
+    /// A file is already at the path the document is renamed to
+    AlreadyExists(PathBuf),
+    Io(io::Error),
+}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::NoPath => write!(f, "document has no path, use :write <path>"),
+            WriteError::MissingParent(parent) => {
+                write!(f, "directory {} doesn't exist", parent.display())
+            }
+            WriteError::AlreadyExists(path) => write!(f, "{} already exists", path.display()),
+            WriteError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+fn check_parent(path: &Path) -> Result<(), WriteError> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() && !parent.is_dir() => {
+            Err(WriteError::MissingParent(parent.to_path_buf()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn write_lines(path: &Path, lines: &[String]) -> Result<WriteReport, WriteError> {
+    check_parent(path)?;
+
+    let content = lines.join("\n");
+
+    if let Err(err) = fs::write(path, &content) {
+        error!("Failed to write document to {}, {:?}", path.display(), err);
+        return Err(WriteError::Io(err));
+    }
+
+    Ok(WriteReport {
+        lines: lines.len(),
+        bytes: content.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use editor_action::SingleLineDocumentAction;
+
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn extends_the_start_of_the_selection() {
+        let mut document = Document::from_lines("test".to_string(), lines(&["abc", "def"]));
+        document.select((1, 0), (2, 1));
+
+        document.handle_action(DocumentAction::ExtendStartRight);
+        assert_eq!(document.selection().start(), (2, 0));
+        document.handle_action(DocumentAction::ExtendStartDown);
+        assert_eq!(document.selection().start(), (2, 1));
+        document.handle_action(DocumentAction::ExtendStartUp);
+        assert_eq!(document.selection().start(), (2, 0));
+        document.handle_action(DocumentAction::ExtendStartLeft);
+        assert_eq!(document.selection().start(), (1, 0));
+        assert_eq!(document.selection().end(), (2, 1));
+    }
+
+    #[test]
+    fn undoing_back_to_the_written_lines_is_not_dirty() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut document = Document::from_lines("test".to_string(), lines(&["a"]));
+        let insert = |document: &mut Document| {
+            document.handle_action(DocumentAction::SingleLine(
+                SingleLineDocumentAction::Insert { char: 'x' },
+            ))
+        };
+
+        insert(&mut document);
+        assert!(document.dirty());
+        document.handle_action(DocumentAction::Undo);
+        assert!(!document.dirty());
+
+        insert(&mut document);
+        document.write_to(dir.path().join("file.txt")).unwrap();
+        document.handle_action(DocumentAction::Undo);
+        assert!(document.dirty());
+        document.handle_action(DocumentAction::Redo);
+        assert!(!document.dirty());
+
+        insert(&mut document);
+        document.handle_action(DocumentAction::Undo);
+        assert!(!document.dirty());
+    }
+
+    #[test]
+    fn edits_changing_nothing_add_no_undo_step() {
+        let mut document = Document::from_lines("test".to_string(), lines(&["a"]));
+
+        document.handle_action(DocumentAction::Indent);
+        document.handle_action(DocumentAction::Outdent);
+        document.handle_action(DocumentAction::Outdent);
+        assert_eq!(document.lines(), &lines(&["a"]));
+
+        // Undo goes back to the indented line, the last outdent changed nothing
+        document.handle_action(DocumentAction::Undo);
+        assert_ne!(document.lines(), &lines(&["a"]));
+
+        document.handle_action(DocumentAction::Undo);
+        document.handle_action(DocumentAction::Outdent);
+        assert!(!document.dirty());
+    }
+
+    #[test]
+    fn writes_to_a_new_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        let mut document = Document::from_lines("new".to_string(), lines(&["a", "bc"]));
+
+        assert!(matches!(document.write(), Err(WriteError::NoPath)));
+
+        let report = document.write_to(path.clone()).unwrap();
+        assert_eq!((report.lines, report.bytes), (2, 4));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nbc");
+        assert_eq!(document.path(), Some(path.as_path()));
+        assert!(!document.dirty());
+
+        let missing = dir.path().join("missing");
+        assert!(matches!(
+            document.write_to(missing.join("file.txt")),
+            Err(WriteError::MissingParent(parent)) if parent == missing
+        ));
+        assert_eq!(document.path(), Some(path.as_path()));
+    }
+
+    #[test]
+    fn renames_without_replacing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (old, new, other) = (
+            dir.path().join("old.txt"),
+            dir.path().join("new.txt"),
+            dir.path().join("other.txt"),
+        );
+        fs::write(&old, "old").unwrap();
+        fs::write(&other, "other").unwrap();
+        let mut document = Document::from_path(old.clone());
+
+        assert!(matches!(
+            document.rename(other.clone()),
+            Err(WriteError::AlreadyExists(path)) if path == other
+        ));
+        assert_eq!(fs::read_to_string(&other).unwrap(), "other");
+        assert!(old.exists());
+
+        assert!(matches!(
+            document.rename(dir.path().join("missing/new.txt")),
+            Err(WriteError::MissingParent(_))
+        ));
+
+        document.rename(new.clone()).unwrap();
+        assert!(!old.exists());
+        assert_eq!(fs::read_to_string(&new).unwrap(), "old");
+        assert_eq!(document.path(), Some(new.as_path()));
+    }
+
+    #[test]
+    fn checks_the_parent_directory() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(check_parent(Path::new("relative.txt")).is_ok());
+        assert!(check_parent(&dir.path().join("file.txt")).is_ok());
+        assert!(matches!(
+            check_parent(&dir.path().join("a/b/file.txt")),
+            Err(WriteError::MissingParent(parent)) if parent == dir.path().join("a/b")
+        ));
+    }
 }
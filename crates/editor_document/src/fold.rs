@@ -0,0 +1,105 @@
+use std::ops::RangeInclusive;
+
+use crate::{indent, text};
+
+/// Lines that can be folded under the line starting them, found from the indentation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fold {
+    /// Line staying visible when the fold is closed
+    pub start: usize,
+    /// Last line hidden by the fold
+    pub end: usize,
+    /// Number of folds containing this one
+    pub level: usize,
+}
+
+impl Fold {
+    /// Lines hidden when the fold is closed
+    pub fn hidden(&self) -> RangeInclusive<usize> {
+        self.start + 1..=self.end
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        (self.start..=self.end).contains(&line)
+    }
+}
+
+/// Folds of `lines` ordered by start, each line followed by more indented ones starting one
+///
+/// Blank lines belong to the fold of the lines around them but aren't hidden at its end
+pub fn folds(lines: &[String], tab_width: usize) -> Vec<Fold> {
+    let mut folds = Vec::new();
+    // Lines whose fold is still open with their indentation width
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut last = 0;
+
+    let mut close = |stack: &mut Vec<(usize, usize)>, last: usize, width: Option<usize>| {
+        while let Some(&(start, start_width)) = stack.last() {
+            if width.is_some_and(|width| width > start_width) {
+                break;
+            }
+
+            stack.pop();
+            if last > start {
+                folds.push(Fold {
+                    start,
+                    end: last,
+                    level: stack.len(),
+                });
+            }
+        }
+    };
+
+    for (y, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let indentation = indent::indentation(line);
+        let width = text::column(line, indentation.chars().count(), tab_width);
+
+        close(&mut stack, last, Some(width));
+        stack.push((y, width));
+        last = y;
+    }
+    close(&mut stack, last, None);
+
+    folds.sort_by_key(|fold| fold.start);
+    folds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_indented_blocks() {
+        let lines = [
+            "fn a() {",
+            "    if b {",
+            "        c",
+            "",
+            "    }",
+            "",
+            "}",
+            "d",
+        ]
+        .map(String::from);
+
+        assert_eq!(
+            folds(&lines, 4),
+            [
+                Fold {
+                    start: 0,
+                    end: 4,
+                    level: 0
+                },
+                Fold {
+                    start: 1,
+                    end: 2,
+                    level: 1
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+/// Indentation style of a document, detected from its content, see [`Indent::detect`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    Tabs,
+    /// Levels of `n` spaces
+    Spaces(usize),
+}
+
+impl Indent {
+    /// Style used when a document has no indented line
+    pub const DEFAULT: Self = Self::Spaces(4);
+
+    /// Guess the style of `lines`, `None` if no line is indented
+    ///
+    /// Tabs win if more lines start with one than with a space, the width of spaces is the
+    /// most common difference of indentation between consecutive lines
+    pub fn detect(lines: &[String]) -> Option<Self> {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        // Count of each indentation difference between consecutive space indented lines
+        let mut differences = HashMap::<usize, usize>::new();
+        let mut previous = 0;
+
+        for line in lines.iter().filter(|line| !line.trim().is_empty()) {
+            if line.starts_with('\t') {
+                tab_lines += 1;
+                continue;
+            }
+
+            let spaces = line.chars().take_while(|char| *char == ' ').count();
+            if spaces > 0 {
+                space_lines += 1;
+            }
+
+            let difference = spaces.abs_diff(previous);
+            if (1..=8).contains(&difference) {
+                *differences.entry(difference).or_default() += 1;
+            }
+            previous = spaces;
+        }
+
+        if tab_lines == 0 && space_lines == 0 {
+            return None;
+        }
+        if tab_lines > space_lines {
+            return Some(Self::Tabs);
+        }
+
+        differences
+            .into_iter()
+            .max_by_key(|(width, count)| (*count, std::cmp::Reverse(*width)))
+            .map(|(width, _)| Self::Spaces(width))
+    }
+
+    /// Text of one level of indentation
+    pub fn unit(&self) -> String {
+        match self {
+            Self::Tabs => "\t".to_string(),
+            Self::Spaces(width) => " ".repeat(*width),
+        }
+    }
+}
+
+/// Whether a line ending with `text` opens a block whose lines are indented one more level,
+/// like after `{` or a Python `:`, `extension` being the one of the document file
+pub fn opens_block(text: &str, extension: Option<&str>) -> bool {
+    let colon_blocks = matches!(extension, Some("py" | "pyi" | "yaml" | "yml" | "nim"));
+
+    match text.trim_end().chars().last() {
+        Some('{' | '(' | '[') => true,
+        Some(':') => colon_blocks,
+        _ => false,
+    }
+}
+
+/// Whether `char` closes a block opened by a bracket, see [`opens_block`]
+pub fn is_closer(char: char) -> bool {
+    matches!(char, '}' | ')' | ']')
+}
+
+/// Leading spaces and tabs of `line`
+pub fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn detects_the_indentation() {
+        assert_eq!(Indent::detect(&lines("a\nb")), None);
+        assert_eq!(
+            Indent::detect(&lines("fn a() {\n\tb();\n\tif c {\n\t\td();\n\t}\n}")),
+            Some(Indent::Tabs)
+        );
+        assert_eq!(
+            Indent::detect(&lines("a:\n  b:\n    c\n  d\n\ne:\n  f")),
+            Some(Indent::Spaces(2))
+        );
+        assert_eq!(
+            Indent::detect(&lines("fn a() {\n    b(\n        c,\n    );\n}")),
+            Some(Indent::Spaces(4))
+        );
+    }
+
+    #[test]
+    fn detects_block_openers() {
+        assert!(opens_block("fn a() {  ", Some("rs")));
+        assert!(opens_block("call(", None));
+        assert!(opens_block("if a:", Some("py")));
+        assert!(!opens_block("a: b:", Some("rs")));
+        assert_eq!(indentation("\t  a b"), "\t  ");
+    }
+}
@@ -1,7 +1,15 @@
+mod conflict;
 mod document;
+mod fold;
+mod indent;
 mod selection;
 mod single_line_document;
+pub mod text;
+mod undo;
 
-pub use document::Document;
+pub use conflict::{Conflict, ConflictRegion};
+pub use document::{Document, DocumentName, WriteError, WriteReport};
+pub use fold::Fold;
+pub use indent::Indent;
 pub use selection::Selection;
 pub use single_line_document::SingleLineDocument;
@@ -1,5 +1,7 @@
 use std::cmp::Ordering;
 
+use crate::text::{self, Layout};
+
 #[derive(Clone, Copy, Debug)]
 pub struct Selection {
     true_start: (usize, usize),
@@ -54,6 +56,7 @@ impl InternalSelection {
         }
     }
 
+    /// Number of graphemes in the selection
     pub fn len(&self, lines: &[String]) -> usize {
         let (min, max) = self.true_min_max(lines);
 
@@ -62,19 +65,12 @@ impl InternalSelection {
                 return len;
             };
 
-            if y == min.1 && y == max.1 {
-                len + line
-                    .chars()
-                    .skip(min.0)
-                    .take(max.0.saturating_sub(min.0))
-                    .count()
-            } else if y == min.1 {
-                len + line.chars().skip(min.0).count()
-            } else if y == max.1 {
-                len + line.chars().take(max.0).count()
-            } else {
-                len + line.chars().count()
-            }
+            let start = if y == min.1 { min.0 } else { 0 };
+            let end = if y == max.1 { max.0 } else { usize::MAX };
+
+            len + text::graphemes(line)
+                .filter(|(x, _)| (start..end).contains(x))
+                .count()
         })
     }
 
@@ -85,7 +81,7 @@ impl InternalSelection {
         }
     }
 
-    fn true_min_max(&self, lines: &[String]) -> ((usize, usize), (usize, usize)) {
+    pub fn true_min_max(&self, lines: &[String]) -> ((usize, usize), (usize, usize)) {
         let true_start = self.true_start(lines);
         let true_end = self.true_end(lines);
 
@@ -102,19 +98,15 @@ impl InternalSelection {
 
     pub fn true_start(&self, lines: &[String]) -> (usize, usize) {
         let y = self.start.1.min(lines.len());
-        let x = self
-            .start
-            .0
-            .min(lines.get(y).map(|line| line.chars().count()).unwrap_or(0));
+        let line = lines.get(y).map(String::as_str).unwrap_or("");
+        let x = text::grapheme_start(line, self.start.0.min(line.chars().count()));
         (x, y)
     }
 
     pub fn true_end(&self, lines: &[String]) -> (usize, usize) {
         let y = self.end.1.min(lines.len());
-        let x = self
-            .end
-            .0
-            .min(lines.get(y).map(|line| line.chars().count()).unwrap_or(0));
+        let line = lines.get(y).map(String::as_str).unwrap_or("");
+        let x = text::grapheme_start(line, self.end.0.min(line.chars().count()));
         (x, y)
     }
 
@@ -138,6 +130,34 @@ impl InternalSelection {
         self.end = true_start;
     }
 
+    pub fn select(&mut self, start: (usize, usize), end: (usize, usize)) {
+        self.start = start;
+        self.end = end;
+    }
+
+    /// Move the positions on the line `y` by `offset` chars, after text was added or removed
+    /// at the start of the line
+    pub fn offset_line(&mut self, y: usize, offset: isize) {
+        for position in [&mut self.start, &mut self.end] {
+            if position.1 == y {
+                position.0 = position.0.saturating_add_signed(offset);
+            }
+        }
+    }
+
+    /// Collapse the selection at `position` (column, line)
+    pub fn collapse_to(&mut self, position: (usize, usize)) {
+        self.start = position;
+        self.end = position;
+    }
+
+    /// Collapse the selection at the start of the `line` (0 based)
+    pub fn goto_line(&mut self, line: usize, lines: &[String]) {
+        let line = line.min(lines.len().saturating_sub(1));
+        self.start = (0, line);
+        self.end = (0, line);
+    }
+
     pub fn move_left(&mut self, lines: &[String]) {
         self.extend_end_left(lines);
         self.collapse_to_end();
@@ -146,12 +166,12 @@ impl InternalSelection {
         self.extend_end_right(lines);
         self.collapse_to_end();
     }
-    pub fn move_down(&mut self, lines: &[String]) {
-        self.extend_end_down(lines);
+    pub fn move_down(&mut self, lines: &[String], layout: Layout) {
+        self.extend_end_down(lines, layout);
         self.collapse_to_end();
     }
-    pub fn move_up(&mut self) {
-        self.extend_end_up();
+    pub fn move_up(&mut self, lines: &[String], layout: Layout) {
+        self.extend_end_up(lines, layout);
         self.collapse_to_end();
     }
 
@@ -163,10 +183,13 @@ impl InternalSelection {
                 self.end = (0, 0);
             } else {
                 self.end.1 = self.end.1 - 1;
-                self.end.0 = lines.get(self.end.1).map(|line| line.len()).unwrap_or(0);
+                self.end.0 = lines
+                    .get(self.end.1)
+                    .map(|line| line.chars().count())
+                    .unwrap_or(0);
             }
         } else {
-            self.end.0 = self.end.0 - 1;
+            self.end.0 = text::previous_grapheme(line(lines, self.end.1), self.end.0);
         }
     }
     pub fn extend_end_right(&mut self, lines: &[String]) {
@@ -183,16 +206,14 @@ impl InternalSelection {
                 self.end.1 = self.end.1.saturating_add(1);
             }
         } else {
-            self.end.0 = self.end.0.saturating_add(1);
+            self.end.0 = text::next_grapheme(line(lines, self.end.1), self.end.0);
         }
     }
-    pub fn extend_end_down(&mut self, lines: &[String]) {
-        if self.end.1 < lines.len() {
-            self.end.1 = self.end.1.saturating_add(1);
-        }
+    pub fn extend_end_down(&mut self, lines: &[String], layout: Layout) {
+        self.end = move_down(self.end, lines, layout);
     }
-    pub fn extend_end_up(&mut self) {
-        self.end.1 = self.end.1.saturating_sub(1);
+    pub fn extend_end_up(&mut self, lines: &[String], layout: Layout) {
+        self.end = move_up(self.end, lines, layout);
     }
 
     pub fn extend_start_left(&mut self, lines: &[String]) {
@@ -203,10 +224,13 @@ impl InternalSelection {
                 self.start = (0, 0);
             } else {
                 self.start.1 = self.start.1 - 1;
-                self.start.0 = lines.get(self.start.1).map(|line| line.len()).unwrap_or(0);
+                self.start.0 = lines
+                    .get(self.start.1)
+                    .map(|line| line.chars().count())
+                    .unwrap_or(0);
             }
         } else {
-            self.start.0 = self.start.0 - 1;
+            self.start.0 = text::previous_grapheme(line(lines, self.start.1), self.start.0);
         }
     }
     pub fn extend_start_right(&mut self, lines: &[String]) {
@@ -223,16 +247,14 @@ impl InternalSelection {
                 self.start.1 = self.start.1.saturating_add(1);
             }
         } else {
-            self.start.0 = self.start.0.saturating_add(1);
+            self.start.0 = text::next_grapheme(line(lines, self.start.1), self.start.0);
         }
     }
-    pub fn extend_start_down(&mut self, lines: &[String]) {
-        if self.start.1 < lines.len() {
-            self.start.1 = self.start.1.saturating_add(1);
-        }
+    pub fn extend_start_down(&mut self, lines: &[String], layout: Layout) {
+        self.start = move_down(self.start, lines, layout);
     }
-    pub fn extend_start_up(&mut self) {
-        self.start.1 = self.start.1.saturating_sub(1);
+    pub fn extend_start_up(&mut self, lines: &[String], layout: Layout) {
+        self.start = move_up(self.start, lines, layout);
     }
 
     pub fn move_selection_left(&mut self, lines: &[String]) {
@@ -243,12 +265,75 @@ impl InternalSelection {
         self.extend_end_right(lines);
         self.extend_start_right(lines);
     }
-    pub fn move_selection_down(&mut self, lines: &[String]) {
-        self.extend_end_down(lines);
-        self.extend_start_down(lines);
+    pub fn move_selection_down(&mut self, lines: &[String], layout: Layout) {
+        self.extend_end_down(lines, layout);
+        self.extend_start_down(lines, layout);
     }
-    pub fn move_selection_up(&mut self) {
-        self.extend_end_up();
-        self.extend_start_up();
+    pub fn move_selection_up(&mut self, lines: &[String], layout: Layout) {
+        self.extend_end_up(lines, layout);
+        self.extend_start_up(lines, layout);
+    }
+}
+
+fn line(lines: &[String], y: usize) -> &str {
+    lines.get(y).map(String::as_str).unwrap_or("")
+}
+
+/// Row of `position` and its column in the row, see [`text::rows`]
+fn row_column(position: (usize, usize), lines: &[String], layout: Layout) -> (usize, usize) {
+    let line = line(lines, position.1);
+    let rows = text::rows(line, layout);
+    let row = text::row_of(&rows, position.0);
+
+    let column = text::column(line, position.0, layout.tab_width)
+        - text::column(line, rows[row], layout.tab_width);
+
+    (row, column)
+}
+
+/// Position at `column` in the row `row` of the line `y`, see [`text::rows`]
+///
+/// Positions past the end of a row that isn't the last are moved to its last grapheme
+fn row_position(
+    (row, column): (usize, usize),
+    y: usize,
+    lines: &[String],
+    layout: Layout,
+) -> (usize, usize) {
+    let line = line(lines, y);
+    let rows = text::rows(line, layout);
+    let row = row.min(rows.len() - 1);
+
+    let row_start = text::column(line, rows[row], layout.tab_width);
+    let x = text::char_index(line, row_start + column, layout.tab_width);
+
+    match rows.get(row + 1) {
+        Some(next) => (x.min(text::previous_grapheme(line, *next)), y),
+        None => (x, y),
+    }
+}
+
+/// Move `position` one row down, keeping its column
+fn move_down(position: (usize, usize), lines: &[String], layout: Layout) -> (usize, usize) {
+    let (row, column) = row_column(position, lines, layout);
+    let row_count = text::rows(line(lines, position.1), layout).len();
+
+    if row + 1 < row_count {
+        row_position((row + 1, column), position.1, lines, layout)
+    } else if position.1 < lines.len() {
+        row_position((0, column), position.1 + 1, lines, layout)
+    } else {
+        position
+    }
+}
+
+/// Move `position` one row up, keeping its column
+fn move_up(position: (usize, usize), lines: &[String], layout: Layout) -> (usize, usize) {
+    let (row, column) = row_column(position, lines, layout);
+
+    match (row, position.1) {
+        (0, 0) => row_position((0, column), 0, lines, layout),
+        (0, y) => row_position((usize::MAX, column), y - 1, lines, layout),
+        (row, y) => row_position((row - 1, column), y, lines, layout),
     }
 }
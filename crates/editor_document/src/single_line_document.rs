@@ -1,5 +1,7 @@
 use editor_action::SingleLineDocumentAction;
 
+use crate::text;
+
 pub struct SingleLineDocument {
     line: String,
     cursor: usize,
@@ -17,10 +19,17 @@ impl SingleLineDocument {
         &self.line
     }
 
+    /// Char index of the cursor, at the start of a grapheme
     pub fn cursor(&self) -> usize {
         self.cursor
     }
 
+    /// Replace the line, moving the cursor at its end
+    pub fn set_line(&mut self, line: String) {
+        self.cursor = line.chars().count();
+        self.line = line;
+    }
+
     pub fn clear(&mut self) {
         self.line = String::new();
         self.cursor = 0;
@@ -30,28 +39,26 @@ impl SingleLineDocument {
         use editor_action::SingleLineDocumentAction::*;
 
         match action {
-            MoveLeft => self.cursor = self.cursor.saturating_sub(1),
+            MoveLeft => self.cursor = text::previous_grapheme(&self.line, self.cursor),
             MoveRight => {
-                if self.cursor < self.line.len() {
-                    self.cursor += 1;
+                if self.cursor < self.line.chars().count() {
+                    self.cursor = text::next_grapheme(&self.line, self.cursor);
                 }
             }
             Insert { char } => {
-                let i = self
-                    .line
-                    .char_indices()
-                    .nth(self.cursor)
-                    .map(|(i, _)| i)
-                    .unwrap_or(self.line.len());
-                self.cursor += 1;
-                self.line.insert(i, char);
+                self.line
+                    .insert(text::byte_index(&self.line, self.cursor), char);
+                self.cursor = text::next_grapheme(&self.line, self.cursor);
             }
             DeleteBefore => {
                 if self.cursor > 0 {
-                    self.cursor -= 1;
-                    if let Some((i, _)) = self.line.char_indices().nth(self.cursor) {
-                        self.line.remove(i);
-                    }
+                    let end = self.cursor;
+                    self.cursor = text::previous_grapheme(&self.line, self.cursor);
+                    self.line.replace_range(
+                        text::byte_index(&self.line, self.cursor)
+                            ..text::byte_index(&self.line, end),
+                        "",
+                    );
                 }
             }
         }
@@ -0,0 +1,270 @@
+//! Grapheme clusters and display width of lines
+//!
+//! Positions in a line are char indices like the ones of [`Selection`](crate::Selection),
+//! columns are terminal cells. The cursor moves by grapheme and a grapheme takes at least
+//! one column, zero width ones being drawn on their own cell.
+//!
+//! A tab goes to the next multiple of the tab width. Positions past the end of a line are
+//! one column per char, for the cursor to keep its column when going through shorter lines
+//!
+//! With soft wrap, lines are split in rows at word boundaries, see [`rows`]
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// How lines are laid out on screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub tab_width: usize,
+    /// Width lines are wrapped at, `None` if they are scrolled horizontally
+    pub wrap_width: Option<usize>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            wrap_width: None,
+        }
+    }
+}
+
+/// Number of columns taken by `grapheme`
+pub fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width().max(1)
+}
+
+/// Number of columns taken by `str`
+pub fn width(str: &str) -> usize {
+    str.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Graphemes of `line` with the char index they start at
+pub fn graphemes(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.graphemes(true).scan(0, |x, grapheme| {
+        let start = *x;
+        *x += grapheme.chars().count();
+        Some((start, grapheme))
+    })
+}
+
+/// Start of the grapheme containing the char `x`
+pub fn grapheme_start(line: &str, x: usize) -> usize {
+    if x >= line.chars().count() {
+        return x;
+    }
+
+    graphemes(line)
+        .map(|(start, _)| start)
+        .take_while(|start| *start <= x)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Start of the grapheme after the one containing the char `x`
+pub fn next_grapheme(line: &str, x: usize) -> usize {
+    graphemes(line)
+        .map(|(start, grapheme)| start + grapheme.chars().count())
+        .find(|end| *end > x)
+        .unwrap_or(x + 1)
+}
+
+/// Start of the grapheme before the one containing the char `x`
+pub fn previous_grapheme(line: &str, x: usize) -> usize {
+    let count = line.chars().count();
+
+    if x > count {
+        return x - 1;
+    }
+
+    graphemes(line)
+        .map(|(start, _)| start)
+        .take_while(|start| *start < x)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Graphemes of `line` with the char index and column they start at and their width
+pub fn columns(line: &str, tab_width: usize) -> impl Iterator<Item = (usize, usize, &str, usize)> {
+    graphemes(line).scan(0, move |column, (x, grapheme)| {
+        let start = *column;
+        let width = match grapheme {
+            "\t" => tab_width.max(1) - start % tab_width.max(1),
+            grapheme => grapheme_width(grapheme),
+        };
+        *column += width;
+        Some((x, start, grapheme, width))
+    })
+}
+
+/// Column of the char `x`
+pub fn column(line: &str, x: usize, tab_width: usize) -> usize {
+    let mut end_column = 0;
+    let mut end = 0;
+
+    for (start, column, grapheme, width) in columns(line, tab_width) {
+        if start >= x {
+            return column;
+        }
+        end_column = column + width;
+        end = start + grapheme.chars().count();
+    }
+
+    end_column + x.saturating_sub(end)
+}
+
+/// Char index of the grapheme covering `column`
+pub fn char_index(line: &str, column: usize, tab_width: usize) -> usize {
+    let mut end_column = 0;
+    let mut end = 0;
+
+    for (start, start_column, grapheme, width) in columns(line, tab_width) {
+        end_column = start_column + width;
+        if end_column > column {
+            return start;
+        }
+        end = start + grapheme.chars().count();
+    }
+
+    end + column - end_column
+}
+
+/// Byte index of the char `x`, the length of `line` if past its end
+pub fn byte_index(line: &str, x: usize) -> usize {
+    line.char_indices()
+        .nth(x)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len())
+}
+
+/// Char indices the rows of `line` start at, a single `0` if it isn't wrapped
+///
+/// A row ends after the last whitespace fitting in the wrap width, or at the last grapheme
+/// fitting if it has none. A grapheme wider than the wrap width gets a row of its own and
+/// a line filling its last row is followed by an empty one for the cursor
+pub fn rows(line: &str, layout: Layout) -> Vec<usize> {
+    let mut rows = vec![0];
+    let Some(wrap_width) = layout.wrap_width.map(|width| width.max(1)) else {
+        return rows;
+    };
+
+    let mut row_column = 0;
+    // Start and column of the grapheme after the last whitespace of the row
+    let mut word_start = None;
+    let (mut end, mut end_column) = (0, 0);
+
+    for (x, column, grapheme, width) in columns(line, layout.tab_width) {
+        if column + width > row_column + wrap_width && x > rows[rows.len() - 1] {
+            let (start, start_column) = word_start.take().unwrap_or((x, column));
+            row_column = start_column;
+            rows.push(start);
+        }
+
+        end = x + grapheme.chars().count();
+        end_column = column + width;
+        if grapheme.chars().all(char::is_whitespace) {
+            word_start = Some((end, end_column));
+        }
+    }
+
+    // Room for the cursor after the last grapheme
+    if end_column >= row_column + wrap_width {
+        rows.push(end);
+    }
+
+    rows
+}
+
+/// Index in `rows` of the row containing the char `x`, see [`rows`]
+pub fn row_of(rows: &[usize], x: usize) -> usize {
+    rows.iter().rposition(|start| *start <= x).unwrap_or(0)
+}
+
+/// Start of `str` with the graphemes fitting in `width` columns
+pub fn truncate(str: &str, width: usize) -> &str {
+    let mut columns = 0;
+    let end = str
+        .grapheme_indices(true)
+        .find(|(_, grapheme)| {
+            columns += grapheme_width(grapheme);
+            columns > width
+        })
+        .map_or(str.len(), |(end, _)| end);
+
+    &str[..end]
+}
+
+/// `str` followed by spaces to be `width` columns wide
+pub fn pad(str: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        str,
+        " ".repeat(width.saturating_sub(self::width(str)))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_by_grapheme() {
+        let line = "ae\u{301}漢b";
+
+        assert_eq!(next_grapheme(line, 1), 3);
+        assert_eq!(next_grapheme(line, 2), 3);
+        assert_eq!(previous_grapheme(line, 3), 1);
+        assert_eq!(grapheme_start(line, 2), 1);
+        assert_eq!(grapheme_start(line, 5), 5);
+    }
+
+    #[test]
+    fn converts_columns() {
+        let line = "a漢e\u{301}b";
+
+        assert_eq!(width(line), 5);
+        assert_eq!(column(line, 2, 4), 3);
+        assert_eq!(column(line, 4, 4), 4);
+        assert_eq!(column(line, 7, 4), 7);
+        assert_eq!(char_index(line, 2, 4), 1);
+        assert_eq!(char_index(line, 3, 4), 2);
+        assert_eq!(char_index(line, 7, 4), 7);
+    }
+
+    #[test]
+    fn truncates_to_a_width() {
+        assert_eq!(truncate("a漢字b", 4), "a漢");
+        assert_eq!(truncate("a漢字b", 3), "a漢");
+        assert_eq!(truncate("ae\u{301}b", 2), "ae\u{301}");
+        assert_eq!(truncate("abc", 5), "abc");
+        assert_eq!(truncate("漢", 1), "");
+    }
+
+    #[test]
+    fn wraps_at_word_boundaries() {
+        let layout = |width| Layout {
+            tab_width: 4,
+            wrap_width: Some(width),
+        };
+
+        assert_eq!(rows("abc def ghi", layout(8)), [0, 8]);
+        assert_eq!(rows("abc def ghi", layout(5)), [0, 4, 8]);
+        assert_eq!(rows("abcdefghij", layout(4)), [0, 4, 8]);
+        assert_eq!(rows("a漢字", layout(2)), [0, 1, 2, 3]);
+        assert_eq!(rows("abc", Layout::default()), [0]);
+        assert_eq!(rows("\tab\tcd ef", layout(6)), [0, 1, 4]);
+        assert_eq!(rows("abc ", layout(4)), [0, 4]);
+        assert_eq!(row_of(&[0, 4, 8], 5), 1);
+    }
+
+    #[test]
+    fn tabs_go_to_the_next_stop() {
+        let line = "\tab\tc";
+
+        assert_eq!(column(line, 1, 4), 4);
+        assert_eq!(column(line, 4, 4), 8);
+        assert_eq!(column(line, 4, 8), 16);
+        assert_eq!(char_index(line, 2, 4), 0);
+        assert_eq!(char_index(line, 7, 4), 3);
+    }
+}
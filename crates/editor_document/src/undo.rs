@@ -0,0 +1,76 @@
+use crate::selection::InternalSelection;
+
+/// State of a document before a change
+#[derive(Clone)]
+pub struct Snapshot {
+    pub lines: Vec<String>,
+    pub selection: InternalSelection,
+    /// Revision at which the lines were last changed, to know if they are the saved ones
+    pub revision: usize,
+}
+
+/// Undo and redo stacks of a document
+///
+/// Changes made between [`UndoHistory::begin_transaction`] and [`UndoHistory::end_transaction`]
+/// are reverted as a single one
+pub struct UndoHistory {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    transaction_depth: usize,
+    /// Whether a snapshot was already saved for the current transaction
+    transaction_saved: bool,
+}
+
+impl UndoHistory {
+    pub fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            transaction_depth: 0,
+            transaction_saved: false,
+        }
+    }
+
+    /// Whether [`UndoHistory::save`] takes a snapshot, the current transaction not having one
+    pub fn saves_next(&self) -> bool {
+        self.transaction_depth == 0 || !self.transaction_saved
+    }
+
+    /// Save the state before a change, unless the current transaction already did
+    pub fn save(&mut self, snapshot: impl FnOnce() -> Snapshot) {
+        if !self.saves_next() {
+            return;
+        }
+
+        self.undo.push(snapshot());
+        self.redo.clear();
+        self.transaction_saved = self.transaction_depth > 0;
+    }
+
+    pub fn begin_transaction(&mut self) {
+        if self.transaction_depth == 0 {
+            self.transaction_saved = false;
+        }
+        self.transaction_depth += 1;
+    }
+
+    pub fn end_transaction(&mut self) {
+        self.transaction_depth = self.transaction_depth.saturating_sub(1);
+    }
+
+    /// Get the state before the last change, `current` becoming redoable
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.undo.pop()?;
+        self.redo.push(current);
+        self.transaction_saved = false;
+        Some(snapshot)
+    }
+
+    /// Get the state before the last undo, `current` becoming undoable
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.redo.pop()?;
+        self.undo.push(current);
+        self.transaction_saved = false;
+        Some(snapshot)
+    }
+}
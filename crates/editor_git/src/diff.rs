@@ -0,0 +1,281 @@
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use similar::{Algorithm, DiffTag};
+
+use crate::TrackedFile;
+
+/// Longest time spent looking for the smallest diff of lines or chars, a larger but correct diff
+/// is given past it so that large changes don't hang the editor
+const DIFF_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How a hunk changes the lines of the base version
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// Consecutive lines differing from the base version
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub change: Change,
+    /// Lines of the document, empty for removed lines which were before `lines.start`
+    pub lines: Range<usize>,
+    /// Lines of the base version replaced by the hunk
+    pub base_lines: Range<usize>,
+}
+
+impl Hunk {
+    /// Line showing the hunk, the one above removed lines
+    pub fn marker_line(&self) -> usize {
+        match self.change {
+            Change::Removed => self.lines.start.saturating_sub(1),
+            _ => self.lines.start,
+        }
+    }
+}
+
+/// Changes of a file compared to its version in the git index
+pub struct FileDiff {
+    base: Vec<String>,
+    hunks: Vec<Hunk>,
+    /// Index file of the repository and when it was modified as the base was read
+    index: Option<(PathBuf, Option<SystemTime>)>,
+}
+
+impl FileDiff {
+    /// Read the version of the file at `path` in the index of its repository,
+    /// `None` if it isn't tracked in a repository
+    pub fn load(path: &Path) -> Option<Self> {
+        let file = TrackedFile::open(path).ok()?;
+        let index = file.repository().path().join("index");
+        let modified = modified(&index);
+        let text = file.index_text().ok()??;
+
+        // Binary files have no lines to compare, see `git diff`
+        if text.contains('\0') {
+            return None;
+        }
+
+        Some(Self {
+            index: Some((index, modified)),
+            ..Self::from_base(text.lines().map(String::from).collect())
+        })
+    }
+
+    /// Diff with `base` as the version the lines are compared to
+    pub fn from_base(base: Vec<String>) -> Self {
+        Self {
+            base,
+            hunks: Vec::new(),
+            index: None,
+        }
+    }
+
+    /// The index was written since the base was read, by git or by staging a hunk, so the
+    /// diff should be loaded again
+    pub fn index_changed(&self) -> bool {
+        self.index
+            .as_ref()
+            .is_some_and(|(index, read_at)| modified(index) != *read_at)
+    }
+
+    pub fn base(&self) -> &[String] {
+        &self.base
+    }
+
+    /// Compare the current `lines` of the file to the base version
+    pub fn update(&mut self, lines: &[String]) {
+        self.hunks = diff_lines(&self.base, lines);
+    }
+
+    /// Hunks ordered by line, as of the last [`FileDiff::update`]
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    /// Hunk shown on the line `line`, see [`Hunk::marker_line`]
+    pub fn hunk_at(&self, line: usize) -> Option<&Hunk> {
+        self.hunks
+            .iter()
+            .find(|hunk| hunk.lines.contains(&line))
+            .or_else(|| {
+                self.hunks
+                    .iter()
+                    .find(|hunk| hunk.change == Change::Removed && hunk.marker_line() == line)
+            })
+    }
+
+    pub fn change_at(&self, line: usize) -> Option<Change> {
+        self.hunk_at(line).map(|hunk| hunk.change)
+    }
+
+    /// Line of the base version matching the line `line`, the start of the hunk replaced by
+    /// the lines of a hunk
+    pub fn base_line(&self, line: usize) -> usize {
+        let mut base_line = line;
+
+        for hunk in self
+            .hunks
+            .iter()
+            .take_while(|hunk| hunk.lines.start <= line)
+        {
+            if hunk.lines.contains(&line) {
+                return hunk.base_lines.start;
+            }
+            base_line = line + hunk.base_lines.end - hunk.lines.end;
+        }
+
+        base_line
+    }
+}
+
+/// Hunks changing `base` into `lines`
+pub fn diff_lines(base: &[String], lines: &[String]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+
+    let deadline = Instant::now() + DIFF_TIMEOUT;
+
+    for op in similar::capture_diff_slices_deadline(Algorithm::Myers, base, lines, Some(deadline)) {
+        if op.tag() == DiffTag::Equal {
+            continue;
+        }
+
+        let (base_lines, lines) = (op.old_range(), op.new_range());
+
+        // A deletion followed by an insertion is a modification
+        match hunks.last_mut() {
+            Some(last)
+                if last.base_lines.end == base_lines.start && last.lines.end == lines.start =>
+            {
+                last.base_lines.end = base_lines.end;
+                last.lines.end = lines.end;
+            }
+            _ => hunks.push(Hunk {
+                change: Change::Added,
+                lines,
+                base_lines,
+            }),
+        }
+    }
+
+    for hunk in &mut hunks {
+        hunk.change = match (hunk.base_lines.is_empty(), hunk.lines.is_empty()) {
+            (true, _) => Change::Added,
+            (_, true) => Change::Removed,
+            _ => Change::Modified,
+        };
+    }
+
+    hunks
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Ranges of char indices of `old` and of `new` differing from the other one
+pub fn diff_chars(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let old = old.chars().collect::<Vec<_>>();
+    let new = new.chars().collect::<Vec<_>>();
+    let (mut old_ranges, mut new_ranges) = (Vec::new(), Vec::new());
+
+    let deadline = Instant::now() + DIFF_TIMEOUT;
+
+    for op in similar::capture_diff_slices_deadline(Algorithm::Myers, &old, &new, Some(deadline)) {
+        if op.tag() == DiffTag::Equal {
+            continue;
+        }
+
+        if !op.old_range().is_empty() {
+            old_ranges.push(op.old_range());
+        }
+        if !op.new_range().is_empty() {
+            new_ranges.push(op.new_range());
+        }
+    }
+
+    (old_ranges, new_ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn groups_changes_in_hunks() {
+        let base = lines("a\nb\nc\nd\ne");
+        let hunks = diff_lines(&base, &lines("a\nB\nc\nnew\nd"));
+
+        assert_eq!(
+            hunks,
+            [
+                Hunk {
+                    change: Change::Modified,
+                    lines: 1..2,
+                    base_lines: 1..2
+                },
+                Hunk {
+                    change: Change::Added,
+                    lines: 3..4,
+                    base_lines: 3..3
+                },
+                Hunk {
+                    change: Change::Removed,
+                    lines: 5..5,
+                    base_lines: 4..5
+                },
+            ]
+        );
+        assert_eq!(hunks[2].marker_line(), 4);
+
+        let mut diff = FileDiff::from_base(base);
+        diff.update(&lines("a\nB\nc\nnew\nd"));
+        assert_eq!(diff.base_line(2), 2);
+        assert_eq!(diff.base_line(3), 3);
+        assert_eq!(diff.base_line(4), 3);
+    }
+
+    #[test]
+    fn finds_changed_chars() {
+        assert_eq!(
+            diff_chars("let a = 1;", "let b = 23;"),
+            (vec![4..5, 8..9], vec![4..5, 8..10])
+        );
+    }
+
+    #[test]
+    fn bounds_the_time_spent_on_long_lines() {
+        // Unrelated pseudo-random lines, the slowest to diff
+        let line = |seed: u64| {
+            let mut state = seed;
+            (0..20_000)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    char::from(b'a' + (state >> 60) as u8)
+                })
+                .collect::<String>()
+        };
+        let (old, new) = (line(1), line(2));
+
+        let start = Instant::now();
+        let (old_ranges, new_ranges) = diff_chars(&old, &new);
+
+        assert!(start.elapsed() < DIFF_TIMEOUT * 10);
+        assert!(!old_ranges.is_empty() && !new_ranges.is_empty());
+    }
+}
@@ -0,0 +1,454 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use git2::{Commit, IndexTime, ObjectType, Oid, Repository, Sort};
+
+use crate::{diff::diff_lines, GitError, Hunk};
+
+/// Commit of the history of a file, see [`TrackedFile::log`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitInfo {
+    pub id: String,
+    pub short_id: String,
+    pub author: String,
+    /// First line of the message
+    pub summary: String,
+}
+
+/// Last commit changing a line, see [`TrackedFile::blame`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineBlame {
+    pub id: String,
+    pub short_id: String,
+    pub author: String,
+}
+
+/// File of a git repository, read and written in the index and `HEAD` of the repository
+pub struct TrackedFile {
+    repository: Repository,
+    path: PathBuf,
+    /// Path relative to the work directory of the repository
+    relative: PathBuf,
+}
+
+impl TrackedFile {
+    /// Find the repository containing the file at `path`, which may not exist yet
+    pub fn open(path: &Path) -> Result<Self, GitError> {
+        let not_in_repository = || GitError::NotInRepository(path.to_path_buf());
+
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let parent = parent.canonicalize().map_err(|_| not_in_repository())?;
+        let absolute = parent.join(path.file_name().ok_or_else(not_in_repository)?);
+
+        let repository = Repository::discover(&parent).map_err(|_| not_in_repository())?;
+        let relative = absolute
+            .strip_prefix(repository.workdir().ok_or_else(not_in_repository)?)
+            .map_err(|_| not_in_repository())?
+            .to_path_buf();
+
+        Ok(Self {
+            repository,
+            path: path.to_path_buf(),
+            relative,
+        })
+    }
+
+    pub fn repository(&self) -> &Repository {
+        &self.repository
+    }
+
+    /// Path relative to the work directory of the repository
+    pub fn relative(&self) -> &Path {
+        &self.relative
+    }
+
+    /// Content of the file in the index, `None` if it isn't in it
+    pub fn index_text(&self) -> Result<Option<String>, GitError> {
+        let index = self.repository.index()?;
+
+        match index.get_path(&self.relative, 0) {
+            Some(entry) => self.blob_text(entry.id).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Content of the file in the `HEAD` commit, `None` if it isn't in it or there is no commit
+    pub fn head_text(&self) -> Result<Option<String>, GitError> {
+        let Ok(head) = self.repository.head() else {
+            return Ok(None);
+        };
+
+        match head.peel_to_tree()?.get_path(&self.relative) {
+            Ok(entry) => self.blob_text(entry.id()).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Add the changes of `hunk`, from a diff of `lines` against `base`, to the index
+    ///
+    /// Fails with [`GitError::IndexChanged`] if the index no longer contains `base`
+    pub fn stage_hunk(
+        &self,
+        base: &[String],
+        hunk: &Hunk,
+        lines: &[String],
+    ) -> Result<(), GitError> {
+        let index = self.index_text()?.ok_or_else(|| self.untracked())?;
+        if !index.lines().eq(base.iter().map(String::as_str)) {
+            return Err(GitError::IndexChanged(self.path.clone()));
+        }
+
+        let lines = lines.get(hunk.lines.clone()).unwrap_or_default();
+        self.write_index(&replace_lines(&index, hunk.base_lines.clone(), lines))
+    }
+
+    /// Remove from the index the changes of the hunk containing its line `line`,
+    /// returns whether the line was in a hunk
+    pub fn unstage_hunk(&self, line: usize) -> Result<bool, GitError> {
+        let index = self.index_text()?.ok_or_else(|| self.untracked())?;
+        let head = self.head_text()?.unwrap_or_default();
+
+        let head_lines = head.lines().map(String::from).collect::<Vec<_>>();
+        let index_lines = index.lines().map(String::from).collect::<Vec<_>>();
+
+        let hunk = diff_lines(&head_lines, &index_lines)
+            .into_iter()
+            .find(|hunk| hunk.lines.contains(&line) || hunk.marker_line() == line);
+        let Some(hunk) = hunk else {
+            return Ok(false);
+        };
+
+        let head_lines = &head_lines[hunk.base_lines.clone()];
+        self.write_index(&replace_lines(&index, hunk.lines, head_lines))?;
+
+        Ok(true)
+    }
+
+    /// Add the file as it is in the work directory to the index, removing it if it was deleted
+    pub fn stage(&self) -> Result<(), GitError> {
+        let mut index = self.repository.index()?;
+
+        match self
+            .repository
+            .workdir()
+            .map(|workdir| workdir.join(&self.relative))
+        {
+            Some(path) if path.exists() => index.add_path(&self.relative)?,
+            _ => index.remove_path(&self.relative)?,
+        }
+        index.write()?;
+
+        Ok(())
+    }
+
+    /// Reset the file in the index to its version in `HEAD`, removing it if it isn't in it
+    pub fn unstage(&self) -> Result<(), GitError> {
+        match self.repository.head() {
+            Ok(head) => {
+                let head = head.peel(ObjectType::Commit)?;
+                self.repository
+                    .reset_default(Some(&head), [&self.relative])?;
+            }
+            Err(_) => {
+                let mut index = self.repository.index()?;
+                index.remove_path(&self.relative)?;
+                index.write()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Commits reachable from `HEAD` changing the file, most recent first
+    ///
+    /// Like `git log <path>`, merges are only listed when the file differs from all their parents
+    pub fn log(&self) -> Result<Vec<CommitInfo>, GitError> {
+        let mut walk = self.repository.revwalk()?;
+        if walk.push_head().is_err() {
+            return Ok(Vec::new());
+        }
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+
+        let mut commits = Vec::new();
+        for id in walk {
+            let commit = self.repository.find_commit(id?)?;
+            let blob = self.blob_at(&commit);
+
+            let changed = match commit.parent_count() {
+                0 => blob.is_some(),
+                _ => commit.parents().all(|parent| self.blob_at(&parent) != blob),
+            };
+            if !changed {
+                continue;
+            }
+
+            let short_id = commit.as_object().short_id()?;
+            commits.push(CommitInfo {
+                id: commit.id().to_string(),
+                short_id: short_id.as_str().unwrap_or_default().to_string(),
+                author: commit.author().name().unwrap_or_default().to_string(),
+                summary: commit.summary().unwrap_or_default().to_string(),
+            });
+        }
+
+        Ok(commits)
+    }
+
+    /// Last commit changing each of `lines`, the current content of the file, `None` for the
+    /// lines changed since `HEAD`
+    pub fn blame(&self, lines: &[String]) -> Result<Vec<Option<LineBlame>>, GitError> {
+        let blame = self.repository.blame_file(&self.relative, None)?;
+        let buffer = lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect::<String>();
+        let blame = blame.blame_buffer(buffer.as_bytes())?;
+
+        let mut result = vec![None; lines.len()];
+        let mut commits: HashMap<Oid, LineBlame> = HashMap::new();
+        for hunk in blame.iter() {
+            let id = hunk.final_commit_id();
+            if id.is_zero() {
+                continue;
+            }
+
+            // The signatures of the hunks blamed from a buffer aren't set, unlike their commit
+            let line = match commits.get(&id) {
+                Some(line) => line.clone(),
+                None => {
+                    let commit = self.repository.find_commit(id)?;
+                    let short_id = commit.as_object().short_id()?;
+                    let line = LineBlame {
+                        id: id.to_string(),
+                        short_id: short_id.as_str().unwrap_or_default().to_string(),
+                        author: commit.author().name().unwrap_or_default().to_string(),
+                    };
+                    commits.insert(id, line.clone());
+                    line
+                }
+            };
+
+            // Lines are numbered from 1
+            let start = hunk.final_start_line().saturating_sub(1);
+            let end = (start + hunk.lines_in_hunk()).min(result.len());
+            for blame in result.get_mut(start..end).unwrap_or_default() {
+                *blame = Some(line.clone());
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Content of the file in the commit `id`, `None` if it isn't in it
+    pub fn text_at(&self, id: &str) -> Result<Option<String>, GitError> {
+        let commit = self.repository.find_commit(Oid::from_str(id)?)?;
+
+        match self.blob_at(&commit) {
+            Some(blob) => self.blob_text(blob).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn blob_at(&self, commit: &Commit) -> Option<Oid> {
+        let entry = commit.tree().ok()?.get_path(&self.relative).ok()?;
+        Some(entry.id())
+    }
+
+    /// Replace the content of the file in the index by `text`
+    ///
+    /// The stat data of the entry is cleared like `git update-index --cacheinfo` does, the one
+    /// of the file would make git take the work directory file for the new content
+    fn write_index(&self, text: &str) -> Result<(), GitError> {
+        let mut index = self.repository.index()?;
+        let mut entry = index
+            .get_path(&self.relative, 0)
+            .ok_or_else(|| self.untracked())?;
+
+        entry.id = self.repository.blob(text.as_bytes())?;
+        entry.ctime = IndexTime::new(0, 0);
+        entry.mtime = IndexTime::new(0, 0);
+        entry.dev = 0;
+        entry.ino = 0;
+        entry.uid = 0;
+        entry.gid = 0;
+        entry.file_size = 0;
+        index.add(&entry)?;
+        index.write()?;
+
+        Ok(())
+    }
+
+    fn blob_text(&self, id: Oid) -> Result<String, GitError> {
+        let blob = self.repository.find_blob(id)?;
+
+        Ok(String::from_utf8_lossy(blob.content()).into_owned())
+    }
+
+    fn untracked(&self) -> GitError {
+        GitError::Untracked(self.path.clone())
+    }
+}
+
+/// `text` with its lines in `range` replaced by `lines`,
+/// keeping its line endings and whether it ends with one
+fn replace_lines(text: &str, range: Range<usize>, lines: &[String]) -> String {
+    let ending = match text.contains("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    };
+
+    let mut result = text.lines().collect::<Vec<_>>();
+    result.splice(range, lines.iter().map(String::as_str));
+
+    let mut result = result.join(ending);
+    if !result.is_empty() && (text.is_empty() || text.ends_with('\n')) {
+        result.push_str(ending);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use git2::Signature;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::FileDiff;
+
+    /// Repository with a commit adding `file.txt` containing `text`
+    fn repository(text: &str) -> (TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, text).unwrap();
+
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("file.txt")).unwrap();
+        index.write().unwrap();
+
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        repository
+            .commit(Some("HEAD"), &signature, &signature, "add", &tree, &[])
+            .unwrap();
+
+        (dir, path)
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn stages_and_unstages_a_single_hunk() {
+        let (_dir, path) = repository("a\nb\nc\n");
+        let file = TrackedFile::open(&path).unwrap();
+        let current = lines("a\nB\nc\nd");
+
+        let mut diff = FileDiff::load(&path).unwrap();
+        diff.update(&current);
+        assert_eq!(diff.hunks().len(), 2);
+
+        file.stage_hunk(diff.base(), &diff.hunks()[0], &current)
+            .unwrap();
+        assert_eq!(file.index_text().unwrap().unwrap(), "a\nB\nc\n");
+
+        let mut diff = FileDiff::load(&path).unwrap();
+        diff.update(&current);
+        assert_eq!(diff.hunks().len(), 1);
+
+        assert!(!file.unstage_hunk(0).unwrap());
+        assert!(file.unstage_hunk(1).unwrap());
+        assert_eq!(file.index_text().unwrap().unwrap(), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn staged_hunks_dont_hide_the_file_changes() {
+        // The lines aren't written, the file still has the committed version
+        let (dir, path) = repository("a\nb\nc\nd\n");
+        let file = TrackedFile::open(&path).unwrap();
+        let current = lines("a\nB\nc\nD");
+
+        let mut diff = FileDiff::load(&path).unwrap();
+        diff.update(&current);
+        file.stage_hunk(diff.base(), &diff.hunks()[0], &current)
+            .unwrap();
+
+        let repository = Repository::open(dir.path()).unwrap();
+        let status = repository.status_file(Path::new("file.txt")).unwrap();
+        assert!(status.contains(git2::Status::INDEX_MODIFIED));
+        assert!(status.contains(git2::Status::WT_MODIFIED));
+
+        let unstaged = repository.diff_index_to_workdir(None, None).unwrap();
+        assert_eq!(unstaged.stats().unwrap().insertions(), 1);
+        assert_eq!(unstaged.stats().unwrap().deletions(), 1);
+    }
+
+    #[test]
+    fn refuses_to_stage_against_a_changed_index() {
+        let (dir, path) = repository("a\nb\nc\n");
+        let file = TrackedFile::open(&path).unwrap();
+        let current = lines("a\nb\nc\nd");
+
+        let mut diff = FileDiff::load(&path).unwrap();
+        diff.update(&current);
+
+        // Another program stages a shorter version of the file
+        fs::write(&path, "a\n").unwrap();
+        let repository = Repository::open(dir.path()).unwrap();
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("file.txt")).unwrap();
+        index.write().unwrap();
+
+        assert!(matches!(
+            file.stage_hunk(diff.base(), &diff.hunks()[0], &current),
+            Err(GitError::IndexChanged(_))
+        ));
+        assert_eq!(file.index_text().unwrap().unwrap(), "a\n");
+    }
+
+    #[test]
+    fn notices_index_changes() {
+        let (dir, path) = repository("a\nb\n");
+        let diff = FileDiff::load(&path).unwrap();
+        assert!(!diff.index_changed());
+
+        fs::write(&path, "a\nB\n").unwrap();
+        let repository = Repository::open(dir.path()).unwrap();
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new("file.txt")).unwrap();
+        index.write().unwrap();
+
+        assert!(diff.index_changed());
+        assert_eq!(FileDiff::load(&path).unwrap().base(), lines("a\nB"));
+    }
+
+    #[test]
+    fn blames_the_committed_lines() {
+        let (_dir, path) = repository("a\nb\n");
+        let file = TrackedFile::open(&path).unwrap();
+
+        let blame = file.blame(&lines("a\nnew\nb")).unwrap();
+        assert_eq!(blame[0].as_ref().unwrap().author, "test");
+        assert_eq!(blame[1], None);
+        assert_eq!(blame[2], blame[0]);
+    }
+
+    #[test]
+    fn keeps_line_endings() {
+        assert_eq!(
+            replace_lines("a\r\nb\r\n", 1..2, &lines("B\nC")),
+            "a\r\nB\r\nC\r\n"
+        );
+        assert_eq!(replace_lines("a\nb", 0..1, &[]), "b");
+    }
+}
@@ -0,0 +1,84 @@
+//! Git integration, reading and writing the repositories containing the documents with libgit2
+
+mod diff;
+mod file;
+mod repository;
+
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    path::PathBuf,
+};
+
+use git2::RepositoryState;
+
+pub use diff::{diff_chars, diff_lines, Change, FileDiff, Hunk};
+pub use file::{CommitInfo, LineBlame, TrackedFile};
+pub use repository::{FileChange, FileStatus, GitRepository};
+
+/// Reason a git operation failed
+#[derive(Debug)]
+pub enum GitError {
+    NotInRepository(PathBuf),
+    /// The file isn't in the index or in `HEAD`
+    Untracked(PathBuf),
+    /// The version of the file in the index isn't the one the changes were computed against
+    IndexChanged(PathBuf),
+    /// The index has no changes since `HEAD`
+    NothingToCommit,
+    /// The index still has conflicts of a merge
+    Conflicts,
+    /// An operation like a rebase is in progress, see [`git2::RepositoryState`]
+    InProgress(RepositoryState),
+    /// The commit message has no lines other than comments
+    EmptyMessage,
+    Git(git2::Error),
+    Io(io::Error),
+}
+
+impl Display for GitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotInRepository(path) => {
+                write!(f, "{} is not in a git repository", path.display())
+            }
+            Self::Untracked(path) => write!(f, "{} is not tracked", path.display()),
+            Self::IndexChanged(path) => write!(
+                f,
+                "{} changed in the index since the diff was computed, try again",
+                path.display()
+            ),
+            Self::NothingToCommit => write!(f, "nothing to commit, stage changes first"),
+            Self::Conflicts => write!(f, "resolve and stage the conflicts first"),
+            Self::InProgress(state) => {
+                let operation = match state {
+                    RepositoryState::Revert | RepositoryState::RevertSequence => "a revert",
+                    RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                        "a cherry-pick"
+                    }
+                    RepositoryState::Bisect => "a bisect",
+                    RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => {
+                        "git am"
+                    }
+                    _ => "a rebase",
+                };
+                write!(f, "{} is in progress, finish it with git first", operation)
+            }
+            Self::EmptyMessage => write!(f, "empty commit message"),
+            Self::Git(err) => write!(f, "{}", err.message()),
+            Self::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<git2::Error> for GitError {
+    fn from(err: git2::Error) -> Self {
+        Self::Git(err)
+    }
+}
+
+impl From<io::Error> for GitError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
@@ -0,0 +1,440 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use git2::{DiffFormat, Oid, Repository, RepositoryState, Status, StatusOptions};
+
+use crate::GitError;
+
+/// How a file differs between `HEAD`, the index and the work directory, see `git status --short`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileChange {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    TypeChanged,
+    /// Not in the index, only in the unstaged changes
+    Untracked,
+    /// Left with conflicts by a merge, in both the staged and unstaged changes
+    Conflicted,
+}
+
+impl FileChange {
+    /// Letter of the change in `git status --short`
+    pub fn code(&self) -> char {
+        match self {
+            Self::Added => 'A',
+            Self::Modified => 'M',
+            Self::Deleted => 'D',
+            Self::Renamed => 'R',
+            Self::TypeChanged => 'T',
+            Self::Untracked => '?',
+            Self::Conflicted => 'U',
+        }
+    }
+}
+
+/// Changes of a file of the work directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileStatus {
+    /// Path relative to the work directory
+    pub path: PathBuf,
+    /// Change of the index compared to `HEAD`
+    pub staged: Option<FileChange>,
+    /// Change of the work directory compared to the index
+    pub unstaged: Option<FileChange>,
+}
+
+impl FileStatus {
+    /// Two letter code of the staged and unstaged changes, like `git status --short`
+    pub fn code(&self) -> String {
+        let code = |change: Option<FileChange>| change.map_or(' ', |change| change.code());
+        format!("{}{}", code(self.staged), code(self.unstaged))
+    }
+}
+
+/// Repository with a work directory, for the git actions not about a single file
+pub struct GitRepository {
+    repository: Repository,
+}
+
+impl GitRepository {
+    /// Find the repository containing the directory `path`
+    pub fn discover(path: &Path) -> Result<Self, GitError> {
+        let repository = Repository::discover(path)
+            .ok()
+            .filter(|repository| repository.workdir().is_some())
+            .ok_or_else(|| GitError::NotInRepository(path.to_path_buf()))?;
+
+        Ok(Self { repository })
+    }
+
+    /// Create an empty repository in the directory `path`
+    pub fn init(path: &Path) -> Result<Self, GitError> {
+        Ok(Self {
+            repository: Repository::init(path)?,
+        })
+    }
+
+    pub fn workdir(&self) -> &Path {
+        self.repository
+            .workdir()
+            .expect("repositories are opened with a work directory")
+    }
+
+    /// Files with staged or unstaged changes ordered by path, ignored files excluded
+    pub fn status(&self) -> Result<Vec<FileStatus>, GitError> {
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .sort_case_sensitively(true);
+
+        let statuses = self.repository.statuses(Some(&mut options))?;
+
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| {
+                let status = entry.status();
+                let path = PathBuf::from(entry.path()?);
+
+                if status.is_conflicted() {
+                    return Some(FileStatus {
+                        path,
+                        staged: Some(FileChange::Conflicted),
+                        unstaged: Some(FileChange::Conflicted),
+                    });
+                }
+                if status.is_wt_new() {
+                    return Some(FileStatus {
+                        path,
+                        staged: None,
+                        unstaged: Some(FileChange::Untracked),
+                    });
+                }
+
+                let staged = change(
+                    status,
+                    [
+                        Status::INDEX_NEW,
+                        Status::INDEX_MODIFIED,
+                        Status::INDEX_DELETED,
+                        Status::INDEX_RENAMED,
+                        Status::INDEX_TYPECHANGE,
+                    ],
+                );
+                let unstaged = change(
+                    status,
+                    [
+                        Status::WT_NEW,
+                        Status::WT_MODIFIED,
+                        Status::WT_DELETED,
+                        Status::WT_RENAMED,
+                        Status::WT_TYPECHANGE,
+                    ],
+                );
+
+                (staged.is_some() || unstaged.is_some()).then_some(FileStatus {
+                    path,
+                    staged,
+                    unstaged,
+                })
+            })
+            .collect())
+    }
+
+    /// Write the template of the commit message, comments listing the staged changes, where
+    /// `git commit` writes it, returns the path of the file
+    pub fn prepare_message(&self) -> Result<PathBuf, GitError> {
+        let mut template = String::from(
+            "\n\
+             # Write the commit message, lines starting with '#' are ignored.\n\
+             # Write and close the buffer to commit, an empty message aborts the commit.\n\
+             #\n\
+             # Changes to be committed:\n",
+        );
+
+        for file in self.status()? {
+            if let Some(change) = file.staged {
+                template.push_str(&format!("#\t{} {}\n", change.code(), file.path.display()));
+            }
+        }
+
+        let path = self.repository.path().join("COMMIT_EDITMSG");
+        fs::write(&path, template)?;
+
+        Ok(path)
+    }
+
+    /// Commit the index with `message` stripped of its comments, returns the short id of the
+    /// commit
+    ///
+    /// During a merge the merged commits are parents too and the merge is concluded, like
+    /// `git commit`. Other operations in progress, like a rebase, must be finished with git.
+    pub fn commit(&self, message: &str) -> Result<String, GitError> {
+        let merging = match self.repository.state() {
+            RepositoryState::Clean => false,
+            RepositoryState::Merge => true,
+            state => return Err(GitError::InProgress(state)),
+        };
+
+        let message = git2::message_prettify(message, Some(b'#'))?;
+        if message.is_empty() {
+            return Err(GitError::EmptyMessage);
+        }
+
+        let mut index = self.repository.index()?;
+        // Another handle on the repository may have changed the index since it was loaded
+        index.read(false)?;
+        if index.has_conflicts() {
+            return Err(GitError::Conflicts);
+        }
+        let tree = self.repository.find_tree(index.write_tree()?)?;
+        let mut parents = match self.repository.head() {
+            Ok(head) => vec![head.peel_to_commit()?],
+            Err(_) => Vec::new(),
+        };
+
+        // A merge is committed even if it keeps the tree of `HEAD`
+        let unchanged = match parents.first() {
+            _ if merging => false,
+            Some(parent) => parent.tree_id() == tree.id(),
+            None => index.is_empty(),
+        };
+        if unchanged {
+            return Err(GitError::NothingToCommit);
+        }
+
+        if merging {
+            // `MERGE_HEAD` lists the merged commits, one id per line
+            let merged = fs::read_to_string(self.repository.path().join("MERGE_HEAD"))?;
+            for id in merged.lines().filter(|id| !id.is_empty()) {
+                parents.push(self.repository.find_commit(Oid::from_str(id)?)?);
+            }
+        }
+
+        let signature = self.repository.signature()?;
+        let id = self.repository.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &parents.iter().collect::<Vec<_>>(),
+        )?;
+
+        if merging {
+            // Remove `MERGE_HEAD` and `MERGE_MSG`
+            self.repository.cleanup_state()?;
+        }
+
+        let short_id = self.repository.find_object(id, None)?.short_id()?;
+        Ok(short_id.as_str().unwrap_or_default().to_string())
+    }
+
+    /// Lines of the commit `id` like `git show`, its message followed by its patch
+    pub fn show_commit(&self, id: &str) -> Result<Vec<String>, GitError> {
+        let commit = self.repository.find_commit(Oid::from_str(id)?)?;
+        let author = commit.author();
+
+        let mut lines = vec![
+            format!("commit {}", commit.id()),
+            format!(
+                "Author: {} <{}>",
+                author.name().unwrap_or_default(),
+                author.email().unwrap_or_default()
+            ),
+            String::new(),
+        ];
+        lines.extend(
+            commit
+                .message()
+                .unwrap_or_default()
+                .lines()
+                .map(|line| format!("    {}", line)),
+        );
+        lines.push(String::new());
+
+        let parent = match commit.parent_count() {
+            0 => None,
+            _ => Some(commit.parent(0)?.tree()?),
+        };
+        let diff =
+            self.repository
+                .diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), None)?;
+
+        diff.print(DiffFormat::Patch, |_, _, line| {
+            let content = String::from_utf8_lossy(line.content());
+            let content = content.trim_end_matches(['\n', '\r']);
+
+            match line.origin() {
+                origin @ ('+' | '-' | ' ') => lines.push(format!("{}{}", origin, content)),
+                _ => lines.extend(content.lines().map(String::from)),
+            }
+            true
+        })?;
+
+        Ok(lines)
+    }
+}
+
+/// The change matching the first of the `flags` set in `status`, in the order of [`FileChange`]
+fn change(status: Status, flags: [Status; 5]) -> Option<FileChange> {
+    let changes = [
+        FileChange::Added,
+        FileChange::Modified,
+        FileChange::Deleted,
+        FileChange::Renamed,
+        FileChange::TypeChanged,
+    ];
+
+    flags
+        .into_iter()
+        .zip(changes)
+        .find(|(flag, _)| status.contains(*flag))
+        .map(|(_, change)| change)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use git2::{build::CheckoutBuilder, MergeOptions};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::TrackedFile;
+
+    fn repository() -> (TempDir, GitRepository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = GitRepository::init(dir.path()).unwrap();
+        let mut config = repository.repository.config().unwrap();
+        config.set_str("user.name", "test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        (dir, repository)
+    }
+
+    /// Write `text` to the file `name` and commit it
+    fn commit_file(repository: &GitRepository, name: &str, text: &str) {
+        let path = repository.repository.workdir().unwrap().join(name);
+        fs::write(&path, text).unwrap();
+        TrackedFile::open(&path).unwrap().stage().unwrap();
+        repository.commit(name).unwrap();
+    }
+
+    /// Start merging into the current branch a branch changing `name` to `text`, after the
+    /// current branch changed `a.txt` to `ours`
+    fn start_merge(repository: &GitRepository, name: &str, text: &str, ours: &str) {
+        let git = &repository.repository;
+        let base = git.head().unwrap().peel_to_commit().unwrap();
+        let main = git.head().unwrap().name().unwrap().to_string();
+
+        git.branch("other", &base, false).unwrap();
+        git.set_head("refs/heads/other").unwrap();
+        commit_file(repository, name, text);
+        git.set_head(&main).unwrap();
+        git.checkout_head(Some(CheckoutBuilder::new().force()))
+            .unwrap();
+        commit_file(repository, "a.txt", ours);
+
+        let other = git
+            .find_annotated_commit(git.refname_to_id("refs/heads/other").unwrap())
+            .unwrap();
+        git.merge(&[&other], Some(&mut MergeOptions::new()), None)
+            .unwrap();
+        assert_eq!(git.state(), RepositoryState::Merge);
+    }
+
+    #[test]
+    fn commits_merges() {
+        let (_dir, repository) = repository();
+        commit_file(&repository, "a.txt", "a\n");
+        start_merge(&repository, "b.txt", "b\n", "A\n");
+
+        repository.commit("merge").unwrap();
+
+        let git = &repository.repository;
+        let head = git.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.parent_count(), 2);
+        assert_eq!(head.parent(1).unwrap().summary(), Some("b.txt"));
+        assert_eq!(git.state(), RepositoryState::Clean);
+        assert!(repository.status().unwrap().is_empty());
+    }
+
+    #[test]
+    fn refuses_unfinished_operations() {
+        let (dir, repository) = repository();
+        commit_file(&repository, "a.txt", "a\n");
+        start_merge(&repository, "a.txt", "theirs\n", "ours\n");
+
+        assert!(matches!(
+            repository.commit("merge"),
+            Err(GitError::Conflicts)
+        ));
+
+        let git = &repository.repository;
+        git.cleanup_state().unwrap();
+        let head = git.head().unwrap().target().unwrap();
+        fs::write(dir.path().join(".git/CHERRY_PICK_HEAD"), head.to_string()).unwrap();
+        assert!(matches!(
+            repository.commit("pick"),
+            Err(GitError::InProgress(RepositoryState::CherryPick))
+        ));
+    }
+
+    #[test]
+    fn stages_and_commits_files() {
+        let (dir, repository) = repository();
+
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "a\n").unwrap();
+
+        let status = repository.status().unwrap();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].path, Path::new("file.txt"));
+        assert_eq!(status[0].code(), " ?");
+        assert!(matches!(
+            repository.commit("add"),
+            Err(GitError::NothingToCommit)
+        ));
+
+        let file = TrackedFile::open(&path).unwrap();
+        file.stage().unwrap();
+        assert_eq!(repository.status().unwrap()[0].code(), "A ");
+        let message = repository.prepare_message().unwrap();
+        assert!(fs::read_to_string(message)
+            .unwrap()
+            .contains("#\tA file.txt"));
+
+        assert!(matches!(
+            repository.commit("# only a comment\n"),
+            Err(GitError::EmptyMessage)
+        ));
+        repository.commit("add file\n# comment").unwrap();
+        assert!(repository.status().unwrap().is_empty());
+
+        fs::write(&path, "b\n").unwrap();
+        file.stage().unwrap();
+        file.unstage().unwrap();
+        assert_eq!(repository.status().unwrap()[0].code(), " M");
+
+        file.stage().unwrap();
+        repository.commit("change file").unwrap();
+
+        let log = file.log().unwrap();
+        assert_eq!(
+            log.iter()
+                .map(|commit| commit.summary.as_str())
+                .collect::<Vec<_>>(),
+            ["change file", "add file"]
+        );
+        assert_eq!(file.text_at(&log[1].id).unwrap().unwrap(), "a\n");
+
+        let show = repository.show_commit(&log[0].id).unwrap();
+        assert_eq!(show[3], "    change file");
+        assert_eq!(show[show.len() - 2..], ["-a", "+b"]);
+    }
+}
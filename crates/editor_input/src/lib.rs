@@ -1,3 +1,5 @@
+use std::fmt::{self, Display, Formatter};
+
 use editor_action::{Action, DocumentAction, SingleLineDocumentAction};
 use editor_mode::{Focused, Mode};
 use editor_terminal::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
@@ -12,6 +14,86 @@ impl Input {
     pub fn new(key: KeyCode, modifier: KeyModifiers) -> Self {
         Self { key, modifier }
     }
+
+    /// Key event sent by the terminal for this input
+    pub fn to_key_event(&self) -> KeyEvent {
+        KeyEvent::new(self.key, self.modifier)
+    }
+
+    /// Parse an input written like its [`Display`], `C-s`, `A-enter`, `F1` or `Q`
+    pub fn parse(string: &str) -> Option<Self> {
+        let mut modifier = KeyModifiers::NONE;
+        let mut key = string;
+
+        loop {
+            if let Some(rest) = key.strip_prefix("C-") {
+                modifier |= KeyModifiers::CONTROL;
+                key = rest;
+            } else if let Some(rest) = key.strip_prefix("A-") {
+                modifier |= KeyModifiers::ALT;
+                key = rest;
+            } else if let Some(rest) = key.strip_prefix("S-") {
+                modifier |= KeyModifiers::SHIFT;
+                key = rest;
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = key.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(char), None) => {
+                // Terminals report upper case letters with shift
+                if char.is_uppercase() {
+                    modifier |= KeyModifiers::SHIFT;
+                }
+                KeyCode::Char(char)
+            }
+            _ => match key {
+                "space" => KeyCode::Char(' '),
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                key => KeyCode::F(key.strip_prefix('F')?.parse().ok()?),
+            },
+        };
+
+        Some(Self { key, modifier })
+    }
+}
+
+/// Display like `C-s`, `A-enter` or `S-backtab`
+impl Display for Input {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.modifier.contains(KeyModifiers::CONTROL) {
+            write!(f, "C-")?;
+        }
+        if self.modifier.contains(KeyModifiers::ALT) {
+            write!(f, "A-")?;
+        }
+        if self.modifier.contains(KeyModifiers::SHIFT) && !matches!(self.key, KeyCode::Char(_)) {
+            write!(f, "S-")?;
+        }
+
+        match self.key {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(char) => write!(f, "{}", char),
+            KeyCode::F(number) => write!(f, "F{}", number),
+            key => write!(f, "{}", format!("{:?}", key).to_lowercase()),
+        }
+    }
 }
 
 pub struct Inputs {
@@ -22,6 +104,38 @@ pub struct Inputs {
 }
 
 impl Inputs {
+    /// Every key binding along with the name of the mode it applies to
+    pub fn bindings(&self) -> impl Iterator<Item = (&'static str, &Input, &Action)> {
+        [
+            ("normal", &self.normal),
+            ("insert", &self.insert),
+            ("selection", &self.selection),
+            ("command", &self.text_box),
+        ]
+        .into_iter()
+        .flat_map(|(mode, bindings)| {
+            bindings
+                .iter()
+                .map(move |(input, action)| (mode, input, action))
+        })
+    }
+
+    /// Bind `input` to `action` in the mode named like in [`Inputs::bindings`],
+    /// taking precedence over the existing bindings
+    pub fn bind(&mut self, mode: &str, input: Input, action: Action) -> Result<(), String> {
+        let bindings = match mode {
+            "normal" => &mut self.normal,
+            "insert" => &mut self.insert,
+            "selection" => &mut self.selection,
+            "command" => &mut self.text_box,
+            mode => return Err(format!("unknown mode `{}`", mode)),
+        };
+
+        bindings.insert(0, (input, action));
+
+        Ok(())
+    }
+
     pub fn key_event(&self, key_event: &KeyEvent, focused: Focused, mode: Mode) -> Option<Action> {
         if key_event.kind == KeyEventKind::Release {
             return None;
@@ -103,8 +217,44 @@ impl Default for Inputs {
             (Char('j'), NONE, DocumentAction::MoveDown),
             (Char('i'), NONE, Action::EnterInsertMode),
             (Char('v'), NONE, Action::EnterSelectionMode),
-            (Char('s'), CONTROL, DocumentAction::Write),
+            (Char('s'), CONTROL, Action::Write),
             (Char(':'), NONE, Action::FocusCommandBar),
+            (F(1), NONE, Action::Help { action: None }),
+            (Char('.'), NONE, Action::RepeatLastChange),
+            (Char('u'), NONE, DocumentAction::Undo),
+            (Char('U'), SHIFT, DocumentAction::Redo),
+            (Char('"'), NONE, Action::SelectRegister),
+            (Char('y'), NONE, Action::Yank { register: None }),
+            (Char('p'), NONE, Action::Paste { register: None }),
+            (Char('Q'), SHIFT, Action::RecordMacro { register: None }),
+            (Char('q'), NONE, Action::ReplayMacro { register: None }),
+            (Char('>'), NONE, DocumentAction::Indent),
+            (Char('>'), SHIFT, DocumentAction::Indent),
+            (Char('<'), NONE, DocumentAction::Outdent),
+            (Char('<'), SHIFT, DocumentAction::Outdent),
+            (Char('o'), NONE, DocumentAction::OpenLineBelow),
+            (Char('O'), SHIFT, DocumentAction::OpenLineAbove),
+            (Char('z'), NONE, DocumentAction::ToggleFold),
+            (Char('Z'), SHIFT, DocumentAction::FoldLevel { level: 0 }),
+            (Char(']'), NONE, Action::NextHunk),
+            (Char('['), NONE, Action::PreviousHunk),
+            (Char('}'), NONE, DocumentAction::NextConflict),
+            (Char('}'), SHIFT, DocumentAction::NextConflict),
+            (Char('{'), NONE, DocumentAction::PreviousConflict),
+            (Char('{'), SHIFT, DocumentAction::PreviousConflict),
+            (Char('-'), NONE, Action::ToggleStaged),
+            (Char('w'), CONTROL, Action::DiffSwitch),
+            (Enter, NONE, Action::OpenEntry),
+            (Char('0'), NONE, Action::CountDigit { digit: 0 }),
+            (Char('1'), NONE, Action::CountDigit { digit: 1 }),
+            (Char('2'), NONE, Action::CountDigit { digit: 2 }),
+            (Char('3'), NONE, Action::CountDigit { digit: 3 }),
+            (Char('4'), NONE, Action::CountDigit { digit: 4 }),
+            (Char('5'), NONE, Action::CountDigit { digit: 5 }),
+            (Char('6'), NONE, Action::CountDigit { digit: 6 }),
+            (Char('7'), NONE, Action::CountDigit { digit: 7 }),
+            (Char('8'), NONE, Action::CountDigit { digit: 8 }),
+            (Char('9'), NONE, Action::CountDigit { digit: 9 }),
         );
 
         let insert = keybinds!(
@@ -112,6 +262,7 @@ impl Default for Inputs {
             (Backspace, NONE, SingleLineDocumentAction::DeleteBefore),
             (Char('j'), CONTROL, DocumentAction::InsertLineBeforeCursor),
             (Enter, CONTROL, DocumentAction::InsertLineBeforeCursor),
+            (Tab, NONE, DocumentAction::InsertTab),
             (Esc, NONE, Action::EnterNormalMode),
         );
 
@@ -127,12 +278,36 @@ impl Default for Inputs {
             (Char('i'), NONE, Action::EnterInsertMode),
             (Esc, NONE, Action::EnterNormalMode),
             (Char(':'), NONE, Action::FocusCommandBar),
+            (Char('"'), NONE, Action::SelectRegister),
+            (Char('y'), NONE, Action::Yank { register: None }),
+            (Char('>'), NONE, DocumentAction::Indent),
+            (Char('>'), SHIFT, DocumentAction::Indent),
+            (Char('<'), NONE, DocumentAction::Outdent),
+            (Char('<'), SHIFT, DocumentAction::Outdent),
+            (Char('0'), NONE, Action::CountDigit { digit: 0 }),
+            (Char('1'), NONE, Action::CountDigit { digit: 1 }),
+            (Char('2'), NONE, Action::CountDigit { digit: 2 }),
+            (Char('3'), NONE, Action::CountDigit { digit: 3 }),
+            (Char('4'), NONE, Action::CountDigit { digit: 4 }),
+            (Char('5'), NONE, Action::CountDigit { digit: 5 }),
+            (Char('6'), NONE, Action::CountDigit { digit: 6 }),
+            (Char('7'), NONE, Action::CountDigit { digit: 7 }),
+            (Char('8'), NONE, Action::CountDigit { digit: 8 }),
+            (Char('9'), NONE, Action::CountDigit { digit: 9 }),
         );
 
         let text_box = keybinds!(
             (Char('j'), CONTROL, Action::Validate),
             (Enter, NONE, Action::Validate),
             (Esc, NONE, Action::Cancel),
+            (Tab, NONE, Action::CompleteNext),
+            (BackTab, SHIFT, Action::CompletePrevious),
+            (BackTab, NONE, Action::CompletePrevious),
+            (Up, NONE, Action::HistoryPrevious),
+            (Down, NONE, Action::HistoryNext),
+            (Char('p'), CONTROL, Action::HistoryPrevious),
+            (Char('n'), CONTROL, Action::HistoryNext),
+            (Char('r'), CONTROL, Action::HistorySearch),
             (Left, NONE, SingleLineDocumentAction::MoveLeft),
             (Right, NONE, SingleLineDocumentAction::MoveRight),
             (Char('h'), CONTROL, SingleLineDocumentAction::DeleteBefore),
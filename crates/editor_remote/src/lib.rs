@@ -0,0 +1,417 @@
+//! Remote control of a running editor through a Unix domain socket
+//!
+//! Clients send one JSON-RPC 2.0 request per line and get one response per line:
+//!
+//! ```text
+//! {"jsonrpc": "2.0", "id": 1, "method": "open", "params": {"path": "src/main.rs", "line": 12}}
+//! {"jsonrpc": "2.0", "id": 1, "result": null}
+//! ```
+//!
+//! The methods are handled by the app, see [`Request`]. The socket is in a directory only the
+//! user can access, so that other users can't replace it or send requests
+
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+    fs::{self, DirBuilder},
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use log::{error, info};
+use serde_json::{json, Value};
+
+/// Request received from a client, answered with [`Request::reply`]
+pub struct Request {
+    pub method: String,
+    pub params: Value,
+    reply: Sender<Result<Value, RpcError>>,
+}
+
+impl Request {
+    pub fn reply(self, result: Result<Value, RpcError>) {
+        // The client may have disconnected
+        let _ = self.reply.send(result);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn parse_error(message: impl Into<String>) -> Self {
+        Self {
+            code: -32700,
+            message: message.into(),
+        }
+    }
+
+    /// The request isn't a valid request object, like when its method is missing
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self {
+            code: -32600,
+            message: message.into(),
+        }
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self {
+            code: -32601,
+            message: format!("unknown method `{}`", method),
+        }
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: -32602,
+            message: message.into(),
+        }
+    }
+
+    /// The request was valid but the editor couldn't do it
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self {
+            code: -32000,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for RpcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+/// Called by the client threads after sending a request, to wake up the app
+type Wake = Arc<dyn Fn() + Send + Sync>;
+
+/// Socket listening for clients on a background thread, removed when dropped
+pub struct RemoteServer {
+    path: PathBuf,
+    requests: Receiver<Request>,
+}
+
+impl RemoteServer {
+    /// Listen on `path`, failing if another editor already does
+    ///
+    /// The directory of `path` is created if missing. `wake` is called from another thread
+    /// whenever a request is ready to be taken with [`RemoteServer::try_recv`].
+    pub fn start(path: PathBuf, wake: impl Fn() + Send + Sync + 'static) -> io::Result<Self> {
+        let dir = socket_dir(&path);
+        if !dir.exists() {
+            DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        }
+        check_socket_dir(dir)?;
+
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is used by another editor", path.display()),
+            ));
+        }
+
+        // Left behind by an editor that didn't exit properly
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        let (sender, requests) = mpsc::channel();
+        let wake: Wake = Arc::new(wake);
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        let wake = wake.clone();
+                        thread::spawn(move || handle_client(stream, sender, wake));
+                    }
+                    Err(err) => error!("Failed to accept remote client, {:?}", err),
+                }
+            }
+        });
+
+        info!("Listening for remote requests on {}", path.display());
+
+        Ok(Self { path, requests })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get a pending request without waiting
+    pub fn try_recv(&self) -> Option<Request> {
+        self.requests.try_recv().ok()
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn handle_client(stream: UnixStream, sender: Sender<Request>, wake: Wake) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (id, result) = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => {
+                let id = request.get("id").cloned().unwrap_or(Value::Null);
+                (id, dispatch(request, &sender, &*wake))
+            }
+            Err(err) => (Value::Null, Err(RpcError::parse_error(err.to_string()))),
+        };
+
+        let response = match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": err.code, "message": err.message},
+            }),
+        };
+
+        if writeln!(writer, "{}", response).is_err() {
+            return;
+        }
+    }
+}
+
+/// Send `request` to the app and wait for its result
+fn dispatch(
+    request: Value,
+    sender: &Sender<Request>,
+    wake: &(dyn Fn() + Send + Sync),
+) -> Result<Value, RpcError> {
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return Err(RpcError::invalid_request("missing method"));
+    };
+
+    let (reply, result) = mpsc::channel();
+    sender
+        .send(Request {
+            method: method.to_string(),
+            params: request.get("params").cloned().unwrap_or(Value::Null),
+            reply,
+        })
+        .map_err(|_| RpcError::failed("the editor is exiting"))?;
+    wake();
+
+    result
+        .recv()
+        .unwrap_or_else(|_| Err(RpcError::failed("the editor dropped the request")))
+}
+
+/// Send a request to the editor listening on `path` and wait for its result
+///
+/// Errors of the editor are formatted like [`RpcError`], with their code
+pub fn call(path: &Path, method: &str, params: Value) -> Result<Value, String> {
+    check_socket_dir(socket_dir(path)).map_err(|err| err.to_string())?;
+
+    let mut stream = UnixStream::connect(path)
+        .map_err(|err| format!("failed to connect to {}: {}", path.display(), err))?;
+
+    let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+    writeln!(stream, "{}", request).map_err(|err| err.to_string())?;
+
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .map_err(|err| err.to_string())?;
+
+    let response = serde_json::from_str::<Value>(&line).map_err(|err| err.to_string())?;
+
+    match response.get("error") {
+        Some(error) => Err(RpcError {
+            code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+                .to_string(),
+        }
+        .to_string()),
+        None => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+    }
+}
+
+/// `path` made absolute to be the same for the client and the editor, which may run in other
+/// directories, with its symbolic links resolved if it exists
+pub fn absolute_path(path: &Path) -> io::Result<PathBuf> {
+    fs::canonicalize(path).or_else(|_| std::path::absolute(path))
+}
+
+/// `$XDG_RUNTIME_DIR/editor.sock`, defaulting to `/tmp/editor-<uid>/editor.sock`
+pub fn socket_path() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| env::temp_dir().join(format!("editor-{}", current_uid())))
+        .join("editor.sock")
+}
+
+fn socket_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Check that `dir` belongs to the user and that no one else can access it
+fn check_socket_dir(dir: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(dir)?;
+
+    if !metadata.is_dir() || metadata.uid() != current_uid() || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} must be a directory only accessible to the user",
+                dir.display()
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: getuid has no preconditions and can't fail
+    unsafe { libc::getuid() }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    /// Send raw `lines` to the server at `path` and get the response to each
+    fn send_lines(path: &Path, lines: &[&str]) -> Vec<Value> {
+        let mut stream = UnixStream::connect(path).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        lines
+            .iter()
+            .map(|line| {
+                writeln!(stream, "{}", line).unwrap();
+
+                let mut response = String::new();
+                reader.read_line(&mut response).unwrap();
+                serde_json::from_str(&response).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn answers_requests_and_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("remote/editor.sock");
+
+        let (woken, wakes) = mpsc::channel();
+        let server = RemoteServer::start(path.clone(), move || woken.send(()).unwrap()).unwrap();
+        assert!(RemoteServer::start(path.clone(), || {}).is_err());
+
+        let client_path = path.clone();
+        let client = thread::spawn(move || {
+            let path = client_path.as_path();
+            let malformed = send_lines(path, &["{", r#"{"jsonrpc": "2.0", "id": 7}"#]);
+
+            (
+                call(path, "echo", json!({"a": 1})),
+                call(path, "fail", Value::Null),
+                call(path, "nothing", Value::Null),
+                malformed,
+            )
+        });
+
+        // Only the well formed requests reach the app, each after a wake up
+        for _ in 0..3 {
+            wakes.recv().unwrap();
+            let request = server.try_recv().unwrap();
+
+            let result = match request.method.as_str() {
+                "echo" => Ok(request.params.clone()),
+                "fail" => Err(RpcError::failed("no can do")),
+                method => Err(RpcError::method_not_found(method)),
+            };
+            request.reply(result);
+        }
+
+        let (echo, fail, unknown, malformed) = client.join().unwrap();
+        assert_eq!(echo, Ok(json!({"a": 1})));
+        assert_eq!(fail, Err("no can do (-32000)".to_string()));
+        assert_eq!(
+            unknown,
+            Err("unknown method `nothing` (-32601)".to_string())
+        );
+        assert_eq!(malformed[0]["error"]["code"], -32700);
+        assert_eq!(malformed[1]["id"], 7);
+        assert_eq!(malformed[1]["error"]["code"], -32600);
+        assert!(server.try_recv().is_none());
+
+        drop(server);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn makes_paths_absolute() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.rs");
+        fs::write(&file, "").unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(
+            absolute_path(Path::new("new.rs")).unwrap(),
+            cwd.join("new.rs")
+        );
+        assert_eq!(
+            absolute_path(&dir.path().join("sub/../a.rs")).unwrap(),
+            absolute_path(&file).unwrap()
+        );
+    }
+
+    #[test]
+    fn creates_a_private_socket_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sockets/editor.sock");
+
+        let server = RemoteServer::start(path.clone(), || {}).unwrap();
+        let mode = fs::metadata(dir.path().join("sockets")).unwrap().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        drop(server);
+
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o777)).unwrap();
+        let shared = dir.path().join("editor.sock");
+        assert!(RemoteServer::start(shared.clone(), || {}).is_err());
+        assert!(call(&shared, "list_buffers", Value::Null)
+            .unwrap_err()
+            .contains("only accessible to the user"));
+    }
+}
@@ -0,0 +1,590 @@
+//! Rhai scripts defining user commands, loaded from the config directory at startup
+//!
+//! Every `*.rhai` file of `$XDG_CONFIG_HOME/editor` (defaulting to `~/.config/editor`) runs once
+//! at startup, in name order. Lines and columns start at 0 in the following API:
+//!
+//! - `register_command(name, description, function)` adds a command to the command bar,
+//!   `function` receives its arguments as an array of strings
+//! - `bind(mode, key, command)` binds a key like `C-u` to a command line in the `normal`,
+//!   `insert`, `selection` or `command` mode
+//! - `line_count()`, `line(index)`, `set_line(index, text)`, `insert_line(index, text)` and
+//!   `remove_line(index)` read and modify the current document, modifying a read-only document
+//!   is an error
+//! - `selection()` gives `[start_column, start_line, end_column, end_line]`,
+//!   `select(start_column, start_line, end_column, end_line)` changes it and `selected_text()`
+//!   gives the selected text
+//! - `action(command)` runs a command line like `move_down 3; undo`, document actions apply
+//!   immediately and the others once the command returns
+//! - `message(text)` displays an info message
+//!
+//! A script running more than a million operations, like an endless loop, is stopped with an
+//! error.
+//!
+//! ```rhai
+//! fn upper(args) {
+//!     let index = selection()[1];
+//!     set_line(index, line(index).to_upper());
+//! }
+//!
+//! register_command("upper", "Upper case the current line", Fn("upper"));
+//! bind("normal", "C-u", "upper");
+//! ```
+
+use std::{
+    cell::RefCell,
+    env, fs, mem,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use editor_action::{register_user_command, Action};
+use editor_document::Document;
+use log::info;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, AST, INT};
+
+pub struct Scripts {
+    engine: Engine,
+    scripts: Vec<AST>,
+    state: Rc<RefCell<State>>,
+}
+
+/// Key binding requested by a script, see [`Scripts::bindings`]
+#[derive(Clone, Debug)]
+pub struct Binding {
+    pub mode: String,
+    pub key: String,
+    /// Command line run by the key
+    pub command: String,
+}
+
+/// Effects of a command on the app, see [`Scripts::run`]
+#[derive(Default)]
+pub struct ScriptOutput {
+    /// Non document actions to handle, with their count
+    pub actions: Vec<(Action, usize)>,
+    pub messages: Vec<String>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Document of the current buffer while a command runs
+    document: Option<Document>,
+    /// Index of the script running at startup
+    loading: usize,
+    commands: Vec<Command>,
+    bindings: Vec<Binding>,
+    output: ScriptOutput,
+}
+
+#[derive(Clone)]
+struct Command {
+    name: String,
+    function: FnPtr,
+    /// Index of the script defining the function
+    script: usize,
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Operations a script can run before being stopped, so that an endless loop can't freeze the
+/// editor
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Depth of nested function calls a script can reach
+const MAX_CALL_LEVELS: usize = 64;
+
+/// Depth of nested expressions in a script, at the top level and in functions
+const MAX_EXPR_DEPTHS: (usize, usize) = (64, 32);
+
+impl Scripts {
+    /// Load the scripts of the config directory, returning the errors of those that failed
+    pub fn load() -> (Self, Vec<String>) {
+        match config_dir() {
+            Some(dir) => Self::load_dir(&dir),
+            None => (Self::empty(), Vec::new()),
+        }
+    }
+
+    /// Load every `*.rhai` file of `dir` in name order
+    pub fn load_dir(dir: &Path) -> (Self, Vec<String>) {
+        let mut scripts = Self::empty();
+        let mut errors = Vec::new();
+
+        let mut paths = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| {
+                        path.extension()
+                            .is_some_and(|extension| extension == "rhai")
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        for path in paths {
+            info!("Loading script {}", path.display());
+
+            if let Err(err) = scripts.load_file(&path) {
+                errors.push(format!("{}: {}", path.display(), err));
+            }
+        }
+
+        (scripts, errors)
+    }
+
+    /// Create without any script, see [`Scripts::load`]
+    pub fn empty() -> Self {
+        let state = Rc::new(RefCell::new(State::default()));
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .set_max_expr_depths(MAX_EXPR_DEPTHS.0, MAX_EXPR_DEPTHS.1);
+
+        register_api(&mut engine, &state);
+
+        Self {
+            engine,
+            scripts: Vec::new(),
+            state,
+        }
+    }
+
+    fn load_file(&mut self, path: &Path) -> ScriptResult<()> {
+        let ast = self.engine.compile_file(path.to_path_buf())?;
+
+        self.state.borrow_mut().loading = self.scripts.len();
+        let result = self.engine.run_ast(&ast);
+        // Keep the script even if it failed, it may have registered commands before
+        self.scripts.push(ast);
+
+        result
+    }
+
+    /// Key bindings requested by the scripts
+    pub fn bindings(&self) -> Vec<Binding> {
+        self.state.borrow().bindings.clone()
+    }
+
+    /// Run the command `name` on `document` as a single undo step
+    pub fn run(
+        &mut self,
+        name: &str,
+        arguments: Vec<String>,
+        document: &mut Document,
+    ) -> Result<ScriptOutput, String> {
+        let command = self
+            .state
+            .borrow()
+            .commands
+            .iter()
+            .find(|command| command.name == name)
+            .cloned()
+            .ok_or_else(|| format!("unknown command `{}`", name))?;
+
+        document.begin_transaction();
+        self.state.borrow_mut().document = Some(mem::replace(document, Document::new_scratch()));
+
+        let arguments = arguments.into_iter().map(Dynamic::from).collect::<Array>();
+        let result = command.function.call::<Dynamic>(
+            &self.engine,
+            &self.scripts[command.script],
+            (arguments,),
+        );
+
+        let mut state = self.state.borrow_mut();
+        if let Some(returned) = state.document.take() {
+            *document = returned;
+        }
+        document.end_transaction();
+        let output = mem::take(&mut state.output);
+
+        result
+            .map(|_| output)
+            .map_err(|err| format!("{}: {}", name, err))
+    }
+}
+
+fn register_api(engine: &mut Engine, state: &Rc<RefCell<State>>) {
+    let s = state.clone();
+    engine.register_fn(
+        "register_command",
+        move |name: &str, description: &str, function: FnPtr| {
+            let mut state = s.borrow_mut();
+            let script = state.loading;
+
+            state.commands.retain(|command| command.name != name);
+            state.commands.push(Command {
+                name: name.to_string(),
+                function,
+                script,
+            });
+            register_user_command(name, description);
+        },
+    );
+
+    let s = state.clone();
+    engine.register_fn("bind", move |mode: &str, key: &str, command: &str| {
+        s.borrow_mut().bindings.push(Binding {
+            mode: mode.to_string(),
+            key: key.to_string(),
+            command: command.to_string(),
+        });
+    });
+
+    let s = state.clone();
+    engine.register_fn("message", move |text: &str| {
+        s.borrow_mut().output.messages.push(text.to_string());
+    });
+
+    let s = state.clone();
+    engine.register_fn("line_count", move || -> ScriptResult<INT> {
+        with_document(&s, |document| document.lines().len() as INT)
+    });
+
+    let s = state.clone();
+    engine.register_fn("line", move |index: INT| -> ScriptResult<String> {
+        let index = to_index(index)?;
+        with_document(&s, |document| {
+            document.get_line(index).unwrap_or_default().to_string()
+        })
+    });
+
+    let s = state.clone();
+    engine.register_fn(
+        "set_line",
+        move |index: INT, text: &str| -> ScriptResult<()> {
+            let index = to_index(index)?;
+            with_editable_document(&s, |document| document.set_line(index, text.to_string()))
+        },
+    );
+
+    let s = state.clone();
+    engine.register_fn(
+        "insert_line",
+        move |index: INT, text: &str| -> ScriptResult<()> {
+            let index = to_index(index)?;
+            with_editable_document(&s, |document| document.insert_line(index, text.to_string()))
+        },
+    );
+
+    let s = state.clone();
+    engine.register_fn("remove_line", move |index: INT| -> ScriptResult<Dynamic> {
+        let index = to_index(index)?;
+        with_editable_document(&s, |document| {
+            document
+                .remove_line(index)
+                .map(Dynamic::from)
+                .unwrap_or(Dynamic::UNIT)
+        })
+    });
+
+    let s = state.clone();
+    engine.register_fn("selection", move || -> ScriptResult<Array> {
+        with_document(&s, |document| {
+            let selection = document.selection();
+            let (start, end) = (selection.start(), selection.end());
+
+            [start.0, start.1, end.0, end.1]
+                .into_iter()
+                .map(|value| Dynamic::from(value as INT))
+                .collect()
+        })
+    });
+
+    let s = state.clone();
+    engine.register_fn(
+        "select",
+        move |start_column: INT,
+              start_line: INT,
+              end_column: INT,
+              end_line: INT|
+              -> ScriptResult<()> {
+            let start = (to_index(start_column)?, to_index(start_line)?);
+            let end = (to_index(end_column)?, to_index(end_line)?);
+            with_document(&s, |document| document.select(start, end))
+        },
+    );
+
+    let s = state.clone();
+    engine.register_fn("selected_text", move || -> ScriptResult<String> {
+        with_document(&s, |document| document.selected_text())
+    });
+
+    let s = state.clone();
+    engine.register_fn("action", move |command: &str| -> ScriptResult<()> {
+        let actions = Action::parse_sequence(command).map_err(|err| err.to_string())?;
+
+        with_document(&s, |document| {
+            for (action, count) in actions {
+                match action {
+                    Action::Document(action) => {
+                        for _ in 0..count {
+                            document.handle_action(action.clone());
+                        }
+                    }
+                    action => s.borrow_mut().output.actions.push((action, count)),
+                }
+            }
+        })
+    });
+}
+
+/// Call `f` with the document of the running command
+fn with_document<T>(state: &RefCell<State>, f: impl FnOnce(&mut Document) -> T) -> ScriptResult<T> {
+    // Taken out of the state so that `f` can borrow it
+    let mut document = state
+        .borrow_mut()
+        .document
+        .take()
+        .ok_or("the document is only available to commands")?;
+
+    let value = f(&mut document);
+    state.borrow_mut().document = Some(document);
+
+    Ok(value)
+}
+
+/// Call `f` with the document of the running command, failing if it is read-only
+fn with_editable_document<T>(
+    state: &RefCell<State>,
+    f: impl FnOnce(&mut Document) -> T,
+) -> ScriptResult<T> {
+    with_document(state, |document| {
+        if document.read_only() {
+            return Err(format!("{} is read-only", document.display_name()).into());
+        }
+        Ok(f(document))
+    })?
+}
+
+fn to_index(value: INT) -> ScriptResult<usize> {
+    usize::try_from(value).map_err(|_| format!("invalid index {}", value).into())
+}
+
+/// `$XDG_CONFIG_HOME/editor`, defaulting to `~/.config/editor`
+fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("editor"))
+}
+
+#[cfg(test)]
+mod tests {
+    use editor_action::DocumentAction;
+
+    use super::*;
+
+    /// Scripts running `source` as if it was loaded from a file
+    fn scripts(source: &str) -> Scripts {
+        let mut scripts = Scripts::empty();
+        let ast = scripts.engine.compile(source).unwrap();
+        scripts.engine.run_ast(&ast).unwrap();
+        scripts.scripts.push(ast);
+        scripts
+    }
+
+    /// Run `body` as a command on a document of `lines`
+    fn run(body: &str, lines: &[&str]) -> (Result<ScriptOutput, String>, Document) {
+        run_on(body, document(lines))
+    }
+
+    fn run_on(body: &str, mut document: Document) -> (Result<ScriptOutput, String>, Document) {
+        let mut scripts = scripts(&format!(
+            "fn test(args) {{ {} }} register_command(\"test\", \"\", Fn(\"test\"));",
+            body
+        ));
+        let result = scripts.run("test", Vec::new(), &mut document);
+        (result, document)
+    }
+
+    fn document(lines: &[&str]) -> Document {
+        let lines = lines.iter().map(|line| line.to_string()).collect();
+        Document::from_lines("test".to_string(), lines)
+    }
+
+    #[test]
+    fn registers_commands_with_their_arguments() {
+        let mut scripts = scripts(
+            r#"
+            fn join(args) { set_line(0, args[0] + args[1]); }
+            register_command("join", "Join the arguments", Fn("join"));
+            "#,
+        );
+        let mut document = document(&["abc"]);
+
+        let arguments = vec!["de".to_string(), "f".to_string()];
+        assert!(scripts.run("join", arguments, &mut document).is_ok());
+        assert_eq!(document.lines(), &["def"]);
+        assert_eq!(
+            scripts
+                .run("missing", Vec::new(), &mut document)
+                .err()
+                .unwrap(),
+            "unknown command `missing`"
+        );
+    }
+
+    #[test]
+    fn binds_keys() {
+        let scripts = scripts(r#"bind("normal", "C-u", "upper 2");"#);
+
+        let bindings = scripts.bindings();
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(
+            (
+                bindings[0].mode.as_str(),
+                bindings[0].key.as_str(),
+                bindings[0].command.as_str()
+            ),
+            ("normal", "C-u", "upper 2")
+        );
+    }
+
+    #[test]
+    fn displays_messages() {
+        let (result, _) = run(r#"message("a"); message("b");"#, &[]);
+
+        assert_eq!(result.unwrap().messages, ["a", "b"]);
+    }
+
+    #[test]
+    fn reads_lines() {
+        let (result, _) = run(
+            r#"message(`${line_count()} ${line(1)} ${line(5)}`);"#,
+            &["abc", "def"],
+        );
+
+        assert_eq!(result.unwrap().messages, ["2 def "]);
+    }
+
+    #[test]
+    fn modifies_lines() {
+        let (result, document) = run(
+            r#"set_line(0, "ABC"); insert_line(1, "new"); message(remove_line(2));"#,
+            &["abc", "def", "ghi"],
+        );
+
+        assert_eq!(result.unwrap().messages, ["def"]);
+        assert_eq!(document.lines(), &["ABC", "new", "ghi"]);
+    }
+
+    #[test]
+    fn removing_a_missing_line_gives_unit() {
+        let (result, document) = run(r#"message(`${remove_line(3) == ()}`);"#, &["abc"]);
+
+        assert_eq!(result.unwrap().messages, ["true"]);
+        assert_eq!(document.lines(), &["abc"]);
+    }
+
+    #[test]
+    fn commands_are_a_single_undo_step() {
+        let (result, mut document) = run(
+            r#"set_line(0, "x"); insert_line(0, "y"); remove_line(2);"#,
+            &["abc", "def", "ghi"],
+        );
+        assert!(result.is_ok());
+        assert_eq!(document.lines(), &["y", "x", "ghi"]);
+
+        document.handle_action(DocumentAction::Undo);
+        assert_eq!(document.lines(), &["abc", "def", "ghi"]);
+    }
+
+    #[test]
+    fn read_only_documents_cant_be_modified() {
+        for body in [
+            r#"set_line(0, "x");"#,
+            r#"insert_line(0, "x");"#,
+            "remove_line(0);",
+        ] {
+            let mut document = document(&["abc"]);
+            document.set_read_only(true);
+
+            let (result, document) = run_on(body, document);
+            let err = result.err().unwrap();
+            assert!(err.contains("[test] is read-only"), "{}", err);
+            assert_eq!(document.lines(), &["abc"]);
+            assert!(!document.dirty());
+        }
+    }
+
+    #[test]
+    fn negative_indices_are_errors() {
+        let (result, document) = run(r#"set_line(-1, "x");"#, &["abc"]);
+
+        assert!(result.err().unwrap().contains("invalid index -1"));
+        assert_eq!(document.lines(), &["abc"]);
+    }
+
+    #[test]
+    fn reads_and_changes_the_selection() {
+        let (result, document) = run(
+            "select(1, 0, 2, 1); message(selected_text()); message(`${selection()}`);",
+            &["abc", "def"],
+        );
+
+        assert_eq!(result.unwrap().messages, ["bc\nde", "[1, 0, 2, 1]"]);
+        assert_eq!(document.selection().start(), (1, 0));
+        assert_eq!(document.selection().end(), (2, 1));
+    }
+
+    #[test]
+    fn runs_actions() {
+        let (result, document) = run(r#"action("move_down 2; toggle_line_numbers");"#, &["a"; 4]);
+
+        let output = result.unwrap();
+        assert_eq!(output.actions.len(), 1);
+        assert_eq!(
+            output.actions[0].0.as_strs(),
+            Action::ToggleLineNumbers.as_strs()
+        );
+        assert_eq!(document.selection().end(), (0, 2));
+
+        let (result, _) = run(r#"action("not_an_action");"#, &[]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn the_document_is_only_available_to_commands() {
+        let scripts = Scripts::empty();
+        let ast = scripts.engine.compile("line_count()").unwrap();
+
+        let err = scripts.engine.run_ast(&ast).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("the document is only available to commands"));
+    }
+
+    #[test]
+    fn endless_loops_are_stopped() {
+        let (result, _) = run("loop {}", &[]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn loads_the_scripts_of_a_directory_in_order() {
+        let dir = env::temp_dir().join(format!("editor_script_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.rhai"), r#"bind("normal", "b", "b");"#).unwrap();
+        fs::write(dir.join("a.rhai"), r#"bind("normal", "a", "a");"#).unwrap();
+        fs::write(dir.join("c.rhai"), "syntax error (").unwrap();
+        fs::write(dir.join("d.txt"), r#"bind("normal", "d", "d");"#).unwrap();
+
+        let (scripts, errors) = Scripts::load_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let keys = scripts
+            .bindings()
+            .into_iter()
+            .map(|binding| binding.key)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["a", "b"]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("c.rhai"));
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    io::{stdout, Stdout, Write},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::{self, MoveTo},
+    event::{self, Event},
+    style::{Color, SetAttributes, SetBackgroundColor, SetForegroundColor, SetUnderlineColor},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, BeginSynchronizedUpdate, Clear, ClearType,
+        EndSynchronizedUpdate, EnterAlternateScreen, LeaveAlternateScreen,
+    },
+    ExecutableCommand, QueueableCommand,
+};
+use glam::U16Vec2;
+use log::error;
+
+use crate::Style;
+
+/// Where a [`Term`](crate::Term) draws and gets its events from
+///
+/// Drawing operations are queued until [`Backend::flush`]
+pub trait Backend {
+    /// Move the cursor used by [`Backend::write`]
+    fn move_to(&mut self, pos: U16Vec2);
+    /// Write at the cursor with the current colors, moving the cursor after the text
+    fn write(&mut self, str: &str);
+    fn set_text_color(&mut self, color: Color);
+    fn set_background_color(&mut self, color: Color);
+    fn set_underline_color(&mut self, color: Color);
+    /// Set the attributes of the next writes, replacing all the previous ones
+    fn set_style(&mut self, style: Style);
+    /// Write spaces to the whole screen
+    fn clear(&mut self);
+    fn flush(&mut self);
+    /// Ask the terminal to hold the display until [`Backend::end_synchronized_update`]
+    /// so a frame doesn't show half drawn
+    fn begin_synchronized_update(&mut self) {}
+    fn end_synchronized_update(&mut self) {}
+    /// Size of the screen in character count
+    fn size(&self) -> U16Vec2;
+    /// Get the next event, waiting for at most `timeout` or forever if `None`
+    ///
+    /// Returns `None` if no event happened before the timeout or a [`Waker`] woke the backend
+    fn read_event(&mut self, timeout: Option<Duration>) -> Option<Result<Event, ()>>;
+    /// Handle to stop [`Backend::read_event`] from another thread, if the backend supports it
+    fn waker(&self) -> Option<Waker> {
+        None
+    }
+}
+
+/// Terminal event, or `None` to wake the reader up
+type Wakeable = Option<Result<Event, ()>>;
+
+/// Wakes a [`Backend`] waiting for an event from another thread, see [`Backend::waker`]
+#[derive(Clone)]
+pub struct Waker(Sender<Wakeable>);
+
+impl Waker {
+    /// Make the current or next [`Backend::read_event`] return `None` right away
+    pub fn wake(&self) {
+        // The backend may have been dropped
+        let _ = self.0.send(None);
+    }
+}
+
+/// Backend drawing to the real terminal through crossterm
+///
+/// Enables `raw mode` and `alternate screen` on creation and disables them on drop,
+/// events are read on a background thread so that a [`Waker`] can interrupt the wait
+pub struct CrosstermBackend {
+    stdout: Stdout,
+    events: Receiver<Wakeable>,
+    sender: Sender<Wakeable>,
+}
+
+impl CrosstermBackend {
+    #[must_use]
+    pub fn new() -> Self {
+        let (sender, events) = mpsc::channel();
+        let mut backend = Self {
+            stdout: stdout(),
+            events,
+            sender: sender.clone(),
+        };
+
+        if let Err(err) = enable_raw_mode() {
+            error!("Failed to enable raw mode, {:?}", err);
+        }
+        if let Err(err) = backend.stdout.queue(EnterAlternateScreen) {
+            error!("Failed to enter alternate screen, {:?}", err);
+        }
+        if let Err(err) = backend.stdout.queue(cursor::Hide) {
+            error!("Failed to hide the terminal cursor, {:?}", err);
+        }
+
+        backend.flush();
+
+        thread::spawn(move || loop {
+            let event = event::read().map_err(|err| {
+                error!("Failed to read terminal event, {:?}", err);
+            });
+
+            if sender.send(Some(event)).is_err() {
+                return;
+            }
+        });
+
+        backend
+    }
+}
+
+impl Default for CrosstermBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backend for CrosstermBackend {
+    fn move_to(&mut self, pos: U16Vec2) {
+        if let Err(err) = self.stdout.queue(MoveTo(pos.x, pos.y)) {
+            error!("Failed to move terminal cursor: {:?}", err);
+        }
+    }
+
+    fn write(&mut self, str: &str) {
+        if let Err(err) = self.stdout.write_all(str.as_bytes()) {
+            error!("Failed to write to terminal, {:?}", err);
+        }
+    }
+
+    fn set_text_color(&mut self, color: Color) {
+        if let Err(err) = self.stdout.queue(SetForegroundColor(color)) {
+            error!("Failed to set text color, {:?}", err);
+        }
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        if let Err(err) = self.stdout.queue(SetBackgroundColor(color)) {
+            error!("Failed to set background color, {:?}", err);
+        }
+    }
+
+    fn set_underline_color(&mut self, color: Color) {
+        if let Err(err) = self.stdout.queue(SetUnderlineColor(color)) {
+            error!("Failed to set underline color, {:?}", err);
+        }
+    }
+
+    fn set_style(&mut self, style: Style) {
+        if let Err(err) = self
+            .stdout
+            .queue(SetAttributes(Style::reset_attributes()))
+            .and_then(|stdout| stdout.queue(SetAttributes(style.attributes())))
+        {
+            error!("Failed to set text style, {:?}", err);
+        }
+    }
+
+    fn clear(&mut self) {
+        if let Err(err) = self.stdout.queue(Clear(ClearType::All)) {
+            error!("Failed to clear terminal screen, {:?}", err);
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Err(err) = self.stdout.flush() {
+            error!("Failed to flush terminal, {:?}", err);
+        };
+    }
+
+    fn begin_synchronized_update(&mut self) {
+        if let Err(err) = self.stdout.queue(BeginSynchronizedUpdate) {
+            error!("Failed to begin synchronized update, {:?}", err);
+        }
+    }
+
+    fn end_synchronized_update(&mut self) {
+        if let Err(err) = self.stdout.queue(EndSynchronizedUpdate) {
+            error!("Failed to end synchronized update, {:?}", err);
+        }
+    }
+
+    fn size(&self) -> U16Vec2 {
+        match crossterm::terminal::size() {
+            Ok(size) => size.into(),
+            Err(err) => {
+                error!("Failed to get terminal size, {:?}", err);
+                (0, 0).into()
+            }
+        }
+    }
+
+    fn read_event(&mut self, timeout: Option<Duration>) -> Option<Result<Event, ()>> {
+        // The sender kept by the backend never disconnects
+        match timeout {
+            Some(timeout) => self.events.recv_timeout(timeout).ok().flatten(),
+            None => self.events.recv().ok().flatten(),
+        }
+    }
+
+    fn waker(&self) -> Option<Waker> {
+        Some(Waker(self.sender.clone()))
+    }
+}
+
+impl Drop for CrosstermBackend {
+    fn drop(&mut self) {
+        if let Err(err) = self.stdout.execute(LeaveAlternateScreen) {
+            error!("Failed to leave alternate screen: {:?}", err);
+        }
+        if let Err(err) = disable_raw_mode() {
+            error!("Failed to disable raw mode: {:?}", err);
+        };
+        if let Err(err) = self.stdout.queue(cursor::Show) {
+            error!("Failed to show the terminal cursor, {:?}", err);
+        }
+    }
+}
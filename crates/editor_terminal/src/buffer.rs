@@ -0,0 +1,157 @@
+use crossterm::style::Color;
+use glam::U16Vec2;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::Style;
+
+/// Column of the screen with the colors and style it is drawn with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cell {
+    /// Grapheme drawn in the cell, empty for the cells covered by the wide grapheme before
+    pub symbol: String,
+    pub text_color: Color,
+    pub background_color: Color,
+    pub underline_color: Color,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: " ".to_string(),
+            text_color: Color::Reset,
+            background_color: Color::Reset,
+            underline_color: Color::Reset,
+            style: Style::new(),
+        }
+    }
+}
+
+/// Grid of [`Cell`]s the size of the terminal
+///
+/// Keeps the colors and style of the next write like a terminal does
+#[derive(Clone, Debug)]
+pub(crate) struct Buffer {
+    size: U16Vec2,
+    cells: Vec<Cell>,
+    pub(crate) text_color: Color,
+    pub(crate) background_color: Color,
+    pub(crate) underline_color: Color,
+    pub(crate) style: Style,
+}
+
+impl Buffer {
+    pub(crate) fn new(size: U16Vec2) -> Self {
+        Self {
+            size,
+            cells: vec![Cell::default(); size.x as usize * size.y as usize],
+            text_color: Color::Reset,
+            background_color: Color::Reset,
+            underline_color: Color::Reset,
+            style: Style::new(),
+        }
+    }
+
+    pub(crate) fn size(&self) -> U16Vec2 {
+        self.size
+    }
+
+    /// Change the size of the grid, clearing it
+    pub(crate) fn resize(&mut self, size: U16Vec2) {
+        self.size = size;
+        self.cells = vec![Cell::default(); size.x as usize * size.y as usize];
+    }
+
+    pub(crate) fn cell(&self, pos: U16Vec2) -> Option<&Cell> {
+        (pos.x < self.size.x && pos.y < self.size.y).then(|| &self.cells[self.index(pos)])
+    }
+
+    /// Write `str` from `pos` with the current colors and style, stopping before the column
+    /// `end` and the edge of the grid
+    ///
+    /// A grapheme takes as many cells as its width, one that doesn't fit before `end`
+    /// is replaced by spaces. Returns the column after the written graphemes
+    pub(crate) fn write(&mut self, mut pos: U16Vec2, str: &str, end: u16) -> u16 {
+        let end = end.min(self.size.x);
+
+        if pos.y >= self.size.y {
+            return pos.x;
+        }
+
+        for grapheme in str.graphemes(true) {
+            if pos.x >= end {
+                break;
+            }
+
+            let (symbol, width) = displayed(grapheme);
+
+            if pos.x as usize + width > end as usize {
+                while pos.x < end {
+                    self.set(pos, " ".to_string());
+                    pos.x += 1;
+                }
+                break;
+            }
+
+            self.set(pos, symbol);
+            for x in 1..width as u16 {
+                self.set(pos + U16Vec2::new(x, 0), String::new());
+            }
+            pos.x += width as u16;
+        }
+
+        pos.x
+    }
+
+    /// Set the symbol of a cell with the current colors and style,
+    /// replacing the rest of the wide graphemes it overwrites by spaces
+    fn set(&mut self, pos: U16Vec2, symbol: String) {
+        let index = self.index(pos);
+
+        if !symbol.is_empty() && self.cells[index].symbol.is_empty() && pos.x > 0 {
+            self.cells[index - 1].symbol = " ".to_string();
+        }
+        if pos.x + 1 < self.size.x && self.cells[index + 1].symbol.is_empty() {
+            self.cells[index + 1].symbol = " ".to_string();
+        }
+
+        self.cells[index] = Cell {
+            symbol,
+            text_color: self.text_color,
+            background_color: self.background_color,
+            underline_color: self.underline_color,
+            style: self.style,
+        };
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.cells.fill(Cell::default());
+    }
+
+    /// Rows of cells, top to bottom
+    pub(crate) fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells
+            .chunks(self.size.x.max(1) as usize)
+            .take(self.size.y as usize)
+    }
+
+    fn index(&self, pos: U16Vec2) -> usize {
+        pos.y as usize * self.size.x as usize + pos.x as usize
+    }
+}
+
+/// Symbol drawn for `grapheme` and its width
+///
+/// Control characters are replaced since the terminal would interpret them and zero width
+/// graphemes are drawn over a space to take a cell like the cursor expects
+fn displayed(grapheme: &str) -> (String, usize) {
+    if grapheme.chars().any(char::is_control) {
+        return ("\u{FFFD}".to_string(), 1);
+    }
+
+    match grapheme.width() {
+        0 => (format!(" {}", grapheme), 1),
+        width => (grapheme.to_string(), width),
+    }
+}
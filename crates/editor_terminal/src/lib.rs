@@ -1,6 +1,10 @@
+mod backend;
+mod buffer;
 mod rect;
 mod slice;
+mod style;
 mod terminal;
+mod virtual_backend;
 
 pub use crossterm::{
     event::{
@@ -10,4 +14,12 @@ pub use crossterm::{
     style::Color,
 };
 
-pub use crate::{rect::TermRect, slice::TermSlice, terminal::Term};
+pub use crate::{
+    backend::{Backend, CrosstermBackend, Waker},
+    buffer::Cell,
+    rect::TermRect,
+    slice::TermSlice,
+    style::{Style, Underline},
+    terminal::Term,
+    virtual_backend::VirtualBackend,
+};
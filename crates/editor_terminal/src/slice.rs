@@ -1,28 +1,21 @@
-use std::io::{stdout, Stdout, Write};
+use std::{cell::RefCell, rc::Rc};
 
-use crossterm::{
-    cursor::MoveTo,
-    style::{Color, SetBackgroundColor, SetForegroundColor, SetUnderlineColor},
-    QueueableCommand,
-};
+use crossterm::style::Color;
 use glam::U16Vec2;
-use log::error;
 
-use crate::TermRect;
+use crate::{buffer::Buffer, Style, TermRect};
 
 /// Represent a rectangular region of terminal
 /// Writing to this slice will be offseted by the position of the rect and restricted by its size
+#[derive(Clone)]
 pub struct TermSlice {
-    stdout: Stdout,
+    buffer: Rc<RefCell<Buffer>>,
     rect: TermRect,
 }
 impl TermSlice {
     #[must_use]
-    pub(crate) fn new(rect: TermRect) -> Self {
-        Self {
-            stdout: stdout(),
-            rect,
-        }
+    pub(crate) fn new(buffer: Rc<RefCell<Buffer>>, rect: TermRect) -> Self {
+        Self { buffer, rect }
     }
 
     #[must_use]
@@ -33,7 +26,7 @@ impl TermSlice {
         rect.pos += self.rect.pos;
 
         Self {
-            stdout: stdout(),
+            buffer: self.buffer.clone(),
             rect,
         }
     }
@@ -53,50 +46,31 @@ impl TermSlice {
             return;
         }
 
-        self.move_to(self.rect.pos + pos);
-        self.write(
-            &str.chars()
-                .take(self.rect.width().saturating_sub(pos.x) as usize)
-                .collect::<String>(),
+        self.buffer.borrow_mut().write(
+            self.rect.pos + pos,
+            str,
+            self.rect.pos.x.saturating_add(self.rect.width()),
         );
     }
-
-    /// Move the terminal cursor to the specified position
-    /// The action will only take effect after flushing the terminal, see [`Term::flush`]
-    fn move_to(&mut self, pos: U16Vec2) {
-        let pos: U16Vec2 = pos.into();
-        if let Err(err) = self.stdout.queue(MoveTo(pos.x, pos.y)) {
-            error!("Failed to move terminal cursor: {:?}", err);
-        }
-    }
-
-    /// Write the string to the current terminal cursor position
-    /// The action will only take effect after flushing the terminal, see [`Term::flush`]
-    fn write(&mut self, str: &str) {
-        if let Err(err) = self.stdout.write_all(str.as_bytes()) {
-            error!("Failed to write to terminal, {:?}", err);
-        }
-    }
 }
 
 impl TermSlice {
     /// Set the text color the next write, see [`Term::write_to`]
     pub fn set_text_color(&mut self, color: Color) {
-        if let Err(err) = self.stdout.queue(SetForegroundColor(color)) {
-            error!("Failed to set text color, {:?}", err);
-        }
+        self.buffer.borrow_mut().text_color = color;
     }
     /// Set the background color the next write, see [`Term::write_to`]
     pub fn set_background_color(&mut self, color: Color) {
-        if let Err(err) = self.stdout.queue(SetBackgroundColor(color)) {
-            error!("Failed to set background color, {:?}", err);
-        }
+        self.buffer.borrow_mut().background_color = color;
     }
     /// Set the underline color the next write, see [`Term::write_to`]
     pub fn set_underline_color(&mut self, color: Color) {
-        if let Err(err) = self.stdout.queue(SetUnderlineColor(color)) {
-            error!("Failed to set background color, {:?}", err);
-        }
+        self.buffer.borrow_mut().underline_color = color;
+    }
+
+    /// Set the style of the next writes, see [`Term::write_to`]
+    pub fn set_style(&mut self, style: Style) {
+        self.buffer.borrow_mut().style = style;
     }
 
     /// Reset the text color to the default one for the next write, see [`Term::write_to`]
@@ -111,13 +85,8 @@ impl TermSlice {
     pub fn reset_underline_color(&mut self) {
         self.set_underline_color(Color::Reset);
     }
-}
-
-impl Clone for TermSlice {
-    fn clone(&self) -> Self {
-        Self {
-            stdout: stdout(),
-            rect: self.rect,
-        }
+    /// Reset the style to one without attributes for the next write, see [`Term::write_to`]
+    pub fn reset_style(&mut self) {
+        self.set_style(Style::new());
     }
 }
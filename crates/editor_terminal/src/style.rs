@@ -0,0 +1,140 @@
+use crossterm::style::{Attribute, Attributes};
+
+/// Line drawn under the text of a [`Style`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Underline {
+    #[default]
+    None,
+    Single,
+    /// Wavy line, usually used for diagnostics
+    Curly,
+    Dotted,
+}
+
+/// Text attributes of a write, on top of its colors
+///
+/// # Examples:
+/// ```
+/// # use editor_terminal::{Style, Underline};
+/// let error = Style::new().bold().underline(Underline::Curly);
+///
+/// assert!(error.bold);
+/// assert_eq!(error.underline, Underline::Curly);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: Underline,
+    pub strikethrough: bool,
+    /// Swap the text and background colors
+    pub reverse: bool,
+}
+
+impl Style {
+    /// Style without any attribute
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            bold: false,
+            dim: false,
+            italic: false,
+            underline: Underline::None,
+            strikethrough: false,
+            reverse: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    #[must_use]
+    pub const fn dim(mut self) -> Self {
+        self.dim = true;
+        self
+    }
+
+    #[must_use]
+    pub const fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    #[must_use]
+    pub const fn underline(mut self, underline: Underline) -> Self {
+        self.underline = underline;
+        self
+    }
+
+    #[must_use]
+    pub const fn strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    #[must_use]
+    pub const fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+
+    /// Add the attributes of `other` to this style, its underline replacing this one if any
+    #[must_use]
+    pub fn patch(self, other: Self) -> Self {
+        Self {
+            bold: self.bold || other.bold,
+            dim: self.dim || other.dim,
+            italic: self.italic || other.italic,
+            underline: match other.underline {
+                Underline::None => self.underline,
+                underline => underline,
+            },
+            strikethrough: self.strikethrough || other.strikethrough,
+            reverse: self.reverse || other.reverse,
+        }
+    }
+
+    /// Attributes turning off everything a style can turn on
+    ///
+    /// Used before [`Style::attributes`] to set a style whatever the previous one was,
+    /// since [`Attribute::Reset`] would also reset the colors
+    pub(crate) fn reset_attributes() -> Attributes {
+        Attributes::from(
+            [
+                Attribute::NormalIntensity,
+                Attribute::NoItalic,
+                Attribute::NoUnderline,
+                Attribute::NotCrossedOut,
+                Attribute::NoReverse,
+            ]
+            .as_slice(),
+        )
+    }
+
+    /// Attributes turned on by this style
+    pub(crate) fn attributes(self) -> Attributes {
+        let mut attributes = Attributes::default();
+
+        let enabled = [
+            (self.bold, Attribute::Bold),
+            (self.dim, Attribute::Dim),
+            (self.italic, Attribute::Italic),
+            (self.underline == Underline::Single, Attribute::Underlined),
+            (self.underline == Underline::Curly, Attribute::Undercurled),
+            (self.underline == Underline::Dotted, Attribute::Underdotted),
+            (self.strikethrough, Attribute::CrossedOut),
+            (self.reverse, Attribute::Reverse),
+        ];
+        for (enabled, attribute) in enabled {
+            if enabled {
+                attributes.set(attribute);
+            }
+        }
+
+        attributes
+    }
+}
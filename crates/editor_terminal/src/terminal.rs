@@ -1,30 +1,25 @@
-use std::{
-    io::{stdout, Stdout, Write},
-    time::Duration,
-};
+use std::{cell::RefCell, rc::Rc, time::Duration};
 
-use crossterm::{
-    cursor,
-    event::{self, Event},
-    terminal::{
-        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
-        LeaveAlternateScreen,
-    },
-    ExecutableCommand, QueueableCommand,
-};
+use crossterm::{event::Event, style::Color};
 use glam::U16Vec2;
-use log::error;
 
-use crate::{TermRect, TermSlice};
+use crate::{
+    buffer::Buffer, Backend, Cell, CrosstermBackend, Style, TermRect, TermSlice, VirtualBackend,
+    Waker,
+};
 
 /// Exposes some terminal apis for user interface purposes
 ///
 /// Writing to the terminal is possible through [`TermSlice`], see [`Term::slice`]
 ///
-/// Enables `raw mode` and `alternate screen` on creation and disables them on drop
+/// Drawing and events go through a [`Backend`], the real terminal for [`Term::new`]
+/// or an in-memory screen for [`Term::headless`]
+///
+/// Slices write to a back buffer, [`Term::flush`] only sends the cells that changed
+/// since the previous flush
 ///
 /// # Examples:
-/// ```
+/// ```no_run
 /// # use editor_terminal::{Term, Event, KeyCode, KeyModifiers};
 /// let mut term = Term::new();
 ///
@@ -40,38 +35,43 @@ use crate::{TermRect, TermSlice};
 /// }
 /// ```
 pub struct Term {
-    stdout: Stdout,
+    backend: Rc<RefCell<dyn Backend>>,
     size: U16Vec2,
+    /// What is displayed by the backend
+    front: Buffer,
+    /// What will be displayed on the next flush
+    back: Rc<RefCell<Buffer>>,
+    /// The backend screen needs to be cleared and fully redrawn, after a resize
+    invalidated: bool,
 }
 
 impl Term {
     /// Create a new `Terminal` enabling terminal `raw mode` and `alternate screen`
     #[must_use]
     pub fn new() -> Self {
-        let mut term = Self {
-            stdout: stdout(),
-            size: match crossterm::terminal::size() {
-                Ok(size) => size.into(),
-                Err(err) => {
-                    error!("Failed to get terminal size, {:?}", err);
-                    (0, 0).into()
-                }
-            },
-        };
+        Self::with_backend(Rc::new(RefCell::new(CrosstermBackend::new())))
+    }
 
-        if let Err(err) = enable_raw_mode() {
-            error!("Failed to enable raw mode, {:?}", err);
-        }
-        if let Err(err) = term.stdout.queue(EnterAlternateScreen) {
-            error!("Failed to enter alternate screen, {:?}", err);
-        }
-        if let Err(err) = term.stdout.queue(cursor::Hide) {
-            error!("Failed to hide the terminal cursor, {:?}", err);
-        }
+    /// Create a terminal drawing to an in-memory screen of `size`,
+    /// also returned to inspect it and queue events
+    #[must_use]
+    pub fn headless(size: impl Into<U16Vec2>) -> (Self, Rc<RefCell<VirtualBackend>>) {
+        let backend = Rc::new(RefCell::new(VirtualBackend::new(size)));
 
-        term.flush();
+        (Self::with_backend(backend.clone()), backend)
+    }
+
+    #[must_use]
+    pub fn with_backend(backend: Rc<RefCell<dyn Backend>>) -> Self {
+        let size = backend.borrow().size();
 
-        term
+        Self {
+            backend,
+            size,
+            front: Buffer::new(size),
+            back: Rc::new(RefCell::new(Buffer::new(size))),
+            invalidated: true,
+        }
     }
 
     /// Create a slice over a portion of the terminal
@@ -81,15 +81,71 @@ impl Term {
     /// See [`TermSlice`]
     #[must_use]
     pub fn slice(&mut self, rect: TermRect) -> TermSlice {
-        TermSlice::new(rect)
+        TermSlice::new(self.back.clone(), rect)
     }
 
     /// Flush the terminal having the effect of displaying
-    /// the commands executed since the last flush
+    /// the writes made since the last flush
+    ///
+    /// Only the cells that differ from the displayed ones are sent, consecutive cells of a row
+    /// are written at once and colors and style are only set when they change
     pub fn flush(&mut self) {
-        if let Err(err) = self.stdout.flush() {
-            error!("Failed to flush terminal, {:?}", err);
-        };
+        let back = self.back.borrow();
+        let mut backend = self.backend.borrow_mut();
+
+        backend.begin_synchronized_update();
+
+        if self.invalidated {
+            backend.set_text_color(Color::Reset);
+            backend.set_background_color(Color::Reset);
+            backend.set_underline_color(Color::Reset);
+            backend.set_style(Style::new());
+            backend.clear();
+            self.front = Buffer::new(back.size());
+            self.invalidated = false;
+        }
+
+        // Unknown at the start of a flush, some other program could have changed them
+        let mut pen: Option<Cell> = None;
+        let mut run = String::new();
+
+        for (y, (row, front_row)) in back.rows().zip(self.front.rows()).enumerate() {
+            let mut run_start = None;
+
+            for (x, (cell, front_cell)) in row.iter().zip(front_row).enumerate() {
+                let unchanged = cell == front_cell;
+                let repen = !unchanged && pen.as_ref().is_none_or(|pen| !same_pen(pen, cell));
+
+                if unchanged || repen {
+                    if let Some(start) = run_start.take() {
+                        backend.move_to((start, y as u16).into());
+                        backend.write(&run);
+                        run.clear();
+                    }
+                }
+                if unchanged {
+                    continue;
+                }
+                if repen {
+                    set_pen(&mut *backend, pen.as_ref(), cell);
+                    pen = Some(cell.clone());
+                }
+
+                run_start.get_or_insert(x as u16);
+                run.push_str(&cell.symbol);
+            }
+
+            if let Some(start) = run_start {
+                backend.move_to((start, y as u16).into());
+                backend.write(&run);
+                run.clear();
+            }
+        }
+
+        backend.end_synchronized_update();
+        backend.flush();
+
+        self.front = back.clone();
     }
 
     /// Get the size of the terminal in character count
@@ -103,9 +159,31 @@ impl Term {
     /// Clear the terminal screen effectively writing spaces to all chars of the terminal
     /// The action will only take effect after flushing the terminal, see [`Term::flush`]
     pub fn clear(&mut self) {
-        if let Err(err) = self.stdout.queue(Clear(ClearType::All)) {
-            error!("Failed to clear terminal screen, {:?}", err);
-        }
+        self.back.borrow_mut().clear();
+    }
+}
+
+/// Whether `a` and `b` are drawn with the same colors and style
+fn same_pen(a: &Cell, b: &Cell) -> bool {
+    a.text_color == b.text_color
+        && a.background_color == b.background_color
+        && a.underline_color == b.underline_color
+        && a.style == b.style
+}
+
+/// Set the colors and style of `cell` on `backend`, skipping the ones already set from `current`
+fn set_pen(backend: &mut dyn Backend, current: Option<&Cell>, cell: &Cell) {
+    if current.is_none_or(|current| current.text_color != cell.text_color) {
+        backend.set_text_color(cell.text_color);
+    }
+    if current.is_none_or(|current| current.background_color != cell.background_color) {
+        backend.set_background_color(cell.background_color);
+    }
+    if current.is_none_or(|current| current.underline_color != cell.underline_color) {
+        backend.set_underline_color(cell.underline_color);
+    }
+    if current.is_none_or(|current| current.style != cell.style) {
+        backend.set_style(cell.style);
     }
 }
 
@@ -113,53 +191,148 @@ impl Term {
     /// Get a terminal event if available
     #[must_use]
     pub fn event(&mut self) -> Option<Result<Event, ()>> {
-        self.event_available().then(|| self.wait_for_event())
+        self.wait_for_event_timeout(Duration::ZERO)
     }
 
-    /// Wait for the next terminal event and return it
+    /// Wait for the next terminal event and return it, `Err` if the terminal was woken
     #[must_use]
     pub fn wait_for_event(&mut self) -> Result<Event, ()> {
-        match event::read() {
-            Ok(event) => {
-                self.process_event(&event);
-                Ok(event)
-            }
-            Err(err) => {
-                error!("Failed to read terminal event, {:?}", err);
-                Err(())
-            }
-        }
+        let event = self
+            .backend
+            .borrow_mut()
+            .read_event(None)
+            .unwrap_or(Err(()));
+        self.process_event(&event);
+        event
     }
 
+    /// Wait for the next terminal event for at most `timeout`
+    ///
+    /// Returns `None` if no event happened before the timeout
     #[must_use]
-    fn event_available(&mut self) -> bool {
-        match event::poll(Duration::ZERO) {
-            Ok(event_available) => event_available,
-            Err(err) => {
-                error!("Failed to poll terminal event, {:?}", err);
-                false
-            }
-        }
+    pub fn wait_for_event_timeout(&mut self, timeout: Duration) -> Option<Result<Event, ()>> {
+        self.wait(Some(timeout))
+    }
+
+    /// Wait for the next terminal event for at most `timeout` or forever if `None`
+    ///
+    /// Returns `None` if no event happened before the timeout or a [`Waker`] woke the terminal
+    #[must_use]
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Option<Result<Event, ()>> {
+        let event = self.backend.borrow_mut().read_event(timeout)?;
+        self.process_event(&event);
+        Some(event)
+    }
+
+    /// Handle to interrupt [`Term::wait`] from another thread, `None` if the backend can't be
+    /// woken
+    #[must_use]
+    pub fn waker(&self) -> Option<Waker> {
+        self.backend.borrow().waker()
     }
 
-    fn process_event(&mut self, event: &Event) {
+    fn process_event(&mut self, event: &Result<Event, ()>) {
         match event {
-            Event::Resize(x, y) => self.size = (*x, *y).into(),
+            Ok(Event::Resize(x, y)) => {
+                self.size = (*x, *y).into();
+                self.back.borrow_mut().resize(self.size);
+                self.invalidated = true;
+            }
             _ => {}
         };
     }
 }
 
-impl Drop for Term {
-    fn drop(&mut self) {
-        if let Err(err) = self.stdout.execute(LeaveAlternateScreen) {
-            error!("Failed to leave alternate screen: {:?}", err);
-        }
-        if let Err(err) = disable_raw_mode() {
-            error!("Failed to disable raw mode: {:?}", err);
-        };
-        if let Err(err) = self.stdout.queue(cursor::Show) {
-            error!("Failed to show the terminal cursor, {:?}", err);
-        }
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Style, Term, TermRect, Underline};
+
+    #[test]
+    fn flush_only_writes_changes() {
+        let (mut term, screen) = Term::headless((8, 2));
+        let rect = TermRect::new((0, 0), (8, 2));
+
+        term.slice(rect).write_to((0, 0), "hello");
+        term.flush();
+        assert_eq!(screen.borrow().written(), 5);
+
+        term.slice(rect).write_to((0, 0), "hello");
+        term.flush();
+        assert_eq!(screen.borrow().written(), 5);
+
+        let mut slice = term.slice(rect);
+        slice.write_to((0, 0), "help");
+        slice.set_text_color(Color::Red);
+        slice.write_to((0, 1), "x");
+        term.flush();
+
+        let screen = screen.borrow();
+        assert_eq!(screen.written(), 7);
+        assert_eq!(screen.lines(), ["helpo", "x"]);
+        assert_eq!(screen.cell((0, 1)).unwrap().text_color, Color::Red);
+    }
+
+    #[test]
+    fn styles_are_kept_per_span() {
+        let (mut term, screen) = Term::headless((8, 1));
+        let bold = Style::new().bold();
+
+        let mut slice = term.slice(TermRect::new((0, 0), (8, 1)));
+        slice.write_to((0, 0), "a");
+        slice.set_style(bold);
+        slice.write_to((1, 0), "b");
+        slice.set_style(Style::new().italic().underline(Underline::Curly));
+        slice.write_to((2, 0), "c");
+        slice.reset_style();
+        slice.write_to((3, 0), "d");
+        term.flush();
+
+        let screen = screen.borrow();
+        assert_eq!(screen.cell((0, 0)).unwrap().style, Style::new());
+        assert_eq!(screen.cell((1, 0)).unwrap().style, bold);
+        assert_eq!(
+            screen.cell((2, 0)).unwrap().style.underline,
+            Underline::Curly
+        );
+        assert_eq!(screen.cell((3, 0)).unwrap().style, Style::new());
+    }
+
+    #[test]
+    fn wide_graphemes_take_their_width() {
+        let (mut term, screen) = Term::headless((6, 2));
+
+        let mut slice = term.slice(TermRect::new((0, 0), (4, 2)));
+        slice.write_to((0, 0), "a漢e\u{301}b");
+        slice.write_to((0, 1), "ab漢");
+        slice.write_to((1, 1), "漢");
+        term.flush();
+
+        assert_eq!(screen.borrow().lines(), ["a漢e\u{301}", "a漢"]);
+        assert_eq!(screen.borrow().cell((2, 0)).unwrap().symbol, "");
+        assert_eq!(screen.borrow().cell((3, 1)).unwrap().symbol, " ");
+
+        // Overwriting half of a wide grapheme removes it, control characters are replaced
+        term.slice(TermRect::new((0, 0), (6, 2)))
+            .write_to((2, 1), "x\t");
+        term.flush();
+        assert_eq!(screen.borrow().lines()[1], "a x\u{FFFD}");
+    }
+
+    #[test]
+    fn resize_redraws_everything() {
+        let (mut term, screen) = Term::headless((4, 1));
+
+        term.slice(TermRect::new((0, 0), (4, 1)))
+            .write_to((0, 0), "ab");
+        term.flush();
+
+        screen.borrow_mut().resize((3, 2));
+        let _ = term.event();
+        term.slice(TermRect::new((0, 0), (3, 2)))
+            .write_to((0, 1), "cd");
+        term.flush();
+
+        assert_eq!(screen.borrow().lines(), ["", "cd"]);
+        assert_eq!(term.size(), (3, 2).into());
     }
 }
@@ -0,0 +1,131 @@
+use std::{collections::VecDeque, time::Duration};
+
+use crossterm::{event::Event, style::Color};
+use glam::U16Vec2;
+
+use crate::{buffer::Buffer, Backend, Cell, Style};
+
+/// In-memory backend for headless runs and tests
+///
+/// Writes go to a grid of [`Cell`]s and events come from a queue filled with
+/// [`VirtualBackend::push_event`]
+pub struct VirtualBackend {
+    screen: Buffer,
+    cursor: U16Vec2,
+    written: usize,
+    events: VecDeque<Event>,
+}
+
+impl VirtualBackend {
+    #[must_use]
+    pub fn new(size: impl Into<U16Vec2>) -> Self {
+        Self {
+            screen: Buffer::new(size.into()),
+            cursor: U16Vec2::ZERO,
+            written: 0,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Queue an event returned by the next [`Backend::read_event`]
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+
+    /// Change the size of the screen, clearing it and queuing the resize event
+    pub fn resize(&mut self, size: impl Into<U16Vec2>) {
+        let size = size.into();
+
+        self.screen.resize(size);
+        self.push_event(Event::Resize(size.x, size.y));
+    }
+
+    #[must_use]
+    pub fn cell(&self, pos: impl Into<U16Vec2>) -> Option<Cell> {
+        self.screen.cell(pos.into()).cloned()
+    }
+
+    /// Symbols of each row of the screen, trailing spaces removed
+    #[must_use]
+    pub fn lines(&self) -> Vec<String> {
+        self.screen
+            .rows()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.symbol.as_str())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// Number of characters written since the creation of the backend
+    #[must_use]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+}
+
+impl Backend for VirtualBackend {
+    fn move_to(&mut self, pos: U16Vec2) {
+        self.cursor = pos;
+    }
+
+    fn write(&mut self, str: &str) {
+        self.cursor.x = self.screen.write(self.cursor, str, u16::MAX);
+        self.written += str.chars().count();
+    }
+
+    fn set_text_color(&mut self, color: Color) {
+        self.screen.text_color = color;
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.screen.background_color = color;
+    }
+
+    fn set_underline_color(&mut self, color: Color) {
+        self.screen.underline_color = color;
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.screen.style = style;
+    }
+
+    fn clear(&mut self) {
+        self.screen.clear();
+    }
+
+    fn flush(&mut self) {}
+
+    fn size(&self) -> U16Vec2 {
+        self.screen.size()
+    }
+
+    /// Pop the next queued event, never waiting since nothing could queue one meanwhile
+    fn read_event(&mut self, _timeout: Option<Duration>) -> Option<Result<Event, ()>> {
+        self.events.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Term, TermRect};
+
+    #[test]
+    fn slices_clip_writes() {
+        let (mut term, screen) = Term::headless((6, 2));
+
+        let mut slice = term.slice(TermRect::new((2, 1), (3, 1)));
+        slice.set_text_color(Color::Red);
+        slice.write_to((0, 0), "abcdef");
+        slice.write_to((0, 1), "below");
+        term.flush();
+
+        let screen = screen.borrow();
+        assert_eq!(screen.lines(), ["", "  abc"]);
+        assert_eq!(screen.cell((2, 1)).unwrap().text_color, Color::Red);
+        assert_eq!(screen.cell((5, 1)).unwrap().symbol, " ");
+    }
+}
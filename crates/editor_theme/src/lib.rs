@@ -1,4 +1,4 @@
-use editor_terminal::Color;
+use editor_terminal::{Color, Style};
 
 pub struct Theme {
     pub cursor: Color,
@@ -6,13 +6,39 @@ pub struct Theme {
     pub code_text: Color,
     pub code_info_background: Color,
     pub code_info_text: Color,
+    pub code_info_mode_style: Style,
     pub gutter_background: Color,
     pub gutter_line: Color,
     pub gutter_current_line: Color,
+    pub gutter_current_line_style: Style,
+    /// Marker of the closed folds in the gutter and their summary after their first line
+    pub fold_summary: Color,
+    pub git_added: Color,
+    pub git_modified: Color,
+    pub git_removed: Color,
+    /// Background of the marker lines of a merge conflict
+    pub conflict_marker: Color,
+    /// Background of the versions of a merge conflict
+    pub conflict_ours: Color,
+    pub conflict_base: Color,
+    pub conflict_theirs: Color,
+    /// Background of the lines of a side of a diff view missing on the other side
+    pub diff_removed_background: Color,
+    pub diff_added_background: Color,
+    /// Background of the chars changed inside a line of a side of a diff view
+    pub diff_removed_inline: Color,
+    pub diff_added_inline: Color,
+    /// Rows standing for the lines of the other side of a diff view
+    pub diff_filler: Color,
     pub command_bar_background: Color,
     pub command_bar_text: Color,
     pub command_suggestion_background: Color,
     pub command_suggestion_text: Color,
+    pub command_suggestion_selected_background: Color,
+    pub command_suggestion_selected_text: Color,
+    pub message_info: Color,
+    pub message_warning: Color,
+    pub message_error: Color,
 }
 impl Default for Theme {
     fn default() -> Self {
@@ -36,6 +62,21 @@ impl Default for Theme {
             g: 255,
             b: 255,
         };
+        let yellow = Color::Rgb {
+            r: 229,
+            g: 192,
+            b: 123,
+        };
+        let green = Color::Rgb {
+            r: 152,
+            g: 195,
+            b: 121,
+        };
+        let red = Color::Rgb {
+            r: 224,
+            g: 108,
+            b: 117,
+        };
 
         Self {
             cursor: white,
@@ -43,13 +84,61 @@ impl Default for Theme {
             code_text: white,
             code_info_background: gray,
             code_info_text: white,
+            code_info_mode_style: Style::new().bold(),
             gutter_background: dark_gray,
             gutter_line: light_gray,
             gutter_current_line: white,
+            gutter_current_line_style: Style::new().bold(),
+            fold_summary: light_gray,
+            git_added: green,
+            git_modified: yellow,
+            git_removed: red,
+            conflict_marker: gray,
+            conflict_ours: Color::Rgb {
+                r: 35,
+                g: 58,
+                b: 42,
+            },
+            conflict_base: Color::Rgb {
+                r: 58,
+                g: 52,
+                b: 35,
+            },
+            conflict_theirs: Color::Rgb {
+                r: 33,
+                g: 47,
+                b: 70,
+            },
+            diff_removed_background: Color::Rgb {
+                r: 63,
+                g: 36,
+                b: 40,
+            },
+            diff_added_background: Color::Rgb {
+                r: 35,
+                g: 58,
+                b: 42,
+            },
+            diff_removed_inline: Color::Rgb {
+                r: 120,
+                g: 55,
+                b: 62,
+            },
+            diff_added_inline: Color::Rgb {
+                r: 62,
+                g: 105,
+                b: 70,
+            },
+            diff_filler: gray,
             command_bar_background: dark_gray,
             command_bar_text: white,
             command_suggestion_background: gray,
             command_suggestion_text: white,
+            command_suggestion_selected_background: light_gray,
+            command_suggestion_selected_text: dark_gray,
+            message_info: white,
+            message_warning: yellow,
+            message_error: red,
         }
     }
 }
@@ -1,16 +1,41 @@
 mod logger;
+mod remote;
+
+use std::{env, path::PathBuf, process::ExitCode};
 
 use log::info;
 use logger::setup_logger;
 
 use editor_app::App;
 
-fn main() {
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some("--remote") {
+        return remote::run_remote(&args[1..]);
+    }
+
+    let diff = match args.first().map(String::as_str) {
+        Some("-d") => match &args[1..] {
+            [left, right] => Some((PathBuf::from(left), PathBuf::from(right))),
+            _ => {
+                eprintln!("usage: editor -d <left> <right>");
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => None,
+    };
+
     setup_logger();
 
     info!("This file is the log file");
     info!("I choosed to be opened by default because why not");
     info!("hjkl or arrow keys to move and enjoy playing around for 20 seconds and then be bored");
 
-    App::new().run();
+    let mut app = App::new();
+    if let Some((left, right)) = diff {
+        app.diff_files(left, right);
+    }
+    app.run();
+
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,84 @@
+use std::{path::Path, process::ExitCode};
+
+use serde_json::{json, Value};
+
+const USAGE: &str = "usage: editor --remote <method> [arguments]
+
+methods:
+    open <path>[:<line>]
+    goto <line>
+    run_action <command>
+    get_buffer_text [path]
+    list_buffers
+    <other method> [json params]";
+
+/// Send the request described by `args` to the running editor and print its result
+pub fn run_remote(args: &[String]) -> ExitCode {
+    let Some((method, args)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let params = match params(method, args) {
+        Ok(params) => params,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match editor_remote::call(&editor_remote::socket_path(), method, params) {
+        Ok(Value::Null) => ExitCode::SUCCESS,
+        Ok(Value::String(text)) => {
+            println!("{}", text);
+            ExitCode::SUCCESS
+        }
+        Ok(result) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&result).unwrap_or_default()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("editor: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn params(method: &str, args: &[String]) -> Result<Value, String> {
+    match (method, args) {
+        ("open", [path]) => {
+            let (path, line) = match path
+                .rsplit_once(':')
+                .and_then(|(path, line)| Some((path, line.parse::<usize>().ok()?)))
+            {
+                Some((path, line)) => (path, Some(line)),
+                None => (path.as_str(), None),
+            };
+
+            // Relative to the directory of the client, not the one of the editor
+            let path = editor_remote::absolute_path(Path::new(path))
+                .map_err(|err| format!("invalid path `{}`: {}", path, err))?;
+
+            Ok(match line {
+                Some(line) => json!({"path": path, "line": line}),
+                None => json!({ "path": path }),
+            })
+        }
+        ("goto", [line]) => line
+            .parse::<usize>()
+            .map(|line| json!({ "line": line }))
+            .map_err(|_| format!("invalid line `{}`", line)),
+        ("run_action", args) if !args.is_empty() => Ok(json!({"command": args.join(" ")})),
+        ("get_buffer_text", []) | ("list_buffers", []) => Ok(Value::Null),
+        ("get_buffer_text", [path]) => Ok(json!({ "path": path })),
+        ("open" | "goto" | "run_action" | "get_buffer_text" | "list_buffers", _) => {
+            Err(format!("invalid arguments for {}", method))
+        }
+        (_, []) => Ok(Value::Null),
+        (_, [params]) => serde_json::from_str(params).map_err(|err| err.to_string()),
+        _ => Err(format!("invalid arguments for {}", method)),
+    }
+}